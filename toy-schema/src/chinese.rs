@@ -0,0 +1,57 @@
+use serde::{Deserialize, Serialize};
+use speedy::{Readable, Writable};
+
+/// 中文字形：简体、繁体以及台湾、香港地区用词
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub enum ChineseVariant {
+    Simplified,
+    Traditional,
+    Taiwan,
+    HongKong,
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ConvertReq {
+    pub text: String,
+    pub target: ChineseVariant,
+    /// 是否同时规范标点符号与中英文间距
+    pub normalize_punctuation: bool,
+    /// 原文所属地区，为台湾或香港时先把地区用词还原为标准用词；未指定时不还原
+    #[speedy(default_on_eof)]
+    #[serde(default)]
+    pub source: Option<ChineseVariant>,
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ConvertRes {
+    pub text: String,
+}
+
+/// 拼音声调的标注方式
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub enum ToneStyle {
+    /// 声调符号，如`hàn`
+    Mark,
+    /// 数字声调，如`han4`
+    Number,
+}
+
+/// 拼音标注，只覆盖常用字和少量多音词，词表之外的汉字不注音
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct PinyinReq {
+    pub text: String,
+    pub style: ToneStyle,
+}
+
+/// 注音结果中的一段：汉字附带拼音，其他文字原样保留
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Ruby {
+    pub text: String,
+    pub pinyin: Option<String>,
+}
@@ -0,0 +1,79 @@
+use serde::{Deserialize, Serialize};
+use speedy::{Readable, Writable};
+
+/// 重新加载配置后发生变化的一项，密码等敏感项不返回原值
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ConfigChange {
+    /// 配置项的路径，如`log.level`
+    pub key: String,
+    /// 原值，新增的配置项为空
+    pub old: String,
+    /// 新值，删除的配置项为空
+    pub new: String,
+    /// 是否已经生效，否则需要重启服务
+    pub applied: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ReloadRes {
+    pub changes: Vec<ConfigChange>,
+}
+
+/// 配置项的类型，决定编辑时的输入方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum ConfigKind {
+    Bool,
+    Integer,
+    Float,
+    Text,
+}
+
+/// 可在设置页编辑的一个配置项
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ConfigField {
+    /// 配置项的路径，如`web.rate_limit.ip_burst`
+    pub key: String,
+    pub kind: ConfigKind,
+    /// 当前值，字符串不带引号；敏感项不返回，保存时为空表示不修改
+    pub value: String,
+    #[serde(default)]
+    pub secret: bool,
+    /// 被TOYS_开头的环境变量覆盖，修改配置文件不会生效
+    #[serde(default)]
+    pub overridden: bool,
+}
+
+/// 保存配置前自动备份的一个历史版本
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ConfigVersion {
+    pub id: String,
+    /// 备份时间（UTC）
+    pub saved_at: String,
+}
+
+/// 设置页展示的配置和历史版本，新的版本在前
+#[derive(Debug, Clone, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ConfigView {
+    pub fields: Vec<ConfigField>,
+    pub versions: Vec<ConfigVersion>,
+}
+
+/// 只需提交修改过的配置项
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct SaveConfigReq {
+    pub fields: Vec<ConfigField>,
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RollbackConfigReq {
+    pub id: String,
+}
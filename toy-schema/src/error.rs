@@ -0,0 +1,39 @@
+use serde::{Deserialize, Serialize};
+use speedy::{Readable, Writable};
+
+/// 接口出错时的响应体：code供程序判断，message展示给用户
+#[derive(Debug, Clone, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ApiError {
+    pub code: String,
+    pub message: String,
+    /// 具体到字段的错误，如注册时邮箱格式有误、邮箱已被注册
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl ToString, message: impl ToString) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl ApiError {
+    /// 某个字段的错误信息
+    pub fn field(&self, field: &str) -> Option<&str> {
+        self.details
+            .iter()
+            .find(|d| d.field == field)
+            .map(|d| d.message.as_str())
+    }
+}
@@ -1 +1,6 @@
+pub mod chinese;
+pub mod config;
+pub mod error;
+pub mod protocol;
 pub mod sign;
+pub mod sudoku;
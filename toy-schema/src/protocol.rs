@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+use speedy::{Readable, Writable};
+
+/// 请求和响应都带上的协议版本头
+pub const PROTOCOL_HEADER: &str = "x-toys-protocol";
+
+/// 服务端记录了请求的链路时，在响应中返回trace id，便于对照服务端的日志和链路
+pub const TRACE_ID_HEADER: &str = "x-trace-id";
+
+/// 当前的协议版本，接口类型的编码发生不兼容的变化时加一
+pub const PROTOCOL_VERSION: u16 = 1;
+
+/// 仍能解码的最低协议版本
+pub const MIN_PROTOCOL_VERSION: u16 = 1;
+
+/// 对方的协议版本是否还能解码。两端各自检查，比自己新的版本由对方负责兼容
+pub fn compatible(peer: u16) -> bool {
+    peer >= MIN_PROTOCOL_VERSION
+}
+
+/// 没有内容的响应
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, Readable, Writable, Serialize, Deserialize,
+)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Empty {}
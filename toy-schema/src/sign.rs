@@ -1,10 +1,262 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 use speedy::{Readable, Writable};
 
-#[derive(Debug, Readable, Writable, Serialize, Deserialize)]
+/// 在Debug中代替密码、令牌等敏感字段，避免写进日志
+struct Redacted;
+
+impl fmt::Debug for Redacted {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("******")
+    }
+}
+
+/// 用户角色，按权限从低到高排列，高权限包含低权限
+#[derive(
+    Debug,
+    Clone,
+    Copy,
+    Default,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Readable,
+    Writable,
+    Serialize,
+    Deserialize,
+)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    #[default]
+    User,
+    Admin,
+}
+
+/// 登录结果：启用了两步验证的账户还需要提交验证码
+#[derive(Debug, Clone, Copy, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub enum SignRes {
+    SignedIn(Role),
+    TotpRequired,
+}
+
+/// 登录状态，未登录时为空
+#[derive(Debug, Clone, Copy, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct SignCheckRes {
+    pub role: Option<Role>,
+}
+
+#[derive(Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
 pub struct SignReq {
     #[speedy(default_on_eof)]
+    #[serde(default)]
     pub name: String,
     pub email: String,
     pub password: String,
 }
+
+impl fmt::Debug for SignReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SignReq")
+            .field("name", &self.name)
+            .field("email", &self.email)
+            .field("password", &Redacted)
+            .finish()
+    }
+}
+
+/// 一次登录（即一个session）的信息
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct LoginSession {
+    pub id: String,
+    pub user_agent: String,
+    pub ip: String,
+    pub created_at: String,
+    pub last_seen: String,
+    /// 是否为发起请求的这次登录
+    #[serde(default)]
+    pub current: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RevokeReq {
+    /// 要撤销的登录，为空时撤销全部登录
+    pub id: Option<String>,
+}
+
+/// 用户资料，查询和修改共用
+#[derive(Debug, Clone, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct Profile {
+    pub name: String,
+    pub email: String,
+    /// 是否已启用两步验证，修改资料时忽略
+    #[speedy(default_on_eof)]
+    #[serde(default)]
+    pub totp_enabled: bool,
+}
+
+#[derive(Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ChangePasswordReq {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+impl fmt::Debug for ChangePasswordReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ChangePasswordReq")
+            .field("old_password", &Redacted)
+            .field("new_password", &Redacted)
+            .finish()
+    }
+}
+
+/// 注销账户前需要再次确认密码
+#[derive(Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct DeleteAccountReq {
+    pub password: String,
+}
+
+impl fmt::Debug for DeleteAccountReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("DeleteAccountReq")
+            .field("password", &Redacted)
+            .finish()
+    }
+}
+
+/// 申请重置密码，重置令牌会发送到该邮箱
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ForgotPasswordReq {
+    pub email: String,
+}
+
+#[derive(Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ResetPasswordReq {
+    pub token: String,
+    pub password: String,
+}
+
+impl fmt::Debug for ResetPasswordReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ResetPasswordReq")
+            .field("token", &Redacted)
+            .field("password", &Redacted)
+            .finish()
+    }
+}
+
+/// 启用两步验证时展示给用户的密钥
+#[derive(Clone, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct TotpSetup {
+    pub otpauth_uri: String,
+    /// 二维码PNG图片的base64编码
+    pub qr_png: String,
+    /// 无法扫码时手动输入的base32密钥
+    pub secret: String,
+}
+
+impl fmt::Debug for TotpSetup {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpSetup")
+            .field("otpauth_uri", &Redacted)
+            .field("qr_png", &Redacted)
+            .field("secret", &Redacted)
+            .finish()
+    }
+}
+
+/// 两步验证码，也可以是恢复码
+#[derive(Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct TotpCodeReq {
+    pub code: String,
+}
+
+impl fmt::Debug for TotpCodeReq {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TotpCodeReq")
+            .field("code", &Redacted)
+            .finish()
+    }
+}
+
+/// 启用两步验证后返回的恢复码，只展示这一次
+#[derive(Clone, Default, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RecoveryCodes {
+    pub codes: Vec<String>,
+}
+
+impl fmt::Debug for RecoveryCodes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RecoveryCodes")
+            .field("codes", &Redacted)
+            .finish()
+    }
+}
+
+/// 个人访问令牌的权限范围，令牌只能调用所含范围内的接口
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    Sudoku,
+    Chinese,
+}
+
+impl Scope {
+    pub const ALL: [Scope; 2] = [Scope::Sudoku, Scope::Chinese];
+}
+
+/// 个人访问令牌的信息，不含令牌本身
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct ApiToken {
+    pub id: String,
+    pub name: String,
+    pub scopes: Vec<Scope>,
+    pub created_at: String,
+    /// 从未使用过时为空
+    pub last_used: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct CreateTokenReq {
+    pub name: String,
+    pub scopes: Vec<Scope>,
+}
+
+/// 新建的令牌，只返回这一次
+#[derive(Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct CreateTokenRes {
+    pub token: String,
+}
+
+impl fmt::Debug for CreateTokenRes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("CreateTokenRes")
+            .field("token", &Redacted)
+            .finish()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Readable, Writable, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(schemars::JsonSchema))]
+pub struct RevokeTokenReq {
+    pub id: String,
+}
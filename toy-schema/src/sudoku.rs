@@ -0,0 +1,48 @@
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use speedy::{Readable, Writable};
+
+/// 数独盘面：81个数字按行排列，0表示空格
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Readable, Writable)]
+pub struct SudokuBoard(pub [u16; 81]);
+
+impl Default for SudokuBoard {
+    fn default() -> Self {
+        SudokuBoard([0; 81])
+    }
+}
+
+// serde只为长度不超过32的数组实现了序列化，JSON中按数字数组处理
+impl Serialize for SudokuBoard {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.as_slice().serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SudokuBoard {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let cells = Vec::<u16>::deserialize(deserializer)?;
+        let len = cells.len();
+        cells
+            .try_into()
+            .map(SudokuBoard)
+            .map_err(|_| D::Error::invalid_length(len, &"81个数字"))
+    }
+}
+
+#[cfg(feature = "openapi")]
+impl schemars::JsonSchema for SudokuBoard {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "SudokuBoard".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "description": "数独盘面：81个数字按行排列，0表示空格",
+            "type": "array",
+            "items": {"type": "integer", "minimum": 0, "maximum": 9},
+            "minItems": 81,
+            "maxItems": 81,
+        })
+    }
+}
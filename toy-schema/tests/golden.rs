@@ -0,0 +1,351 @@
+//! 接口类型的编码样本：tests/golden下保存了各协议版本写出的speedy和JSON编码，
+//! 修改类型后如果旧的样本解码失败或解出的值不同，说明破坏了兼容性，需要提升
+//! MIN_PROTOCOL_VERSION。新增类型后用`TOYS_BLESS=1 cargo test`生成样本，已有的样本不会被覆盖。
+
+use std::fmt::Debug;
+use std::fs;
+use std::path::PathBuf;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use speedy::{LittleEndian, Readable, Writable};
+
+use toy_schema::chinese::{ChineseVariant, ConvertReq, ConvertRes, PinyinReq, Ruby, ToneStyle};
+use toy_schema::config::{
+    ConfigChange, ConfigField, ConfigKind, ConfigVersion, ConfigView, ReloadRes, RollbackConfigReq,
+    SaveConfigReq,
+};
+use toy_schema::error::{ApiError, FieldError};
+use toy_schema::protocol::{Empty, PROTOCOL_VERSION};
+use toy_schema::sign::{
+    ApiToken, ChangePasswordReq, CreateTokenReq, CreateTokenRes, DeleteAccountReq,
+    ForgotPasswordReq, LoginSession, Profile, RecoveryCodes, ResetPasswordReq, RevokeReq,
+    RevokeTokenReq, Role, Scope, SignCheckRes, SignReq, SignRes, TotpCodeReq, TotpSetup,
+};
+use toy_schema::sudoku::SudokuBoard;
+
+trait Payload:
+    for<'a> Readable<'a, LittleEndian>
+    + Writable<LittleEndian>
+    + Serialize
+    + DeserializeOwned
+    + PartialEq
+    + Debug
+{
+}
+
+impl<T> Payload for T where
+    T: for<'a> Readable<'a, LittleEndian>
+        + Writable<LittleEndian>
+        + Serialize
+        + DeserializeOwned
+        + PartialEq
+        + Debug
+{
+}
+
+fn golden_dir() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden")
+}
+
+/// 所有版本下保存的样本文件，版本目录为v1、v2……
+fn samples(name: &str, ext: &str) -> Vec<PathBuf> {
+    (1..=PROTOCOL_VERSION)
+        .map(|v| golden_dir().join(format!("v{v}/{name}.{ext}")))
+        .filter(|p| p.exists())
+        .collect()
+}
+
+fn bless<T: Payload>(name: &str, value: &T) {
+    let dir = golden_dir().join(format!("v{PROTOCOL_VERSION}"));
+    fs::create_dir_all(&dir).unwrap();
+    let speedy = dir.join(format!("{name}.speedy"));
+    if !speedy.exists() {
+        fs::write(speedy, value.write_to_vec().unwrap()).unwrap();
+    }
+    let json = dir.join(format!("{name}.json"));
+    if !json.exists() {
+        fs::write(json, serde_json::to_string_pretty(value).unwrap() + "\n").unwrap();
+    }
+}
+
+fn check<T: Payload>(name: &str, value: T) {
+    if std::env::var_os("TOYS_BLESS").is_some() {
+        bless(name, &value);
+    }
+
+    let speedy = samples(name, "speedy");
+    let json = samples(name, "json");
+    assert!(
+        !speedy.is_empty() && !json.is_empty(),
+        "缺少{name}的样本，请用TOYS_BLESS=1运行测试生成"
+    );
+    for path in speedy {
+        let decoded = T::read_from_buffer(&fs::read(&path).unwrap())
+            .unwrap_or_else(|e| panic!("{}：{e}", path.display()));
+        assert_eq!(decoded, value, "{}", path.display());
+    }
+    for path in json {
+        let decoded: T = serde_json::from_slice(&fs::read(&path).unwrap())
+            .unwrap_or_else(|e| panic!("{}：{e}", path.display()));
+        assert_eq!(decoded, value, "{}", path.display());
+    }
+}
+
+fn board() -> SudokuBoard {
+    let mut board = SudokuBoard::default();
+    for (i, cell) in board.0.iter_mut().enumerate() {
+        *cell = (i % 10) as u16;
+    }
+    board
+}
+
+#[test]
+fn common() {
+    check("empty", Empty {});
+    check(
+        "api_error",
+        ApiError {
+            code: "validation".to_owned(),
+            message: "参数有误".to_owned(),
+            details: vec![FieldError::new("email", "邮箱格式有误")],
+        },
+    );
+}
+
+#[test]
+fn sign() {
+    let sign_req = SignReq {
+        name: "玩具".to_owned(),
+        email: "toy@example.com".to_owned(),
+        password: "password".to_owned(),
+    };
+    check("sign_req", sign_req);
+    check("sign_res_signed_in", SignRes::SignedIn(Role::Admin));
+    check("sign_res_totp_required", SignRes::TotpRequired);
+    check(
+        "sign_check_res",
+        SignCheckRes {
+            role: Some(Role::User),
+        },
+    );
+    check(
+        "sessions",
+        vec![LoginSession {
+            id: "abc".to_owned(),
+            user_agent: "Mozilla/5.0".to_owned(),
+            ip: "127.0.0.1".to_owned(),
+            created_at: "2024-01-01T00:00:00Z".to_owned(),
+            last_seen: "2024-01-02T00:00:00Z".to_owned(),
+            current: true,
+        }],
+    );
+    check(
+        "revoke_req",
+        RevokeReq {
+            id: Some("abc".to_owned()),
+        },
+    );
+    check(
+        "totp_code_req",
+        TotpCodeReq {
+            code: "123456".to_owned(),
+        },
+    );
+}
+
+#[test]
+fn account() {
+    check(
+        "profile",
+        Profile {
+            name: "玩具".to_owned(),
+            email: "toy@example.com".to_owned(),
+            totp_enabled: true,
+        },
+    );
+    check(
+        "change_password_req",
+        ChangePasswordReq {
+            old_password: "old".to_owned(),
+            new_password: "new".to_owned(),
+        },
+    );
+    check(
+        "delete_account_req",
+        DeleteAccountReq {
+            password: "password".to_owned(),
+        },
+    );
+    check(
+        "forgot_password_req",
+        ForgotPasswordReq {
+            email: "toy@example.com".to_owned(),
+        },
+    );
+    check(
+        "reset_password_req",
+        ResetPasswordReq {
+            token: "token".to_owned(),
+            password: "password".to_owned(),
+        },
+    );
+    check(
+        "totp_setup",
+        TotpSetup {
+            otpauth_uri: "otpauth://totp/toys:toy@example.com?secret=ABC".to_owned(),
+            qr_png: "iVBORw0KGgo=".to_owned(),
+            secret: "ABC".to_owned(),
+        },
+    );
+    check(
+        "recovery_codes",
+        RecoveryCodes {
+            codes: vec!["abcde-12345".to_owned(), "fghij-67890".to_owned()],
+        },
+    );
+}
+
+#[test]
+fn api_token() {
+    check(
+        "api_tokens",
+        vec![ApiToken {
+            id: "abc".to_owned(),
+            name: "脚本".to_owned(),
+            scopes: Scope::ALL.to_vec(),
+            created_at: "2024-01-01T00:00:00Z".to_owned(),
+            last_used: None,
+        }],
+    );
+    check(
+        "create_token_req",
+        CreateTokenReq {
+            name: "脚本".to_owned(),
+            scopes: vec![Scope::Sudoku],
+        },
+    );
+    check(
+        "create_token_res",
+        CreateTokenRes {
+            token: "toys_0123456789".to_owned(),
+        },
+    );
+    check(
+        "revoke_token_req",
+        RevokeTokenReq {
+            id: "abc".to_owned(),
+        },
+    );
+}
+
+#[test]
+fn tools() {
+    check("sudoku_board", board());
+    check(
+        "convert_req",
+        ConvertReq {
+            text: "汉字".to_owned(),
+            target: ChineseVariant::HongKong,
+            normalize_punctuation: true,
+            source: None,
+        },
+    );
+    check(
+        "convert_req_source",
+        ConvertReq {
+            text: "軟體".to_owned(),
+            target: ChineseVariant::Simplified,
+            normalize_punctuation: false,
+            source: Some(ChineseVariant::Taiwan),
+        },
+    );
+    check(
+        "convert_res",
+        ConvertRes {
+            text: "漢字".to_owned(),
+        },
+    );
+    check(
+        "pinyin_req",
+        PinyinReq {
+            text: "汉字".to_owned(),
+            style: ToneStyle::Number,
+        },
+    );
+    check(
+        "pinyin_res",
+        vec![
+            Ruby {
+                text: "汉".to_owned(),
+                pinyin: Some("han4".to_owned()),
+            },
+            Ruby {
+                text: "!".to_owned(),
+                pinyin: None,
+            },
+        ],
+    );
+}
+
+#[test]
+fn config() {
+    check(
+        "reload_res",
+        ReloadRes {
+            changes: vec![ConfigChange {
+                key: "log.level".to_owned(),
+                old: "\"INFO\"".to_owned(),
+                new: "\"DEBUG\"".to_owned(),
+                applied: true,
+            }],
+        },
+    );
+    let field = ConfigField {
+        key: "web.rate_limit.ip_burst".to_owned(),
+        kind: ConfigKind::Integer,
+        value: "20".to_owned(),
+        secret: false,
+        overridden: false,
+    };
+    check(
+        "config_view",
+        ConfigView {
+            fields: vec![
+                field.clone(),
+                ConfigField {
+                    key: "trace.authorization".to_owned(),
+                    kind: ConfigKind::Text,
+                    value: String::new(),
+                    secret: true,
+                    overridden: false,
+                },
+            ],
+            versions: vec![ConfigVersion {
+                id: "20261019-120000".to_owned(),
+                saved_at: "2026-10-19 12:00:00".to_owned(),
+            }],
+        },
+    );
+    check(
+        "save_config_req",
+        SaveConfigReq {
+            fields: vec![field],
+        },
+    );
+    check(
+        "rollback_config_req",
+        RollbackConfigReq {
+            id: "20261019-120000".to_owned(),
+        },
+    );
+}
+
+/// 旧版客户端的数独请求是裸的[u16; 81]，换成SudokuBoard后编码不能变
+#[test]
+fn sudoku_board_wire() {
+    let raw: [u16; 81] = board().0;
+    let bytes = Writable::<LittleEndian>::write_to_vec(&raw).unwrap();
+    assert_eq!(bytes, board().write_to_vec().unwrap());
+    assert!(serde_json::from_str::<SudokuBoard>("[1, 2, 3]").is_err());
+}
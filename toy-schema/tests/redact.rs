@@ -0,0 +1,68 @@
+use toy_schema::sign::{
+    ChangePasswordReq, CreateTokenRes, DeleteAccountReq, RecoveryCodes, ResetPasswordReq, SignReq,
+    TotpCodeReq, TotpSetup,
+};
+
+/// 敏感字段不出现在Debug输出中，避免写进日志
+#[test]
+fn debug_redacts_secrets() {
+    let outputs = [
+        format!(
+            "{:?}",
+            SignReq {
+                name: "toy".to_owned(),
+                email: "toy@example.com".to_owned(),
+                password: "5ecret".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            ChangePasswordReq {
+                old_password: "old_5ecret".to_owned(),
+                new_password: "new_5ecret".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            DeleteAccountReq {
+                password: "5ecret".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            ResetPasswordReq {
+                token: "reset_5ecret".to_owned(),
+                password: "5ecret".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            CreateTokenRes {
+                token: "toy_5ecret".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            TotpSetup {
+                otpauth_uri: "otpauth://totp/toys:a@b.c?secret=JBSW5ECRET".to_owned(),
+                qr_png: "iVBOR5ECRET".to_owned(),
+                secret: "JBSW5ECRET".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            TotpCodeReq {
+                code: "5ECRET-0001".to_owned(),
+            }
+        ),
+        format!(
+            "{:?}",
+            RecoveryCodes {
+                codes: vec!["5ECRET-0001".to_owned(), "5ECRET-0002".to_owned()],
+            }
+        ),
+    ];
+    for output in outputs {
+        assert!(!output.to_lowercase().contains("5ecret"), "{output}");
+    }
+}
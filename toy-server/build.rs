@@ -0,0 +1,43 @@
+use std::env;
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// 把git提交和构建时间写入编译环境，供/version使用
+fn main() {
+    // 不在git仓库中构建时（如打包后的源码）可以用环境变量指定
+    println!("cargo:rerun-if-env-changed=TOYS_GIT_HASH");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=../.git/HEAD");
+    println!("cargo:rerun-if-changed=../.git/refs/heads");
+    // 指定了rerun-if-changed后cargo不再在包内文件变化时重跑，构建时间会停在上次，所以源码也要列出
+    println!("cargo:rerun-if-changed=src");
+    println!("cargo:rerun-if-changed=migrations");
+    println!("cargo:rerun-if-changed=Cargo.toml");
+
+    let hash = env::var("TOYS_GIT_HASH").ok().or_else(git_hash);
+    println!(
+        "cargo:rustc-env=TOYS_GIT_HASH={}",
+        hash.as_deref().unwrap_or("unknown")
+    );
+
+    // 设置了SOURCE_DATE_EPOCH时按它构建，便于复现
+    let build_time = env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| {
+            SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or_default()
+        });
+    println!("cargo:rustc-env=TOYS_BUILD_TIME={build_time}");
+}
+
+fn git_hash() -> Option<String> {
+    let output = Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()?;
+    let hash = String::from_utf8(output.stdout).ok()?.trim().to_owned();
+    (output.status.success() && !hash.is_empty()).then_some(hash)
+}
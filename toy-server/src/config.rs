@@ -1,36 +1,415 @@
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::ErrorKind::NotFound;
+use std::net::IpAddr;
+use std::path::Path;
+use std::str::FromStr;
 use std::sync::{Arc, OnceLock};
 use std::{env, fs};
 
 use arc_swap::ArcSwap;
+use log::LevelFilter;
 use serde::{Deserialize, Serialize};
+use toml::{Table, Value};
+
+use toy_schema::config::ConfigChange;
+use toy_schema::error::FieldError;
 
 pub(crate) static GLOBAL_CONFIG: OnceLock<ArcSwap<Config>> = OnceLock::new();
 
-/// 加载配置
-pub(crate) fn reload() {
-    let file = env::var("APP_ENV")
+// 名称以这些结尾的配置项在差异中不显示原值
+const SECRETS: [&str; 3] = ["password", "secret", "authorization"];
+// 环境变量覆盖配置文件，如TOYS_WEB__ADDRESS对应web.address
+const ENV_PREFIX: &str = "TOYS_";
+const ENV_SEPARATOR: &str = "__";
+// 远程数据库和内嵌数据库支持的地址前缀
+const REMOTE_SCHEMES: [&str; 4] = ["ws://", "wss://", "http://", "https://"];
+const EMBEDDED_SCHEMES: [&str; 2] = ["surrealkv://", "mem://"];
+// 健康检查挂在根下的路径，见web::serve
+const PROBE_PATHS: [&str; 3] = ["/healthz", "/readyz", "/version"];
+// 键名由用户自定的表，不检查其中的键是否已知
+const FREE_FORM: [&str; 1] = ["trace.headers"];
+
+/// 配置文件：config.{APP_ENV}.toml，未设置APP_ENV时为config.prd.toml
+pub(crate) fn file() -> String {
+    env::var("APP_ENV")
         .map(|e| format!("config.{e}.toml"))
-        .unwrap_or("config.prd.toml".into());
-    let cfg = GLOBAL_CONFIG.get_or_init(|| ArcSwap::new(Arc::new(Config::default())));
+        .unwrap_or("config.prd.toml".into())
+}
+
+/// 按默认配置、配置文件、环境变量的顺序逐层覆盖，并校验所有配置项
+pub(crate) fn load() -> Result<Config, Vec<FieldError>> {
+    let file = file();
+    build(&file, read_table(&file)?)
+}
+
+/// 读取配置文件，文件不存在时为空
+pub(crate) fn read_table(file: &str) -> Result<Table, Vec<FieldError>> {
+    match fs::read_to_string(file) {
+        Ok(s) => s
+            .parse::<Table>()
+            .map_err(|e| vec![FieldError::new(file, format!("配置格式有误: {e}"))]),
+        // 没有配置文件时使用默认配置，启动时会提示
+        Err(e) if e.kind() == NotFound => Ok(Table::new()),
+        Err(e) => Err(vec![FieldError::new(file, format!("配置读取失败: {e}"))]),
+    }
+}
 
-    match fs::read_to_string(&file) {
-        Ok(s) => match toml::from_str::<Config>(&s) {
-            Ok(c) => {
-                cfg.store(Arc::new(c));
+/// 在默认配置上依次覆盖配置文件的内容和环境变量，校验后返回
+pub(crate) fn build(file: &str, table: Table) -> Result<Config, Vec<FieldError>> {
+    let defaults = Value::try_from(Config::default())
+        .map_err(|e| vec![FieldError::new("", format!("默认配置有误: {e}"))])?;
+    let (table, env) = (Value::Table(table), env_overrides(env::vars()));
+    let mut errors = check_unknown(&defaults, file, &table, &env);
+    let mut layered = defaults;
+    merge(&mut layered, table);
+    merge(&mut layered, env);
+
+    match Config::deserialize(layered) {
+        Ok(config) => {
+            errors.extend(validate(&config));
+            if errors.is_empty() {
+                return Ok(config);
             }
-            Err(e) => log::error!("{file} 配置格式有误，解析失败: {e}"),
-        },
-        Err(e) => {
-            if e.kind() == NotFound {
-                if let Ok(c) = toml::to_string(&Config::default()) {
-                    fs::write(file, c).ok();
+        }
+        Err(e) => errors.push(FieldError::new(file, e.to_string().trim())),
+    }
+    Err(errors)
+}
+
+/// 配置文件和环境变量中默认配置没有的配置项，多半是拼写错误，不报错的话会被悄悄忽略
+fn check_unknown(defaults: &Value, file: &str, table: &Value, env: &Value) -> Vec<FieldError> {
+    let (mut from_file, mut from_env) = (Vec::new(), Vec::new());
+    unknown_keys(defaults, table, "", &mut from_file);
+    unknown_keys(defaults, env, "", &mut from_env);
+    let from_file = from_file
+        .into_iter()
+        .map(|key| FieldError::new(&key, format!("{file}中有未知的配置项")));
+    let from_env = from_env.into_iter().map(|key| {
+        let name = key.replace('.', ENV_SEPARATOR).to_uppercase();
+        FieldError::new(&key, format!("环境变量{ENV_PREFIX}{name}对应未知的配置项"))
+    });
+    from_file.chain(from_env).collect()
+}
+
+fn unknown_keys(defaults: &Value, value: &Value, path: &str, unknown: &mut Vec<String>) {
+    let (Value::Table(defaults), Value::Table(table)) = (defaults, value) else {
+        return;
+    };
+    if FREE_FORM.contains(&path) {
+        return;
+    }
+    for (key, value) in table {
+        let path = if path.is_empty() {
+            key.clone()
+        } else {
+            format!("{path}.{key}")
+        };
+        match defaults.get(key) {
+            Some(default) => unknown_keys(default, value, &path, unknown),
+            None => unknown.push(path),
+        }
+    }
+}
+
+/// 加载配置，返回与原配置相比发生变化的配置项；配置有误时保留原配置，返回所有错误
+pub(crate) fn reload() -> Result<Vec<ConfigChange>, Vec<FieldError>> {
+    let cfg = GLOBAL_CONFIG.get_or_init(|| ArcSwap::new(Arc::new(Config::default())));
+    match load() {
+        Ok(c) => {
+            let old = cfg.swap(Arc::new(c));
+            Ok(diff(&old, &cfg.load()))
+        }
+        Err(errors) => {
+            for e in &errors {
+                log::error!("配置有误 {}: {}", e.field, e.message);
+            }
+            Err(errors)
+        }
+    }
+}
+
+/// 把overlay中的配置项逐个覆盖到base上
+pub(crate) fn merge(base: &mut Value, overlay: Value) {
+    match (base, overlay) {
+        (Value::Table(base), Value::Table(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        base.insert(key, value);
+                    }
                 }
-            } else {
-                log::error!("{file} 配置读取失败: {e}");
             }
         }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// 以TOYS_开头的环境变量，双下划线分隔各级配置项，没有分隔符的（如构建时用的TOYS_GIT_HASH）不是配置项。
+/// 值按默认配置中该项的类型转换：字符串项保持原样（如数字密码），只在写成带引号的TOML字符串时去掉引号；
+/// 其他项按TOML解析，解析不了时作为字符串
+pub(crate) fn env_overrides(vars: impl Iterator<Item = (String, String)>) -> Value {
+    let defaults = Value::try_from(Config::default()).ok();
+    let mut overrides = Value::Table(Table::new());
+    for (name, raw) in vars {
+        let Some(path) = name.strip_prefix(ENV_PREFIX) else {
+            continue;
+        };
+        if !path.contains(ENV_SEPARATOR) {
+            continue;
+        }
+        let is_string = defaults
+            .as_ref()
+            .and_then(|d| {
+                path.split(ENV_SEPARATOR)
+                    .try_fold(d, |v, key| v.get(key.to_lowercase().as_str()))
+            })
+            .is_some_and(Value::is_str);
+        let parsed = format!("v = {raw}")
+            .parse::<Table>()
+            .ok()
+            .and_then(|mut t| t.remove("v"));
+        let value = match parsed {
+            Some(v) if !is_string || v.is_str() => v,
+            _ => Value::String(raw),
+        };
+        merge(&mut overrides, nested(path, ENV_SEPARATOR, value));
     }
+    overrides
+}
+
+/// 把路径（如web.tls.cert）展开为嵌套的表
+pub(crate) fn nested(path: &str, separator: &str, value: Value) -> Value {
+    path.rsplit(separator).fold(value, |value, key| {
+        Value::Table(Table::from_iter([(key.to_lowercase(), value)]))
+    })
+}
+
+/// 密码等敏感项，不返回原值
+pub(crate) fn is_secret(key: &str) -> bool {
+    SECRETS.iter().any(|s| key.ends_with(s))
+}
+
+/// 校验各配置项，返回所有错误
+pub(crate) fn validate(cfg: &Config) -> Vec<FieldError> {
+    let mut errors = Vec::new();
+    let mut check = |ok: bool, field: &str, message: &str| {
+        if !ok {
+            errors.push(FieldError::new(field, message));
+        }
+    };
+
+    check(
+        LevelFilter::from_str(&cfg.log.level).is_ok(),
+        "log.level",
+        "日志级别应为 OFF、ERROR、WARN、INFO、DEBUG 或 TRACE",
+    );
+    check(
+        !cfg.log.directory.is_empty(),
+        "log.directory",
+        "日志目录不能为空",
+    );
+
+    let web = &cfg.web;
+    check(
+        valid_address(&web.address),
+        "web.address",
+        "监听地址应为 host:port",
+    );
+    check(
+        !web.assets_path.is_empty(),
+        "web.assets_path",
+        "静态文件目录不能为空",
+    );
+    check(
+        web.session_max_age > 0,
+        "web.session_max_age",
+        "session有效期应大于0",
+    );
+    check(
+        !matches!(web.session_storage, SessionBackend::File) || !web.session_dir.is_empty(),
+        "web.session_dir",
+        "文件存储时session目录不能为空",
+    );
+    let limit = &web.rate_limit;
+    check(limit.ip_burst > 0, "web.rate_limit.ip_burst", "应大于0");
+    check(
+        limit.ip_per_minute > 0,
+        "web.rate_limit.ip_per_minute",
+        "应大于0",
+    );
+    check(
+        limit.account_burst > 0,
+        "web.rate_limit.account_burst",
+        "应大于0",
+    );
+    check(
+        limit.account_per_minute > 0,
+        "web.rate_limit.account_per_minute",
+        "应大于0",
+    );
+    check(
+        limit.lockout_failures == 0 || limit.lockout_secs > 0,
+        "web.rate_limit.lockout_secs",
+        "锁定账户时锁定时长应大于0",
+    );
+    let openapi = &web.openapi;
+    check(
+        !openapi.enabled
+            || (openapi.path.starts_with('/')
+                && openapi.path != "/"
+                && !openapi.path.starts_with("/api/")
+                && !PROBE_PATHS.contains(&openapi.path.as_str())),
+        "web.openapi.path",
+        "应以/开头，且不能是/、/api下的路径或健康检查的路径",
+    );
+    let metrics = &web.metrics;
+    if metrics.enabled {
+        check(
+            metrics.path.starts_with('/')
+                && metrics.path != "/"
+                && !metrics.path.starts_with("/api/")
+                && (!metrics.address.is_empty() || !PROBE_PATHS.contains(&metrics.path.as_str()))
+                && (!openapi.enabled || metrics.path != openapi.path),
+            "web.metrics.path",
+            "应以/开头，且不能是/、/api下的路径、健康检查或接口文档的路径",
+        );
+        check(
+            metrics.address.is_empty()
+                || (valid_address(&metrics.address) && metrics.address != web.address),
+            "web.metrics.address",
+            "监听地址应为 host:port，且不能与web.address相同",
+        );
+    }
+    check(
+        web.trusted_proxies
+            .iter()
+            .all(|p| p.parse::<IpAddr>().is_ok()),
+        "web.trusted_proxies",
+        "应为IP地址列表",
+    );
+    let tls = &web.tls;
+    if tls.enabled {
+        check(
+            Path::new(&tls.cert).is_file(),
+            "web.tls.cert",
+            "证书文件不存在",
+        );
+        check(
+            Path::new(&tls.key).is_file(),
+            "web.tls.key",
+            "私钥文件不存在",
+        );
+        check(
+            tls.redirect_address.is_empty() || valid_address(&tls.redirect_address),
+            "web.tls.redirect_address",
+            "监听地址应为 host:port",
+        );
+        check(
+            tls.redirect_address != web.address,
+            "web.tls.redirect_address",
+            "不能与web.address相同",
+        );
+    }
+
+    let trace = &cfg.trace;
+    if trace.enabled && !trace.endpoint.is_empty() {
+        check(
+            trace.endpoint.starts_with("http://") || trace.endpoint.starts_with("https://"),
+            "trace.endpoint",
+            "应为 http:// 或 https:// 开头的地址",
+        );
+        check(
+            (0.0..=1.0).contains(&trace.sample_ratio),
+            "trace.sample_ratio",
+            "采样比例应在0.0到1.0之间",
+        );
+        check(trace.timeout > 0, "trace.timeout", "应大于0");
+        for name in trace.headers.keys() {
+            check(
+                !name.is_empty()
+                    && name
+                        .bytes()
+                        .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'-'),
+                &format!("trace.headers.{name}"),
+                "头的名称只能包含小写字母、数字和-",
+            );
+        }
+    }
+
+    let db = &cfg.database;
+    check(
+        REMOTE_SCHEMES
+            .iter()
+            .chain(&EMBEDDED_SCHEMES)
+            .any(|scheme| db.endpoint.starts_with(scheme)),
+        "database.endpoint",
+        "应为 ws://host:port、surrealkv://path 或 mem://",
+    );
+    check(!db.namespace.is_empty(), "database.namespace", "不能为空");
+    check(!db.database.is_empty(), "database.database", "不能为空");
+    check(db.pool_size > 0, "database.pool_size", "连接池大小应大于0");
+
+    let mail = &cfg.mail;
+    check(mail.from.contains('@'), "mail.from", "发件人应为邮箱地址");
+    check(!mail.dir.is_empty(), "mail.dir", "邮件目录不能为空");
+    check(
+        mail.reset_ttl > 0,
+        "mail.reset_ttl",
+        "重置令牌有效期应大于0",
+    );
+    errors
+}
+
+fn valid_address(address: &str) -> bool {
+    address
+        .rsplit_once(':')
+        .is_some_and(|(host, port)| !host.is_empty() && port.parse::<u16>().is_ok())
+}
+
+/// 比较两份配置，按配置项的路径排序
+fn diff(old: &Config, new: &Config) -> Vec<ConfigChange> {
+    let mut changes = Vec::new();
+    if let (Ok(old), Ok(new)) = (Value::try_from(old), Value::try_from(new)) {
+        diff_value("", Some(&old), Some(&new), &mut changes);
+    }
+    changes
+}
+
+fn diff_value(
+    key: &str,
+    old: Option<&Value>,
+    new: Option<&Value>,
+    changes: &mut Vec<ConfigChange>,
+) {
+    if old == new {
+        return;
+    }
+    if let (Some(Value::Table(old)), Some(Value::Table(new))) = (old, new) {
+        let names: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+        for name in names {
+            let key = if key.is_empty() {
+                name.clone()
+            } else {
+                format!("{key}.{name}")
+            };
+            diff_value(&key, old.get(name), new.get(name), changes);
+        }
+        return;
+    }
+
+    let secret = is_secret(key);
+    let show = |value: Option<&Value>| match value {
+        None => String::new(),
+        Some(_) if secret => "******".to_owned(),
+        Some(value) => value.to_string(),
+    };
+    changes.push(ConfigChange {
+        key: key.to_owned(),
+        old: show(old),
+        new: show(new),
+        applied: false,
+    });
 }
 
 #[derive(Deserialize, Serialize, Default)]
@@ -38,6 +417,8 @@ pub(crate) struct Config {
     pub(crate) log: LogCfg,
     pub(crate) web: WebCfg,
     pub(crate) trace: TraceCfg,
+    pub(crate) database: DatabaseCfg,
+    pub(crate) mail: MailCfg,
 }
 
 #[derive(Deserialize, Serialize)]
@@ -56,9 +437,124 @@ impl Default for LogCfg {
 }
 
 #[derive(Deserialize, Serialize)]
+#[serde(default)]
 pub(crate) struct WebCfg {
     pub(crate) address: String,
     pub(crate) assets_path: String,
+    /// session有效期（秒），同时作为cookie的max-age
+    pub(crate) session_max_age: u64,
+    pub(crate) session_storage: SessionBackend,
+    /// 文件存储时session文件所在目录
+    pub(crate) session_dir: String,
+    pub(crate) rate_limit: RateLimitCfg,
+    pub(crate) openapi: OpenApiCfg,
+    pub(crate) tls: TlsCfg,
+    /// 以HTTP运行时只采信这些反向代理转发的X-Forwarded-For
+    pub(crate) trusted_proxies: Vec<String>,
+    pub(crate) metrics: MetricsCfg,
+    /// 重新加载配置时，监听地址、证书或接口文档有变化则重新监听，期间会短暂中断服务
+    pub(crate) rebind: bool,
+}
+
+/// HTTPS证书；不启用时以HTTP运行，用于反向代理之后，此时采信可信代理转发的X-Forwarded-For
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub(crate) struct TlsCfg {
+    pub(crate) enabled: bool,
+    /// PEM格式的证书链
+    pub(crate) cert: String,
+    /// PEM格式的私钥
+    pub(crate) key: String,
+    /// 检查证书文件是否更新的间隔（秒），更新后不用重启即可生效，0表示不检查
+    pub(crate) reload_interval: u64,
+    /// 把HTTP请求跳转到HTTPS的监听地址，为空时不监听
+    pub(crate) redirect_address: String,
+}
+
+impl Default for TlsCfg {
+    fn default() -> Self {
+        TlsCfg {
+            enabled: true,
+            cert: "localhost+3.pem".to_owned(),
+            key: "localhost+3-key.pem".to_owned(),
+            reload_interval: 60,
+            redirect_address: String::new(),
+        }
+    }
+}
+
+/// OpenAPI文档和接口浏览页
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub(crate) struct OpenApiCfg {
+    pub(crate) enabled: bool,
+    /// 接口浏览页的路径，文档在其下的openapi.json
+    pub(crate) path: String,
+}
+
+impl Default for OpenApiCfg {
+    fn default() -> Self {
+        OpenApiCfg {
+            enabled: true,
+            path: "/api-docs".to_owned(),
+        }
+    }
+}
+
+/// Prometheus指标，需以metrics特性编译
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub(crate) struct MetricsCfg {
+    pub(crate) enabled: bool,
+    pub(crate) path: String,
+    /// 单独以HTTP监听的地址，为空时挂在主服务上
+    pub(crate) address: String,
+}
+
+impl Default for MetricsCfg {
+    fn default() -> Self {
+        MetricsCfg {
+            enabled: false,
+            path: "/metrics".to_owned(),
+            address: String::new(),
+        }
+    }
+}
+
+/// 认证接口限流，令牌桶容量为burst，每分钟补充per_minute个
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub(crate) struct RateLimitCfg {
+    pub(crate) ip_burst: u32,
+    pub(crate) ip_per_minute: u32,
+    pub(crate) account_burst: u32,
+    pub(crate) account_per_minute: u32,
+    /// 连续登录失败多少次后锁定账户，0表示不锁定
+    pub(crate) lockout_failures: u32,
+    /// 账户锁定时长（秒）
+    pub(crate) lockout_secs: u64,
+}
+
+impl Default for RateLimitCfg {
+    fn default() -> Self {
+        RateLimitCfg {
+            ip_burst: 20,
+            ip_per_minute: 30,
+            account_burst: 5,
+            account_per_minute: 5,
+            lockout_failures: 5,
+            lockout_secs: 15 * 60,
+        }
+    }
+}
+
+/// session存储后端
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum SessionBackend {
+    Surreal,
+    Memory,
+    File,
 }
 
 impl Default for WebCfg {
@@ -66,21 +562,264 @@ impl Default for WebCfg {
         WebCfg {
             address: "0.0.0.0:8080".to_owned(),
             assets_path: "../dist/public".to_owned(),
+            session_max_age: 10 * 60 * 60,
+            session_storage: SessionBackend::Surreal,
+            session_dir: "sessions".to_owned(),
+            rate_limit: RateLimitCfg::default(),
+            openapi: OpenApiCfg::default(),
+            tls: TlsCfg::default(),
+            trusted_proxies: vec!["127.0.0.1".to_owned(), "::1".to_owned()],
+            metrics: MetricsCfg::default(),
+            rebind: false,
         }
     }
 }
 
-#[derive(Deserialize, Serialize)]
+/// 链路追踪，以OTLP上报到collector；关闭或没有配置endpoint时不上报
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
 pub(crate) struct TraceCfg {
-    pub(crate) authorization: String,
-    pub(crate) assets_path: String,
+    pub(crate) enabled: bool,
+    /// gRPC如http://localhost:4317，HTTP如http://localhost:4318/v1/traces
+    pub(crate) endpoint: String,
+    pub(crate) protocol: TraceProtocol,
+    /// 每次上报附带的头，如authorization
+    pub(crate) headers: BTreeMap<String, String>,
+    /// 采样比例，0.0到1.0
+    pub(crate) sample_ratio: f64,
+    /// 上报超时（秒）
+    pub(crate) timeout: u64,
+}
+
+/// OTLP的传输方式
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum TraceProtocol {
+    Grpc,
+    /// HTTP上的protobuf
+    Http,
 }
 
 impl Default for TraceCfg {
     fn default() -> Self {
         TraceCfg {
-            authorization: "0.0.0.0:8080".to_owned(),
-            assets_path: "./dist".to_owned(),
+            enabled: true,
+            endpoint: String::new(),
+            protocol: TraceProtocol::Grpc,
+            headers: BTreeMap::new(),
+            sample_ratio: 1.0,
+            timeout: 10,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+pub(crate) struct DatabaseCfg {
+    /// ws://host:port 远程数据库，surrealkv://path 内嵌文件数据库，mem:// 内存数据库
+    pub(crate) endpoint: String,
+    pub(crate) namespace: String,
+    pub(crate) database: String,
+    pub(crate) username: String,
+    pub(crate) password: String,
+    pub(crate) pool_size: usize,
+}
+
+impl DatabaseCfg {
+    /// 内嵌或内存数据库：进程内只打开一次，没有开启认证。其他地址前缀在校验时已报错
+    pub(crate) fn is_embedded(&self) -> bool {
+        EMBEDDED_SCHEMES
+            .iter()
+            .any(|scheme| self.endpoint.starts_with(scheme))
+    }
+}
+
+impl Default for DatabaseCfg {
+    fn default() -> Self {
+        DatabaseCfg {
+            endpoint: "ws://localhost:8000".to_owned(),
+            namespace: "toy".to_owned(),
+            database: "toy".to_owned(),
+            username: "root".to_owned(),
+            password: "root_pass".to_owned(),
+            pool_size: 16,
+        }
+    }
+}
+
+#[derive(Deserialize, Serialize, Clone)]
+#[serde(default)]
+pub(crate) struct MailCfg {
+    pub(crate) mailer: MailerKind,
+    pub(crate) from: String,
+    /// 文件邮件的保存目录
+    pub(crate) dir: String,
+    /// 重置密码页面地址，`{token}`替换为重置令牌，为空时邮件中只有令牌
+    pub(crate) reset_url: String,
+    /// 重置令牌有效期（秒）
+    pub(crate) reset_ttl: u64,
+}
+
+/// 邮件发送方式
+#[derive(Deserialize, Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum MailerKind {
+    /// 写入本地文件，用于开发调试
+    File,
+}
+
+impl Default for MailCfg {
+    fn default() -> Self {
+        MailCfg {
+            mailer: MailerKind::File,
+            from: "toys@localhost".to_owned(),
+            dir: "mails".to_owned(),
+            reset_url: String::new(),
+            reset_ttl: 30 * 60,
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod test {
+    use toml::{Table, Value};
+
+    use crate::config::{check_unknown, diff, env_overrides, merge, validate, Config};
+
+    #[test]
+    fn config_diff() {
+        let old = Config::default();
+        assert!(diff(&old, &Config::default()).is_empty());
+
+        let mut new = Config::default();
+        new.log.level = "DEBUG".to_owned();
+        new.web.rate_limit.ip_burst = 1;
+        new.database.password = "changed".to_owned();
+        let changes = diff(&old, &new);
+        let keys: Vec<&str> = changes.iter().map(|c| c.key.as_str()).collect();
+        assert_eq!(
+            keys,
+            ["database.password", "log.level", "web.rate_limit.ip_burst"]
+        );
+        assert_eq!(changes[0].new, "******");
+        assert_eq!(changes[1].old, "\"INFO\"");
+        assert_eq!(changes[1].new, "\"DEBUG\"");
+    }
+
+    #[test]
+    fn config_validate() {
+        let mut cfg = Config::default();
+        cfg.web.tls.enabled = false;
+        assert!(validate(&cfg).is_empty());
+
+        cfg.log.level = "VERBOSE".to_owned();
+        cfg.web.address = "8080".to_owned();
+        cfg.web.rate_limit.ip_burst = 0;
+        cfg.web.openapi.path = "/api/docs".to_owned();
+        cfg.web.metrics.enabled = true;
+        cfg.web.metrics.path = "/".to_owned();
+        cfg.web.tls.enabled = true;
+        cfg.web.tls.cert = "missing.pem".to_owned();
+        cfg.trace.endpoint = "localhost:4317".to_owned();
+        cfg.trace.sample_ratio = 1.5;
+        cfg.trace
+            .headers
+            .insert("Stream Name".to_owned(), "toy-server".to_owned());
+        cfg.database.endpoint = "surealkv://toys.db".to_owned();
+        cfg.database.pool_size = 0;
+        cfg.mail.from = "toys".to_owned();
+        let fields: Vec<String> = validate(&cfg).into_iter().map(|e| e.field).collect();
+        assert_eq!(
+            fields,
+            [
+                "log.level",
+                "web.address",
+                "web.rate_limit.ip_burst",
+                "web.openapi.path",
+                "web.metrics.path",
+                "web.tls.cert",
+                "web.tls.key",
+                "trace.endpoint",
+                "trace.sample_ratio",
+                "trace.headers.Stream Name",
+                "database.endpoint",
+                "database.pool_size",
+                "mail.from",
+            ]
+        );
+
+        // 拼错的配置项报错，trace.headers下的键由用户自定
+        let table: Table = r#"
+            [web]
+            adress = "0.0.0.0:9090"
+            [web.tls]
+            enabled = false
+            [trace.headers]
+            stream-name = "toy-server"
+            [logs]
+            level = "DEBUG"
+        "#
+        .parse()
+        .unwrap();
+        let defaults = Value::try_from(Config::default()).unwrap();
+        let errors = check_unknown(
+            &defaults,
+            "config.test.toml",
+            &Value::Table(table),
+            &Value::Table(Table::new()),
+        );
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["logs", "web.adress"]);
+        assert_eq!(errors[0].message, "config.test.toml中有未知的配置项");
+    }
+
+    #[test]
+    fn config_env_overrides() {
+        let vars = [
+            ("TOYS_WEB__ADDRESS", "127.0.0.1:9090"),
+            ("TOYS_WEB__RATE_LIMIT__IP_BURST", "3"),
+            ("TOYS_WEB__TLS__ENABLED", "false"),
+            ("TOYS_LOG__LEVEL", "\"DEBUG\""),
+            ("TOYS_DATABASE__PASSWORD", "123456"),
+            ("TOYS_MAIL__FROM", "1979-05-27T07:32:00Z"),
+            ("TOYS_GIT_HASH", "abc1234"),
+            ("PATH", "/usr/bin"),
+        ]
+        .map(|(k, v)| (k.to_owned(), v.to_owned()));
+        let env = env_overrides(vars.into_iter());
+        let defaults = Value::try_from(Config::default()).unwrap();
+        let empty = Value::Table(Table::new());
+        assert!(check_unknown(&defaults, "config.test.toml", &empty, &env).is_empty());
+        let mut layered = defaults;
+        merge(&mut layered, env);
+        let cfg: Config = layered.try_into().unwrap();
+        assert_eq!(cfg.web.address, "127.0.0.1:9090");
+        assert_eq!(cfg.web.rate_limit.ip_burst, 3);
+        assert!(!cfg.web.tls.enabled);
+        assert_eq!(cfg.log.level, "DEBUG");
+        // 字符串项即使看起来像数字或时间也保持字符串
+        assert_eq!(cfg.database.password, "123456");
+        assert_eq!(cfg.mail.from, "1979-05-27T07:32:00Z");
+        // 未覆盖的配置项保持默认值
+        assert_eq!(
+            cfg.web.session_max_age,
+            Config::default().web.session_max_age
+        );
+
+        // 拼错的环境变量报错
+        let vars =
+            [("TOYS_WEB__ADRESS", "127.0.0.1:9090")].map(|(k, v)| (k.to_owned(), v.to_owned()));
+        let env = env_overrides(vars.into_iter());
+        let errors = check_unknown(
+            &Value::try_from(Config::default()).unwrap(),
+            "config.test.toml",
+            &empty,
+            &env,
+        );
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].field, "web.adress");
+        assert_eq!(
+            errors[0].message,
+            "环境变量TOYS_WEB__ADRESS对应未知的配置项"
+        );
+    }
+}
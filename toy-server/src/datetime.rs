@@ -0,0 +1,46 @@
+/// UTC时间，如20261019-120000，用作配置历史版本的id等
+pub(crate) fn timestamp(secs: u64) -> String {
+    let (days, rem) = ((secs / 86400) as i64, secs % 86400);
+    // 把1970-01-01起的天数换算为公历日期
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z.rem_euclid(146097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}",
+        rem / 3600,
+        rem % 3600 / 60,
+        rem % 60
+    )
+}
+
+/// 把timestamp的结果转为2026-10-19 12:00:00的形式
+pub(crate) fn readable(id: &str) -> String {
+    format!(
+        "{}-{}-{} {}:{}:{}",
+        &id[0..4],
+        &id[4..6],
+        &id[6..8],
+        &id[9..11],
+        &id[11..13],
+        &id[13..15]
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use crate::datetime::{readable, timestamp};
+
+    #[test]
+    fn utc_format() {
+        assert_eq!(timestamp(0), "19700101-000000");
+        assert_eq!(timestamp(951868799), "20000229-235959");
+        assert_eq!(timestamp(1792411200), "20261019-120000");
+        assert_eq!(readable(&timestamp(1792411200)), "2026-10-19 12:00:00");
+    }
+}
@@ -1,34 +1,78 @@
+use deadpool::managed::PoolError;
+use std::error::Error as StdError;
+
 use poem::error::ResponseError;
-use poem::http::StatusCode;
+use poem::http::{header, HeaderValue, StatusCode};
+use poem::{IntoResponse, Response};
 use log::error;
 
+use toy_schema::error::{ApiError, FieldError};
+
+use crate::web::speedy_data::Speedy;
+
 #[derive(thiserror::Error, Debug)]
 pub enum Error {
     #[error("系统异常")]
     InternalServerErr,
     #[error("请登录后再试")]
     UnAuthenticated,
-    // #[error("您无此功能权限")]
-    // UnAuthorized,
+    #[error("您无此功能权限")]
+    UnAuthorized,
     #[error("数据库异常")]
     DbException(#[from] surrealdb::Error),
+    #[error("数据库连接池异常")]
+    DbPool(#[from] PoolError<surrealdb::Error>),
+    #[error("会话数据异常，请重新登录")]
+    SessionCorrupted,
+    #[error("会话文件读写异常")]
+    SessionIo(#[from] std::io::Error),
+    #[error("配置文件读写异常")]
+    ConfigIo(std::io::Error),
 
     /// Invalid content type.
-    #[error("invalid content type `{0}`, expect: `application/octet-stream`")]
+    #[error(
+        "invalid content type `{0}`, expect: `application/octet-stream` or `application/json`"
+    )]
     InvalidContentType(String),
 
     /// `Content-Type` header is required.
-    #[error("expect content type `application/octet-stream`")]
+    #[error("expect content type `application/octet-stream` or `application/json`")]
     ContentTypeRequired,
 
     /// Url decode error.
     #[error("parse error: {0}")]
     Parse(#[from] speedy::Error),
 
+    /// JSON body decode error.
+    #[error("parse error: {0}")]
+    ParseJson(#[from] serde_json::Error),
+
+    /// 客户端的协议版本过旧，服务端已无法解码
+    #[error("客户端版本{0}已不受支持，请刷新页面")]
+    ProtocolMismatch(String),
+
     #[error("注册失败")]
     SignUpFail,
     #[error("登录失败")]
     SignInFail,
+    #[error("密码错误")]
+    PasswordMismatch,
+    #[error("邮箱格式有误或已被使用")]
+    ProfileUpdateFail,
+    #[error("重置令牌无效或已过期")]
+    ResetTokenInvalid,
+    #[error("邮件发送失败")]
+    MailFail,
+    #[error("验证码错误")]
+    TotpInvalid,
+    #[error("两步验证已启用，请先关闭再重新绑定")]
+    TotpEnabled,
+    #[error("提交的信息有误")]
+    Validation(Vec<FieldError>),
+    #[error("请求过于频繁，请{0}秒后再试")]
+    TooManyRequests(u64),
+    #[error("登录失败次数过多，账户已临时锁定，请{0}秒后再试")]
+    AccountLocked(u64),
 
     #[error("数独游戏数字异常: {0}")]
     SudokuNumInvalid(u16),
@@ -38,21 +82,100 @@ pub enum Error {
     SudokuUnsolvable,
 }
 
+impl Error {
+    /// 机器可读的错误码，客户端据此判断错误类型，不随提示文字变化
+    pub(crate) fn code(&self) -> &'static str {
+        match self {
+            Error::InternalServerErr => "internal",
+            Error::UnAuthenticated => "unauthenticated",
+            Error::UnAuthorized => "forbidden",
+            Error::DbException(_) => "database",
+            Error::DbPool(_) => "database_unavailable",
+            Error::SessionCorrupted => "session_corrupted",
+            Error::SessionIo(_) => "session_io",
+            Error::ConfigIo(_) => "config_io",
+            Error::InvalidContentType(_) => "invalid_content_type",
+            Error::ContentTypeRequired => "content_type_required",
+            Error::Parse(_) => "parse",
+            Error::ParseJson(_) => "parse",
+            Error::ProtocolMismatch(_) => "protocol_mismatch",
+            Error::SignUpFail => "sign_up_failed",
+            Error::SignInFail => "sign_in_failed",
+            Error::PasswordMismatch => "password_mismatch",
+            Error::ProfileUpdateFail => "profile_update_failed",
+            Error::ResetTokenInvalid => "reset_token_invalid",
+            Error::MailFail => "mail_failed",
+            Error::TotpInvalid => "totp_invalid",
+            Error::TotpEnabled => "totp_enabled",
+            Error::Validation(_) => "validation",
+            Error::TooManyRequests(_) => "too_many_requests",
+            Error::AccountLocked(_) => "account_locked",
+            Error::SudokuNumInvalid(_) => "sudoku_num_invalid",
+            Error::SudokuNumErr(_, _, _) => "sudoku_num_conflict",
+            Error::SudokuUnsolvable => "sudoku_unsolvable",
+        }
+    }
+
+    pub(crate) fn api_error(&self) -> ApiError {
+        ApiError {
+            code: self.code().to_owned(),
+            message: self.to_string(),
+            details: match self {
+                Error::Validation(details) => details.clone(),
+                _ => Vec::new(),
+            },
+        }
+    }
+}
+
 impl ResponseError for Error {
     fn status(&self) -> StatusCode {
         match self {
             Error::InternalServerErr => StatusCode::INTERNAL_SERVER_ERROR,
             Error::SignUpFail => StatusCode::UNAUTHORIZED,
             Error::SignInFail => StatusCode::UNAUTHORIZED,
+            Error::PasswordMismatch => StatusCode::FORBIDDEN,
+            Error::ProfileUpdateFail => StatusCode::BAD_REQUEST,
+            Error::ResetTokenInvalid => StatusCode::BAD_REQUEST,
+            Error::MailFail => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::TotpInvalid => StatusCode::FORBIDDEN,
+            Error::TotpEnabled => StatusCode::CONFLICT,
+            Error::Validation(_) => StatusCode::BAD_REQUEST,
+            Error::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
+            Error::AccountLocked(_) => StatusCode::TOO_MANY_REQUESTS,
             Error::UnAuthenticated => StatusCode::UNAUTHORIZED,
-            // Error::UnAuthorized => StatusCode::UNAUTHORIZED,
+            // 401会让前端回到登录页，无权限用403
+            Error::UnAuthorized => StatusCode::FORBIDDEN,
             Error::DbException(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::DbPool(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Error::SessionCorrupted => StatusCode::UNAUTHORIZED,
+            Error::SessionIo(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            Error::ConfigIo(_) => StatusCode::INTERNAL_SERVER_ERROR,
             Error::InvalidContentType(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Error::ContentTypeRequired => StatusCode::UNSUPPORTED_MEDIA_TYPE,
             Error::Parse(_) => StatusCode::BAD_REQUEST,
+            Error::ParseJson(_) => StatusCode::BAD_REQUEST,
+            Error::ProtocolMismatch(_) => StatusCode::BAD_REQUEST,
             Error::SudokuNumInvalid(_) => StatusCode::BAD_REQUEST,
             Error::SudokuNumErr(_, _, _) => StatusCode::BAD_REQUEST,
             Error::SudokuUnsolvable => StatusCode::BAD_REQUEST,
         }
     }
+
+    fn as_response(&self) -> Response
+    where
+        Self: StdError + Send + Sync + 'static,
+    {
+        #[cfg(feature = "metrics")]
+        crate::metrics::error(self.code());
+        // 按协商出的格式返回ApiError
+        let mut resp = Speedy(self.api_error()).into_response();
+        resp.set_status(self.status());
+        // 限流时告诉客户端多少秒后重试
+        if let Error::TooManyRequests(secs) | Error::AccountLocked(secs) = self {
+            resp.headers_mut()
+                .insert(header::RETRY_AFTER, HeaderValue::from(*secs));
+        }
+        resp
+    }
 }
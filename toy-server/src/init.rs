@@ -1,6 +1,7 @@
 use crate::config;
-use crate::config::{Config, GLOBAL_CONFIG};
+use crate::config::{Config, TraceCfg, TraceProtocol, GLOBAL_CONFIG};
 use arc_swap::access::Access;
+use fastrace::collector::Reporter;
 use fastrace_opentelemetry::OpenTelemetryReporter;
 use log::LevelFilter;
 use logforth::append;
@@ -11,39 +12,110 @@ use logforth::layout::TextLayout;
 use logforth::non_blocking::WorkerGuard;
 use opentelemetry::trace::SpanKind;
 use opentelemetry::InstrumentationScope;
-use opentelemetry_otlp::{Protocol, SpanExporter, WithExportConfig, WithTonicConfig};
+use opentelemetry_otlp::{
+    Protocol, SpanExporter, WithExportConfig, WithHttpConfig, WithTonicConfig,
+};
 use opentelemetry_sdk::Resource;
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io::{self, Write};
+use std::path::Path;
 use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
 use std::time::Duration;
-use tonic::metadata::MetadataMap;
+use tonic::metadata::{MetadataKey, MetadataMap};
 
 const SERVICE_NAME: &str = env!("CARGO_PKG_NAME");
+
+static LOG_FILE: OnceLock<LogFile> = OnceLock::new();
+// 是否已设置链路追踪的上报
+static TRACING: AtomicBool = AtomicBool::new(false);
+
+/// 滚动日志文件，重新加载配置时可以换到新的目录
+struct LogFile(Mutex<RollingFileWriter>);
+
+impl Write for &'static LogFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.lock().unwrap().flush()
+    }
+}
+
+fn rolling_writer(directory: &str) -> io::Result<RollingFileWriter> {
+    RollingFileWriter::builder()
+        .rotation(Rotation::Daily)
+        .filename_prefix(SERVICE_NAME)
+        .filename_suffix("log")
+        .max_file_size(128 * 1024 * 1024)
+        .build(directory)
+        .map_err(io::Error::other)
+}
+
+/// 修改日志级别，立即生效
+pub(crate) fn set_log_level(level: &str) -> bool {
+    match LevelFilter::from_str(level) {
+        Ok(level) => {
+            log::set_max_level(level);
+            true
+        }
+        Err(_) => {
+            log::error!("日志级别有误: {level}");
+            false
+        }
+    }
+}
+
+/// 在新的目录中重新打开日志文件
+pub(crate) fn reopen_log(directory: &str) -> bool {
+    let Some(file) = LOG_FILE.get() else {
+        return false;
+    };
+    match rolling_writer(directory) {
+        Ok(writer) => {
+            *file.0.lock().unwrap() = writer;
+            log::info!("日志文件已切换到 {directory}");
+            true
+        }
+        Err(e) => {
+            log::error!("日志目录 {directory} 打开失败: {e}");
+            false
+        }
+    }
+}
+
 pub(crate) fn init_log() -> WorkerGuard {
-    //加载配置
-    config::reload();
+    //加载配置，有误时列出所有错误后退出
+    if let Err(errors) = config::reload() {
+        for e in errors {
+            eprintln!("配置有误 {}: {}", e.field, e.message);
+        }
+        std::process::exit(1);
+    }
     let cfg = GLOBAL_CONFIG
         .get()
         .unwrap()
         .map(|cfg: &Config| &cfg.log)
         .load();
 
-    let rolling_writer = RollingFileWriter::builder()
-        .rotation(Rotation::Daily)
-        .filename_prefix(SERVICE_NAME)
-        .filename_suffix("log")
-        .max_file_size(128 * 1024 * 1024)
-        .build(&cfg.directory)
-        .unwrap();
-
-    let (non_blocking, guard) = rolling_file::non_blocking(rolling_writer)
+    let file = LOG_FILE.get_or_init(|| match rolling_writer(&cfg.directory) {
+        Ok(writer) => LogFile(Mutex::new(writer)),
+        Err(e) => {
+            eprintln!("日志目录 {} 打开失败: {e}", cfg.directory);
+            std::process::exit(1);
+        }
+    });
+    let (non_blocking, guard) = rolling_file::non_blocking(file)
         .shutdown_timeout(Duration::from_secs(10))
         .finish();
 
-    let level = LevelFilter::from_str(&cfg.level).unwrap();
+    // 日志级别由log::set_max_level控制，以便重新加载配置时修改
     logforth::builder()
         .dispatch(|d| {
-            d.filter(level)
+            d.filter(LevelFilter::Trace)
                 .diagnostic(FastraceDiagnostic::default())
                 .append(
                     RollingFile::new(non_blocking).with_layout(TextLayout::default().no_color()),
@@ -54,38 +126,175 @@ pub(crate) fn init_log() -> WorkerGuard {
                 .append(append::FastraceEvent::default())
         })
         .apply();
+    // 级别已在加载配置时校验
+    set_log_level(&cfg.level);
+    let file = config::file();
+    if !Path::new(&file).exists() {
+        log::warn!("配置文件{file}不存在，使用默认配置");
+    }
 
     guard
 }
 
+/// 按[trace]配置上报链路追踪，没有配置collector或初始化失败时不上报，不影响服务启动
 pub(crate) fn init_trace() {
-    let mut metadata = MetadataMap::with_capacity(3);
-    metadata.insert(
-        "authorization",
-        "Basic cm9vdEBleGFtcGxlLmNvbTpEbDF4RFAwWGlzanR0UEZa"
-            .parse()
-            .unwrap(),
+    let cfg = GLOBAL_CONFIG
+        .get()
+        .unwrap()
+        .map(|cfg: &Config| &cfg.trace)
+        .load();
+    let exporter = match exporter(&cfg) {
+        Ok(Some(exporter)) => exporter,
+        Ok(None) => {
+            log::info!("未配置链路追踪的collector，不上报");
+            return;
+        }
+        Err(e) => {
+            log::error!("链路追踪初始化失败，不上报: {e}");
+            return;
+        }
+    };
+
+    fastrace::set_reporter(
+        reporter(exporter),
+        fastrace::collector::Config::default().report_interval(Duration::ZERO),
+    );
+    TRACING.store(true, Ordering::Relaxed);
+    log::info!(
+        "链路追踪以{:?}上报到{}，采样比例{}",
+        cfg.protocol,
+        cfg.endpoint,
+        cfg.sample_ratio
     );
-    metadata.insert("organization", "default".parse().unwrap());
-    metadata.insert("stream-name", SERVICE_NAME.parse().unwrap());
-
-    let reporter = OpenTelemetryReporter::new(
-        SpanExporter::builder()
-            .with_tonic()
-            .with_endpoint("http://localhost:5081")
-            .with_metadata(metadata)
-            .with_protocol(Protocol::Grpc)
-            .build()
-            .expect("initialize oltp exporter"),
+}
+
+/// 是否在上报链路，上游请求已采样时据此决定是否记录
+pub(crate) fn tracing() -> bool {
+    TRACING.load(Ordering::Relaxed)
+}
+
+/// 新的请求是否记录链路，按配置的比例采样，重新加载配置后立即生效
+pub(crate) fn sampled() -> bool {
+    tracing() && rand::random::<f64>() < GLOBAL_CONFIG.get().unwrap().load().trace.sample_ratio
+}
+
+fn exporter(cfg: &TraceCfg) -> Result<Option<SpanExporter>, String> {
+    if !cfg.enabled || cfg.endpoint.is_empty() {
+        return Ok(None);
+    }
+    let timeout = Duration::from_secs(cfg.timeout);
+    let exporter = match cfg.protocol {
+        TraceProtocol::Grpc => {
+            let mut metadata = MetadataMap::with_capacity(cfg.headers.len());
+            for (name, value) in &cfg.headers {
+                match (MetadataKey::from_bytes(name.as_bytes()), value.parse()) {
+                    (Ok(name), Ok(value)) => {
+                        metadata.insert(name, value);
+                    }
+                    _ => log::warn!("链路追踪的头{name}有误，已忽略"),
+                }
+            }
+            SpanExporter::builder()
+                .with_tonic()
+                .with_endpoint(&cfg.endpoint)
+                .with_metadata(metadata)
+                .with_timeout(timeout)
+                .with_protocol(Protocol::Grpc)
+                .build()
+        }
+        TraceProtocol::Http => SpanExporter::builder()
+            .with_http()
+            .with_endpoint(&cfg.endpoint)
+            .with_headers(HashMap::from_iter(cfg.headers.clone()))
+            .with_timeout(timeout)
+            .with_protocol(Protocol::HttpBinary)
+            .build(),
+    };
+    exporter.map(Some).map_err(|e| e.to_string())
+}
+
+fn reporter(exporter: SpanExporter) -> impl Reporter {
+    OpenTelemetryReporter::new(
+        exporter,
         SpanKind::Server,
         Cow::Owned(Resource::builder().with_service_name(SERVICE_NAME).build()),
         InstrumentationScope::builder(SERVICE_NAME)
             .with_version(env!("CARGO_PKG_VERSION"))
             .build(),
-    );
+    )
+}
 
-    fastrace::set_reporter(
-        reporter,
-        fastrace::collector::Config::default().report_interval(Duration::ZERO),
-    );
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use fastrace::collector::{Reporter, SpanId, SpanRecord, TraceId};
+    use poem::endpoint::make;
+    use poem::listener::{Acceptor, Listener, TcpListener};
+    use poem::{post, Request, Route, Server};
+    use tokio::sync::mpsc;
+
+    use crate::config::{TraceCfg, TraceProtocol};
+    use crate::init::{exporter, reporter};
+
+    #[test]
+    fn no_collector() {
+        assert!(exporter(&TraceCfg::default()).unwrap().is_none());
+        let cfg = TraceCfg {
+            enabled: false,
+            endpoint: "http://localhost:4317".to_owned(),
+            ..TraceCfg::default()
+        };
+        assert!(exporter(&cfg).unwrap().is_none());
+    }
+
+    /// 用本地的OTLP/HTTP接收端代替collector，检查上报的地址和头
+    #[tokio::test(flavor = "multi_thread")]
+    async fn export_to_local_collector() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let acceptor = TcpListener::bind("127.0.0.1:0")
+            .into_acceptor()
+            .await
+            .unwrap();
+        let addr = acceptor.local_addr()[0].as_socket_addr().cloned().unwrap();
+        let route = Route::new().at(
+            "/v1/traces",
+            post(make(move |req: Request| {
+                let tx = tx.clone();
+                async move {
+                    let auth = req.header("authorization").unwrap_or_default().to_owned();
+                    let body = req.into_body().into_vec().await.unwrap_or_default();
+                    tx.send((auth, body.len())).unwrap();
+                }
+            })),
+        );
+        tokio::spawn(Server::new_with_acceptor(acceptor).run(route));
+
+        let cfg = TraceCfg {
+            endpoint: format!("http://{addr}/v1/traces"),
+            protocol: TraceProtocol::Http,
+            headers: BTreeMap::from([("authorization".to_owned(), "Basic dGVzdA==".to_owned())]),
+            ..TraceCfg::default()
+        };
+        let mut reporter = reporter(exporter(&cfg).unwrap().unwrap());
+        // 上报时阻塞等待HTTP响应，不能在异步运行时的线程中进行
+        tokio::task::spawn_blocking(move || {
+            reporter.report(vec![SpanRecord {
+                trace_id: TraceId(1),
+                span_id: SpanId(1),
+                name: "test".into(),
+                ..SpanRecord::default()
+            }])
+        })
+        .await
+        .unwrap();
+
+        let (auth, len) = tokio::time::timeout(Duration::from_secs(10), rx.recv())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(auth, "Basic dGVzdA==");
+        assert!(len > 0);
+    }
 }
@@ -1,18 +1,62 @@
+use std::env;
+
 use crate::config::GLOBAL_CONFIG;
 use crate::init::{init_log, init_trace};
+use crate::web::{account, database};
 use futures::FutureExt;
 use rustls::crypto::ring;
 use tokio::signal::ctrl_c;
 
 mod config;
+mod datetime;
 mod error;
 mod init;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod migration;
 mod web;
 
 #[tokio::main]
 async fn main() {
     ring::default_provider().install_default().expect("Failed to install rustls crypto provider");
+    let args: Vec<String> = env::args().skip(1).collect();
+
+    // toy-server --check-config 只校验配置，列出所有错误
+    if args.first().is_some_and(|cmd| cmd == "--check-config") {
+        match config::load() {
+            Ok(_) => println!("配置有效: {}", config::file()),
+            Err(errors) => {
+                for e in errors {
+                    eprintln!("配置有误 {}: {}", e.field, e.message);
+                }
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
     let _guard = init_log();
+
+    // toy-server migrate [list | up [版本] | down [步数]]
+    if args.first().is_some_and(|cmd| cmd == "migrate") {
+        database::init(&GLOBAL_CONFIG.get().unwrap().load().database);
+        if let Err(e) = migration::cli(&args[1..]).await {
+            eprintln!("数据库迁移失败: {e:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // toy-server role <邮箱> <admin | user>
+    if args.first().is_some_and(|cmd| cmd == "role") {
+        database::init(&GLOBAL_CONFIG.get().unwrap().load().database);
+        if let Err(e) = account::cli_role(&args[1..]).await {
+            eprintln!("设置角色失败: {e:?}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     init_trace();
     web::start(ctrl_c().map(|_| ())).await;
 }
@@ -0,0 +1,176 @@
+use std::collections::HashSet;
+use std::sync::{Arc, LazyLock};
+use std::time::{Duration, Instant};
+
+use poem::http::{header, Method};
+use poem::{handler, Endpoint, IntoResponse, Middleware, Request, Response, Result};
+use prometheus::{
+    register_histogram, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    Encoder, Histogram, HistogramVec, IntCounterVec, IntGauge, TextEncoder,
+};
+use log::{error, warn};
+
+use crate::config::GLOBAL_CONFIG;
+use crate::web::{database, login};
+
+static HTTP_REQUESTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "toys_http_requests_total",
+        "接口请求数",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+static HTTP_DURATION: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "toys_http_request_duration_seconds",
+        "接口响应时间（秒）",
+        &["method", "route", "status"]
+    )
+    .unwrap()
+});
+static ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("toys_errors_total", "接口返回的错误数，按错误码", &["code"]).unwrap()
+});
+static SUDOKU_SOLVE: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "toys_sudoku_solve_seconds",
+        "数独求解时间（秒）",
+        vec![0.0001, 0.0005, 0.001, 0.005, 0.01, 0.05, 0.1, 0.5, 1.0, 5.0]
+    )
+    .unwrap()
+});
+static SUDOKU_DEPTH: LazyLock<Histogram> = LazyLock::new(|| {
+    register_histogram!(
+        "toys_sudoku_backtrack_depth",
+        "数独枚举的最大回溯深度，无需枚举时为0",
+        vec![0.0, 1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0]
+    )
+    .unwrap()
+});
+static ACTIVE_SESSIONS: LazyLock<IntGauge> = LazyLock::new(|| {
+    register_int_gauge!("toys_active_sessions", "session有效期内有活动的登录数").unwrap()
+});
+static DB_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "toys_db_connection_errors_total",
+        "从连接池取数据库连接失败的次数",
+        &["pool"]
+    )
+    .unwrap()
+});
+
+/// 注册所有指标，还没有发生过的也以0导出
+pub(crate) fn init() {
+    LazyLock::force(&HTTP_REQUESTS);
+    LazyLock::force(&HTTP_DURATION);
+    LazyLock::force(&ERRORS);
+    LazyLock::force(&SUDOKU_SOLVE);
+    LazyLock::force(&SUDOKU_DEPTH);
+    LazyLock::force(&ACTIVE_SESSIONS);
+    for pool in ["user", "root"] {
+        DB_ERRORS.with_label_values(&[pool]);
+    }
+}
+
+pub(crate) fn error(code: &str) {
+    ERRORS.with_label_values(&[code]).inc();
+}
+
+pub(crate) fn sudoku_solved(elapsed: Duration, depth: usize) {
+    SUDOKU_SOLVE.observe(elapsed.as_secs_f64());
+    SUDOKU_DEPTH.observe(depth as f64);
+}
+
+pub(crate) fn db_error(pool: &str) {
+    DB_ERRORS.with_label_values(&[pool]).inc();
+}
+
+/// Prometheus文本格式的指标，抓取时统计活跃的登录数
+#[handler]
+pub(crate) async fn export() -> Response {
+    let max_age = Duration::from_secs(GLOBAL_CONFIG.get().unwrap().load().web.session_max_age);
+    match database::exclusive(login::count_active(max_age)).await {
+        Ok(count) => ACTIVE_SESSIONS.set(count as i64),
+        Err(e) => warn!("统计活跃登录数失败：{e:?}"),
+    }
+
+    let encoder = TextEncoder::new();
+    let mut body = Vec::new();
+    if let Err(e) = encoder.encode(&prometheus::gather(), &mut body) {
+        error!("指标编码失败：{e}");
+    }
+    Response::builder()
+        .header(header::CONTENT_TYPE, encoder.format_type())
+        .body(body)
+}
+
+/// 按路由和状态码统计接口的请求数和响应时间
+pub struct Metrics {
+    routes: Arc<HashSet<&'static str>>,
+}
+
+impl Metrics {
+    /// routes是登记过的接口路径，其他路径都归为unmatched
+    pub fn new(routes: impl IntoIterator<Item = &'static str>) -> Self {
+        Self {
+            routes: Arc::new(routes.into_iter().collect()),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Metrics {
+    type Output = MetricsEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        MetricsEndpoint {
+            ep,
+            routes: self.routes.clone(),
+        }
+    }
+}
+
+pub struct MetricsEndpoint<E> {
+    ep: E,
+    routes: Arc<HashSet<&'static str>>,
+}
+
+/// 方法和路径都来自客户端，只取固定的取值，避免标签无限增长
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::PATCH => "PATCH",
+        Method::HEAD => "HEAD",
+        Method::OPTIONS => "OPTIONS",
+        _ => "OTHER",
+    }
+}
+
+impl<E: Endpoint> Endpoint for MetricsEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let method = method_label(req.method());
+        let route = self
+            .routes
+            .get(req.uri().path())
+            .copied()
+            .unwrap_or("unmatched");
+        let start = Instant::now();
+        let res = self.ep.call(req).await.map(IntoResponse::into_response);
+
+        let status = match &res {
+            Ok(resp) => resp.status(),
+            Err(e) => e.status(),
+        };
+        let labels = [method, route, status.as_str()];
+        HTTP_REQUESTS.with_label_values(&labels).inc();
+        HTTP_DURATION
+            .with_label_values(&labels)
+            .observe(start.elapsed().as_secs_f64());
+        res
+    }
+}
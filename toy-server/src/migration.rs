@@ -0,0 +1,240 @@
+use std::process;
+use std::str::FromStr;
+
+use log::{info, warn};
+use serde::Deserialize;
+
+use crate::error::Error;
+use crate::web::database;
+
+/// 数据库迁移，按版本号顺序执行，up/down脚本内置在可执行文件中
+struct Migration {
+    version: u32,
+    name: &'static str,
+    up: &'static str,
+    down: &'static str,
+}
+
+const USAGE: &str = "用法：toy-server migrate [list | up [版本] | down [步数]]";
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "user",
+        up: include_str!("../migrations/0001_user.up.surql"),
+        down: include_str!("../migrations/0001_user.down.surql"),
+    },
+    Migration {
+        version: 2,
+        name: "session",
+        up: include_str!("../migrations/0002_session.up.surql"),
+        down: include_str!("../migrations/0002_session.down.surql"),
+    },
+    Migration {
+        version: 3,
+        name: "session_expiry",
+        up: include_str!("../migrations/0003_session_expiry.up.surql"),
+        down: include_str!("../migrations/0003_session_expiry.down.surql"),
+    },
+    Migration {
+        version: 4,
+        name: "session_object",
+        up: include_str!("../migrations/0004_session_object.up.surql"),
+        down: include_str!("../migrations/0004_session_object.down.surql"),
+    },
+    Migration {
+        version: 5,
+        name: "login",
+        up: include_str!("../migrations/0005_login.up.surql"),
+        down: include_str!("../migrations/0005_login.down.surql"),
+    },
+    Migration {
+        version: 6,
+        name: "password_reset",
+        up: include_str!("../migrations/0006_password_reset.up.surql"),
+        down: include_str!("../migrations/0006_password_reset.down.surql"),
+    },
+    Migration {
+        version: 7,
+        name: "user_role",
+        up: include_str!("../migrations/0007_user_role.up.surql"),
+        down: include_str!("../migrations/0007_user_role.down.surql"),
+    },
+    Migration {
+        version: 8,
+        name: "totp",
+        up: include_str!("../migrations/0008_totp.up.surql"),
+        down: include_str!("../migrations/0008_totp.down.surql"),
+    },
+    Migration {
+        version: 9,
+        name: "api_token",
+        up: include_str!("../migrations/0009_api_token.up.surql"),
+        down: include_str!("../migrations/0009_api_token.down.surql"),
+    },
+];
+
+const INIT_MIGRATION_TABLE: &str = "
+DEFINE TABLE IF NOT EXISTS migration SCHEMAFULL PERMISSIONS NONE;
+DEFINE FIELD IF NOT EXISTS name ON migration TYPE string;
+DEFINE FIELD IF NOT EXISTS applied_at ON migration TYPE datetime;
+";
+
+#[derive(Deserialize)]
+struct Applied {
+    version: u32,
+    applied_at: String,
+}
+
+async fn applied() -> Result<Vec<Applied>, Error> {
+    let db = database::root().await?;
+    db.query(INIT_MIGRATION_TABLE).await?.check()?;
+    let applied = db
+        .query("SELECT record::id(id) AS version, <string> applied_at AS applied_at FROM migration ORDER BY version")
+        .await?
+        .take(0)?;
+    Ok(applied)
+}
+
+/// 执行未应用的迁移，直到目标版本（默认最新）
+pub(crate) async fn apply(target: Option<u32>) -> Result<(), Error> {
+    let applied = applied().await?;
+    let db = database::root().await?;
+    for m in MIGRATIONS {
+        if target.is_some_and(|t| m.version > t) || applied.iter().any(|a| a.version == m.version) {
+            continue;
+        }
+
+        info!("执行数据库迁移 {:04}_{}", m.version, m.name);
+        db.query(format!(
+            "BEGIN TRANSACTION;\n{}\nCREATE type::thing('migration', $version) SET name = $name, applied_at = time::now();\nCOMMIT TRANSACTION;",
+            m.up
+        ))
+        .bind(("version", m.version))
+        .bind(("name", m.name))
+        .await?
+        .check()?;
+    }
+    Ok(())
+}
+
+/// 按版本倒序回滚最近应用的若干个迁移
+pub(crate) async fn rollback(steps: usize) -> Result<(), Error> {
+    let applied = applied().await?;
+    let db = database::root().await?;
+    for a in applied.iter().rev().take(steps) {
+        let Some(m) = MIGRATIONS.iter().find(|m| m.version == a.version) else {
+            warn!("未知的数据库迁移版本 {}，跳过", a.version);
+            continue;
+        };
+
+        info!("回滚数据库迁移 {:04}_{}", m.version, m.name);
+        db.query(format!(
+            "BEGIN TRANSACTION;\n{}\nDELETE type::thing('migration', $version);\nCOMMIT TRANSACTION;",
+            m.down
+        ))
+        .bind(("version", m.version))
+        .await?
+        .check()?;
+    }
+    Ok(())
+}
+
+/// 命令行：toy-server migrate [list | up [版本] | down [步数]]
+pub(crate) async fn cli(args: &[String]) -> Result<(), Error> {
+    match args.first().map(String::as_str) {
+        None | Some("list") => {
+            let applied = applied().await?;
+            for m in MIGRATIONS {
+                let state = applied
+                    .iter()
+                    .find(|a| a.version == m.version)
+                    .map_or("未应用".to_owned(), |a| {
+                        format!("已应用 {}", a.applied_at)
+                    });
+                println!("{:04}_{:<16}{state}", m.version, m.name);
+            }
+        }
+        Some("up") => apply(number(args.get(1))).await?,
+        Some("down") => rollback(number(args.get(1)).unwrap_or(1)).await?,
+        Some(other) => usage(&format!("未知的迁移命令：{other}")),
+    }
+    Ok(())
+}
+
+/// 可选的数字参数，不是数字时提示用法后退出，避免误执行全部迁移
+fn number<T: FromStr>(arg: Option<&String>) -> Option<T> {
+    let arg = arg?;
+    match arg.parse() {
+        Ok(n) => Some(n),
+        Err(_) => usage(&format!("参数应为数字：{arg}")),
+    }
+}
+
+fn usage(message: &str) -> ! {
+    eprintln!("{message}");
+    eprintln!("{USAGE}");
+    process::exit(2);
+}
+
+#[cfg(test)]
+mod test {
+    use crate::migration::{applied, apply, rollback, MIGRATIONS};
+    use crate::web::database::{self, testing};
+
+    async fn versions() -> Vec<u32> {
+        applied()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|a| a.version)
+            .collect()
+    }
+
+    async fn tables() -> Vec<String> {
+        let db = database::root().await.unwrap();
+        let mut tables: Vec<String> = db
+            .query("RETURN object::keys((INFO FOR DB).tables)")
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        tables.sort();
+        tables
+    }
+
+    #[test]
+    fn apply_and_rollback() {
+        testing::run(async {
+            let all: Vec<u32> = MIGRATIONS.iter().map(|m| m.version).collect();
+            assert_eq!(versions().await, all);
+            // 已应用的不再执行
+            apply(None).await.unwrap();
+            assert_eq!(versions().await, all);
+
+            rollback(2).await.unwrap();
+            assert_eq!(versions().await, all[..all.len() - 2]);
+            assert!(!tables().await.contains(&"api_token".to_owned()));
+
+            rollback(MIGRATIONS.len()).await.unwrap();
+            assert!(versions().await.is_empty());
+            assert_eq!(tables().await, ["migration"]);
+
+            apply(Some(5)).await.unwrap();
+            assert_eq!(versions().await, [1, 2, 3, 4, 5]);
+            apply(None).await.unwrap();
+            assert_eq!(versions().await, all);
+            assert_eq!(
+                tables().await,
+                [
+                    "api_token",
+                    "login",
+                    "migration",
+                    "password_reset",
+                    "session",
+                    "user"
+                ]
+            );
+        });
+    }
+}
@@ -0,0 +1,485 @@
+use std::process;
+use std::sync::Arc;
+
+use poem::session::Session;
+use poem::web::Data;
+use poem::handler;
+use serde::Deserialize;
+use surrealdb::RecordId;
+use log::{error, info, warn};
+
+use toy_schema::error::FieldError;
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{
+    ChangePasswordReq, DeleteAccountReq, ForgotPasswordReq, Profile, ResetPasswordReq, Role,
+};
+
+use crate::error::Error;
+use crate::web::database::{self, DbConn, Traced};
+use crate::web::login::LOGIN_KEY;
+use crate::web::mailer::{self, Mail};
+use crate::web::rate_limit;
+use crate::web::speedy_data::Speedy;
+use crate::GLOBAL_CONFIG;
+
+const VERIFY_PASSWORD: &str =
+    "SELECT VALUE crypto::argon2::compare(password, $password) FROM ONLY $auth";
+const CHANGE_PASSWORD: &str = "UPDATE $auth SET password = crypto::argon2::generate($new_password)
+    WHERE crypto::argon2::compare(password, $old_password) RETURN VALUE id";
+// 修改密码后其他设备上的登录全部失效
+const REVOKE_OTHER_LOGINS: &str = "DELETE login WHERE id != type::thing('login', $current)";
+// 删除账户及其名下的所有数据
+const DELETE_ACCOUNT: &str = "BEGIN TRANSACTION;
+DELETE login WHERE user = $auth.id;
+DELETE password_reset WHERE user = $auth.id;
+DELETE api_token WHERE user = $auth.id;
+DELETE $auth;
+COMMIT TRANSACTION;";
+const CREATE_RESET: &str = "DELETE password_reset WHERE user = $user;
+LET $token = rand::string(48);
+CREATE password_reset SET user = $user, token_hash = crypto::sha256($token),
+    expires_at = time::now() + duration::from::secs($ttl) RETURN NONE;
+RETURN $token;";
+const RESET_PASSWORD: &str = "BEGIN TRANSACTION;
+LET $user = (SELECT VALUE user FROM password_reset
+    WHERE token_hash = crypto::sha256($token) AND expires_at > time::now())[0];
+IF !$user { THROW 'invalid reset token' };
+UPDATE $user SET password = crypto::argon2::generate($password);
+DELETE password_reset WHERE user = $user;
+DELETE login WHERE user = $user;
+COMMIT TRANSACTION;";
+const UPDATE_PROFILE: &str = "UPDATE $user SET name = $name, email = $email RETURN NONE";
+const DELETE_EXPIRED_RESETS: &str = "DELETE password_reset WHERE expires_at < time::now()";
+// 角色保存在session中，修改后让该用户重新登录
+const SET_ROLE: &str = "BEGIN TRANSACTION;
+LET $user = (SELECT VALUE id FROM user WHERE email = $email)[0];
+IF !$user { THROW 'user not found' };
+UPDATE $user SET role = $role;
+DELETE login WHERE user = $user;
+COMMIT TRANSACTION;";
+
+#[derive(Deserialize)]
+struct ProfileOwner {
+    id: RecordId,
+    email: String,
+}
+
+#[derive(Deserialize)]
+struct ResetUser {
+    id: RecordId,
+    name: String,
+}
+
+/// 邮箱格式的粗略校验，以便给出字段级的提示，最终以数据库的校验为准
+pub(crate) fn check_email(email: &str) -> Option<FieldError> {
+    let valid = email.split_once('@').is_some_and(|(local, domain)| {
+        !local.is_empty()
+            && !domain.contains('@')
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.ends_with('.')
+    });
+    (!valid).then(|| FieldError::new("email", "邮箱格式有误"))
+}
+
+pub(crate) fn email_taken() -> FieldError {
+    FieldError::new("email", "邮箱已被注册")
+}
+
+/// 邮箱是否已注册，注册前检查以便给出明确的提示
+pub(crate) async fn email_registered(email: &str) -> Result<bool, Error> {
+    let db = database::root().await?;
+    let id: Option<RecordId> = db
+        .query("SELECT VALUE id FROM ONLY user WHERE email = $email LIMIT 1")
+        .bind(("email", email.to_owned()))
+        .traced("db.user.email_registered")
+        .await?
+        .take(0)?;
+    Ok(id.is_some())
+}
+
+#[handler]
+pub async fn profile(db: Data<&Arc<DbConn>>) -> poem::Result<Speedy<Profile>> {
+    let profile: Option<Profile> = db
+        .query("SELECT name, email, totp_secret != NONE AS totp_enabled FROM ONLY $auth")
+        .traced("db.user.profile")
+        .await
+        .and_then(|mut res| res.take(0))
+        .map_err(Error::DbException)?;
+    Ok(Speedy(profile.ok_or(Error::UnAuthenticated)?))
+}
+
+#[handler]
+pub async fn update_profile(
+    req: Speedy<Profile>,
+    db: Data<&Arc<DbConn>>,
+) -> poem::Result<Speedy<Empty>> {
+    let Profile { name, email, .. } = req.0;
+    info!("修改用户资料：{name} {email}");
+    update(&db, name, email).await?;
+    Ok(Speedy(Empty {}))
+}
+
+async fn update(db: &DbConn, name: String, email: String) -> Result<(), Error> {
+    if let Some(invalid) = check_email(&email) {
+        return Err(Error::Validation(vec![invalid]));
+    }
+    let owner: Option<ProfileOwner> = db
+        .query("SELECT id, email FROM ONLY $auth")
+        .traced("db.user.owner")
+        .await?
+        .take(0)?;
+    let owner = owner.ok_or(Error::UnAuthenticated)?;
+    // 与注册一样先检查邮箱以便给出明确的提示，并发修改时仍由唯一索引兜底
+    if owner.email != email && email_registered(&email).await? {
+        return Err(Error::Validation(vec![email_taken()]));
+    }
+
+    // 内嵌数据库取过root连接后用户连接的认证已清除（见database::Exclusive），按id用root连接修改
+    let root = database::root().await?;
+    root.query(UPDATE_PROFILE)
+        .bind(("user", owner.id))
+        .bind(("name", name))
+        .bind(("email", email))
+        .traced("db.user.update_profile")
+        .await
+        .and_then(|res| res.check())
+        .map_err(|e| {
+            warn!("修改用户资料失败：{e}");
+            Error::ProfileUpdateFail
+        })?;
+    Ok(())
+}
+
+#[handler]
+pub async fn change_password(
+    req: Speedy<ChangePasswordReq>,
+    db: Data<&Arc<DbConn>>,
+    session: &Session,
+) -> poem::Result<Speedy<Empty>> {
+    let ChangePasswordReq {
+        old_password,
+        new_password,
+    } = req.0;
+    let changed: Vec<RecordId> = db
+        .query(CHANGE_PASSWORD)
+        .bind(("old_password", old_password))
+        .bind(("new_password", new_password))
+        .traced("db.user.change_password")
+        .await
+        .and_then(|mut res| res.take(0))
+        .map_err(Error::DbException)?;
+    if changed.is_empty() {
+        return Err(Error::PasswordMismatch.into());
+    }
+    info!("密码修改成功");
+
+    let current = session.get::<String>(LOGIN_KEY).unwrap_or_default();
+    db.query(REVOKE_OTHER_LOGINS)
+        .bind(("current", current))
+        .traced("db.login.revoke_others")
+        .await
+        .and_then(|res| res.check())
+        .map_err(Error::DbException)?;
+    Ok(Speedy(Empty {}))
+}
+
+#[handler]
+pub async fn delete_account(
+    req: Speedy<DeleteAccountReq>,
+    db: Data<&Arc<DbConn>>,
+    session: &Session,
+) -> poem::Result<Speedy<Empty>> {
+    delete(&db, req.0.password).await?;
+    session.purge();
+    Ok(Speedy(Empty {}))
+}
+
+/// 验证密码后删除当前用户及其名下的数据，db须是已认证的用户连接
+async fn delete(db: &DbConn, password: String) -> Result<(), Error> {
+    let matched: Option<bool> = db
+        .query(VERIFY_PASSWORD)
+        .bind(("password", password))
+        .traced("db.user.verify_password")
+        .await?
+        .take(0)?;
+    if matched != Some(true) {
+        return Err(Error::PasswordMismatch);
+    }
+
+    db.query(DELETE_ACCOUNT)
+        .traced("db.user.delete")
+        .await?
+        .check()?;
+    warn!("账户已注销");
+    Ok(())
+}
+
+/// 申请重置密码：无论邮箱是否注册都返回成功，避免泄露账户是否存在
+#[handler]
+pub async fn forgot_password(req: Speedy<ForgotPasswordReq>) -> poem::Result<Speedy<Empty>> {
+    let email = req.0.email;
+    // 同一邮箱限流，避免被用来轰炸收件箱
+    rate_limit::limiter().check_reset(&email)?;
+    let db = database::root().await?;
+    let user: Option<ResetUser> = db
+        .query("SELECT id, name FROM ONLY user WHERE email = $email LIMIT 1")
+        .bind(("email", email.clone()))
+        .traced("db.user.find_by_email")
+        .await
+        .and_then(|mut res| res.take(0))
+        .map_err(Error::DbException)?;
+    let Some(user) = user else {
+        warn!("申请重置密码的邮箱未注册：{email}");
+        return Ok(Speedy(Empty {}));
+    };
+
+    let cfg = GLOBAL_CONFIG.get().unwrap().load().mail.clone();
+    let token = create_reset(&db, user.id, cfg.reset_ttl).await?;
+    // 发邮件前归还连接
+    drop(db);
+
+    let mut body = format!(
+        "{}，您好：\n\n我们收到了重置密码的申请，请在{}分钟内使用以下重置令牌设置新密码：\n\n{token}\n",
+        user.name,
+        cfg.reset_ttl / 60
+    );
+    if !cfg.reset_url.is_empty() {
+        body += &format!(
+            "\n或打开链接：{}\n",
+            cfg.reset_url.replace("{token}", &token)
+        );
+    }
+    body += "\n如果不是您本人操作，请忽略本邮件。";
+
+    mailer::mailer()
+        .send(Mail {
+            to: email,
+            subject: "重置密码".to_owned(),
+            body,
+        })
+        .await?;
+    Ok(Speedy(Empty {}))
+}
+
+/// 为用户生成重置令牌，之前的令牌作废，db须是root连接
+async fn create_reset(db: &DbConn, user: RecordId, ttl: u64) -> Result<String, Error> {
+    let token: Option<String> = db
+        .query(CREATE_RESET)
+        .bind(("user", user))
+        .bind(("ttl", ttl))
+        .traced("db.password_reset.create")
+        .await?
+        .take(3)?;
+    token.ok_or(Error::InternalServerErr)
+}
+
+/// 使用重置令牌设置新密码，该用户所有的登录随之失效
+#[handler]
+pub async fn reset_password(req: Speedy<ResetPasswordReq>) -> poem::Result<Speedy<Empty>> {
+    let ResetPasswordReq { token, password } = req.0;
+    reset(token, password).await?;
+    Ok(Speedy(Empty {}))
+}
+
+/// 令牌只能使用一次，过期后无效
+async fn reset(token: String, password: String) -> Result<(), Error> {
+    let db = database::root().await?;
+    db.query(RESET_PASSWORD)
+        .bind(("token", token))
+        .bind(("password", password))
+        .traced("db.password_reset.use")
+        .await
+        .and_then(|res| res.check())
+        .map_err(|e| {
+            error!("重置密码失败：{e}");
+            Error::ResetTokenInvalid
+        })?;
+    info!("密码重置成功");
+    Ok(())
+}
+
+/// 删除过期的重置令牌，由session清理任务调用
+pub(crate) async fn delete_expired_resets() -> Result<(), Error> {
+    let db = database::root().await?;
+    db.query(DELETE_EXPIRED_RESETS)
+        .traced("db.password_reset.delete_expired")
+        .await?
+        .check()?;
+    Ok(())
+}
+
+/// 命令行：toy-server role <邮箱> <admin | user>
+pub(crate) async fn cli_role(args: &[String]) -> Result<(), Error> {
+    let role = match args.get(1).map(String::as_str) {
+        Some("admin") => Role::Admin,
+        Some("user") => Role::User,
+        _ => {
+            eprintln!("用法：toy-server role <邮箱> <admin | user>");
+            process::exit(2);
+        }
+    };
+    let email = args[0].clone();
+
+    let db = database::root().await?;
+    db.query(SET_ROLE)
+        .bind(("email", email.clone()))
+        .bind(("role", role))
+        .await?
+        .check()?;
+    println!("{email} 的角色已设置为 {role:?}，该用户需要重新登录");
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use surrealdb::RecordId;
+
+    use crate::error::Error;
+    use crate::web::account::{check_email, create_reset, delete, reset, update};
+    use crate::web::database::{self, testing, DbConn};
+
+    /// 注册一个用户，返回已认证的用户连接和用户id
+    async fn sign_up(email: &str) -> (DbConn, RecordId) {
+        let db = database::user().await.unwrap();
+        db.signup(database::user_credentials(json!({
+            "name": "测试",
+            "email": email,
+            "password": "secret",
+        })))
+        .await
+        .unwrap();
+        let id: Option<RecordId> = db.query("RETURN $auth.id").await.unwrap().take(0).unwrap();
+        (db, id.unwrap())
+    }
+
+    async fn sign_in(email: &str, password: &str) -> Option<DbConn> {
+        let db = database::user().await.unwrap();
+        db.signin(database::user_credentials(json!({
+            "email": email,
+            "password": password,
+        })))
+        .await
+        .ok()?;
+        Some(db)
+    }
+
+    /// 用root连接统计用户名下的记录数
+    async fn count(table: &str, user: &RecordId) -> usize {
+        let root = database::root().await.unwrap();
+        let ids: Vec<RecordId> = root
+            .query(format!("SELECT VALUE id FROM {table} WHERE user = $user"))
+            .bind(("user", user.clone()))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        ids.len()
+    }
+
+    #[test]
+    fn email_format() {
+        assert!(check_email("someone@example.com").is_none());
+        assert!(check_email("someone").is_some());
+        assert!(check_email("@example.com").is_some());
+        assert!(check_email("someone@localhost").is_some());
+        assert!(check_email("someone@example.").is_some());
+        assert!(check_email("a@b@example.com").is_some());
+    }
+
+    #[test]
+    fn profile_email_taken() {
+        testing::run(async {
+            sign_up("taken@example.com").await;
+            let (db, id) = sign_up("profile@example.com").await;
+            assert!(matches!(
+                update(&db, "测试".to_owned(), "taken@example.com".to_owned()).await,
+                Err(Error::Validation(e)) if e[0].field == "email"
+            ));
+            // 邮箱不变时只改名字
+            let db = sign_in("profile@example.com", "secret").await.unwrap();
+            update(&db, "改名".to_owned(), "profile@example.com".to_owned())
+                .await
+                .unwrap();
+            let root = database::root().await.unwrap();
+            let name: Option<String> = root
+                .query("SELECT VALUE name FROM ONLY $user")
+                .bind(("user", id))
+                .await
+                .unwrap()
+                .take(0)
+                .unwrap();
+            assert_eq!(name.as_deref(), Some("改名"));
+        });
+    }
+
+    #[test]
+    fn reset_token() {
+        testing::run(async {
+            let (db, id) = sign_up("reset@example.com").await;
+            db.query("CREATE login SET user = $auth.id, user_agent = 'test', ip = '127.0.0.1'")
+                .await
+                .unwrap()
+                .check()
+                .unwrap();
+            drop(db);
+            let root = database::root().await.unwrap();
+
+            // 重新申请后之前的令牌作废
+            let old = create_reset(&root, id.clone(), 60).await.unwrap();
+            let token = create_reset(&root, id.clone(), 60).await.unwrap();
+            assert!(matches!(
+                reset(old, "changed".to_owned()).await,
+                Err(Error::ResetTokenInvalid)
+            ));
+            reset(token.clone(), "changed".to_owned()).await.unwrap();
+            assert_eq!(count("login", &id).await, 0);
+            assert!(sign_in("reset@example.com", "secret").await.is_none());
+            assert!(sign_in("reset@example.com", "changed").await.is_some());
+            // 只能使用一次
+            assert!(matches!(
+                reset(token, "again".to_owned()).await,
+                Err(Error::ResetTokenInvalid)
+            ));
+
+            // 过期的令牌无效
+            let root = database::root().await.unwrap();
+            let expired = create_reset(&root, id.clone(), 0).await.unwrap();
+            assert!(matches!(
+                reset(expired, "expired".to_owned()).await,
+                Err(Error::ResetTokenInvalid)
+            ));
+            assert!(sign_in("reset@example.com", "changed").await.is_some());
+        });
+    }
+
+    #[test]
+    fn delete_cascade() {
+        testing::run(async {
+            let (db, id) = sign_up("delete@example.com").await;
+            db.query(
+                "CREATE login SET user = $auth.id, user_agent = 'test', ip = '127.0.0.1';
+                CREATE api_token SET user = $auth.id, name = 'test', token_hash = 'hash', scopes = [];",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+            let root = database::root().await.unwrap();
+            create_reset(&root, id.clone(), 60).await.unwrap();
+            drop(root);
+
+            let db = sign_in("delete@example.com", "secret").await.unwrap();
+            assert!(matches!(
+                delete(&db, "wrong".to_owned()).await,
+                Err(Error::PasswordMismatch)
+            ));
+            delete(&db, "secret".to_owned()).await.unwrap();
+            for table in ["login", "password_reset", "api_token"] {
+                assert_eq!(count(table, &id).await, 0, "{table}");
+            }
+            assert!(sign_in("delete@example.com", "secret").await.is_none());
+        });
+    }
+}
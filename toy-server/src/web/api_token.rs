@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use poem::http::header;
+use poem::web::Data;
+use poem::{handler, Request};
+use rand::distr::{Alphanumeric, SampleString};
+use serde::Serialize;
+use log::{info, warn};
+
+use toy_schema::error::FieldError;
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{ApiToken, CreateTokenReq, CreateTokenRes, RevokeTokenReq, Scope};
+
+use crate::error::Error;
+use crate::web::database::{self, DbConn, Traced};
+use crate::web::speedy_data::Speedy;
+
+const TOKEN_PREFIX: &str = "toys_";
+const TOKEN_LEN: usize = 40;
+
+const CREATE_TOKEN: &str = "CREATE ONLY api_token SET user = $auth.id, name = $name,
+    token_hash = crypto::sha256($token), scopes = $scopes RETURN NONE";
+const LIST_TOKENS: &str = "SELECT record::id(id) AS id, name, scopes,
+    time::format(created_at, '%Y-%m-%d %H:%M:%S') AS created_at,
+    IF last_used THEN time::format(last_used, '%Y-%m-%d %H:%M:%S') END AS last_used
+    FROM api_token ORDER BY created_at DESC";
+const USE_TOKEN: &str = "UPDATE api_token SET last_used = time::now()
+    WHERE token_hash = crypto::sha256($token) RETURN VALUE scopes";
+
+#[derive(Serialize)]
+struct TokenParams {
+    token: String,
+}
+
+/// 从Authorization头取Bearer令牌
+pub(crate) fn bearer(req: &Request) -> Option<String> {
+    req.headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(|t| t.trim().to_owned())
+}
+
+/// 用令牌认证数据库连接并记录使用时间，返回令牌的权限范围
+pub(crate) async fn authenticate(db: &DbConn, token: String) -> Result<Vec<Scope>, Error> {
+    db.signin(database::token_credentials(TokenParams {
+        token: token.clone(),
+    }))
+    .traced("db.signin_token")
+    .await
+    .map_err(|e| {
+        warn!("访问令牌认证失败：{e}");
+        Error::UnAuthenticated
+    })?;
+
+    let scopes: Vec<Vec<Scope>> = db
+        .query(USE_TOKEN)
+        .bind(("token", token))
+        .traced("db.api_token.use")
+        .await?
+        .take(0)?;
+    scopes.into_iter().next().ok_or(Error::UnAuthenticated)
+}
+
+#[handler]
+pub async fn api_tokens(db: Data<&Arc<DbConn>>) -> poem::Result<Speedy<Vec<ApiToken>>> {
+    let tokens: Vec<ApiToken> = db
+        .query(LIST_TOKENS)
+        .traced("db.api_token.list")
+        .await
+        .and_then(|mut res| res.take(0))
+        .map_err(Error::DbException)?;
+    Ok(Speedy(tokens))
+}
+
+/// 创建令牌，令牌只在此时返回一次
+#[handler]
+pub async fn create_token(
+    req: Speedy<CreateTokenReq>,
+    db: Data<&Arc<DbConn>>,
+) -> poem::Result<Speedy<CreateTokenRes>> {
+    let CreateTokenReq { name, scopes } = req.0;
+    let name = name.trim().to_owned();
+    let mut details = Vec::new();
+    if name.is_empty() {
+        details.push(FieldError::new("name", "请填写令牌名称"));
+    }
+    if scopes.is_empty() {
+        details.push(FieldError::new("scopes", "请至少选择一个权限范围"));
+    }
+    if !details.is_empty() {
+        return Err(Error::Validation(details).into());
+    }
+
+    info!("创建访问令牌：{name} {scopes:?}");
+    let token = format!(
+        "{TOKEN_PREFIX}{}",
+        Alphanumeric.sample_string(&mut rand::rng(), TOKEN_LEN)
+    );
+    db.query(CREATE_TOKEN)
+        .bind(("name", name))
+        .bind(("token", token.clone()))
+        .bind(("scopes", scopes))
+        .traced("db.api_token.create")
+        .await
+        .and_then(|res| res.check())
+        .map_err(Error::DbException)?;
+    Ok(Speedy(CreateTokenRes { token }))
+}
+
+#[handler]
+pub async fn revoke_token(
+    req: Speedy<RevokeTokenReq>,
+    db: Data<&Arc<DbConn>>,
+) -> poem::Result<Speedy<Empty>> {
+    info!("撤销访问令牌 {}", req.id);
+    db.query("DELETE type::thing('api_token', $id)")
+        .bind(("id", req.0.id))
+        .traced("db.api_token.revoke")
+        .await
+        .and_then(|res| res.check())
+        .map_err(Error::DbException)?;
+    Ok(Speedy(Empty {}))
+}
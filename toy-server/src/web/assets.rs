@@ -0,0 +1,39 @@
+use std::sync::{Arc, OnceLock};
+
+use arc_swap::ArcSwap;
+use poem::endpoint::StaticFilesEndpoint;
+use poem::{Endpoint, IntoResponse, Request, Response, Result};
+use log::info;
+
+static ASSETS: OnceLock<ArcSwap<StaticFilesEndpoint>> = OnceLock::new();
+
+fn endpoint(path: &str) -> StaticFilesEndpoint {
+    StaticFilesEndpoint::new(path)
+        .prefer_utf8(true)
+        .index_file("index.html")
+        .fallback_to_index()
+}
+
+pub(crate) fn init(path: &str) {
+    ASSETS.get_or_init(|| ArcSwap::from_pointee(endpoint(path)));
+}
+
+/// 换到新的静态文件目录，之后的请求立即生效
+pub(crate) fn reload(path: &str) {
+    if let Some(assets) = ASSETS.get() {
+        assets.store(Arc::new(endpoint(path)));
+        info!("静态文件目录已切换到 {path}");
+    }
+}
+
+/// 前端静态文件，目录可以在运行时切换
+pub struct Assets;
+
+impl Endpoint for Assets {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let assets = ASSETS.get().unwrap().load_full();
+        assets.call(req).await.map(IntoResponse::into_response)
+    }
+}
@@ -1,74 +1,145 @@
+use std::sync::Arc;
+
+use fastrace::prelude::{FutureExt, Span};
+use fastrace::trace;
 use poem::session::Session;
+use poem::web::Data;
 use poem::{handler, Endpoint, Middleware, Request};
-use surrealdb::opt::auth::{Jwt, Record};
+use surrealdb::opt::auth::Jwt;
 use log::{debug, error, info, warn};
 
-use toy_schema::sign::SignReq;
+use toy_schema::error::FieldError;
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{Role, Scope, SignCheckRes, SignReq, SignRes};
 
 use crate::error::Error;
-use crate::web::database;
+use crate::web::account;
+use crate::web::api_token;
+use crate::web::database::{self, DbConn, Traced};
+use crate::web::login::{self, LOGIN_KEY};
+use crate::web::rate_limit;
 use crate::web::speedy_data::Speedy;
+use crate::web::totp::{self, TotpPending, TOTP_PENDING_KEY};
+
+/// session中保存用户角色的键，登录时写入
+const ROLE_KEY: &str = "role";
 
 #[handler]
-pub async fn sign_up(sign_req: Speedy<SignReq>, session: &Session) -> poem::Result<Speedy<()>> {
+pub async fn sign_up(
+    sign_req: Speedy<SignReq>,
+    session: &Session,
+    req: &Request,
+) -> poem::Result<Speedy<SignRes>> {
     debug!("sign_up session: {session:#?}");
     if session.get::<Jwt>("token").is_some() {
         session.renew();
     }
 
-    let db = database::connect().await.map_err(Error::DbException)?;
-    let credentials = Record {
-        namespace: "toy",
-        database: "toy",
-        access: "user_access",
-        params: sign_req.0,
-    };
+    let mut details = Vec::new();
+    if sign_req.name.trim().is_empty() {
+        details.push(FieldError::new("name", "请填写姓名"));
+    }
+    details.extend(account::check_email(&sign_req.email));
+    if sign_req.password.is_empty() {
+        details.push(FieldError::new("password", "请填写密码"));
+    }
+    if details.is_empty() && account::email_registered(&sign_req.email).await? {
+        details.push(account::email_taken());
+    }
+    if !details.is_empty() {
+        return Err(Error::Validation(details).into());
+    }
 
-    let token: Jwt = db.signup(credentials).await.map_err(|e| {
-        error!("sign_up error: {e}");
-        Error::SignUpFail
-    })?;
+    let db = database::user().await?;
+    let token: Jwt = db
+        .signup(database::user_credentials(sign_req.0))
+        .traced("db.signup")
+        .await
+        .map_err(|e| {
+            error!("sign_up error: {e}");
+            Error::SignUpFail
+        })?;
+    let role = complete_sign_in(&db, session, req, token).await?;
 
-    session.set("token", token);
-
-    Ok(Speedy(()))
+    Ok(Speedy(SignRes::SignedIn(role)))
 }
 
 #[handler]
-pub async fn sign_in(sign_req: Speedy<SignReq>, session: &Session) -> poem::Result<Speedy<()>> {
+pub async fn sign_in(
+    sign_req: Speedy<SignReq>,
+    session: &Session,
+    req: &Request,
+) -> poem::Result<Speedy<SignRes>> {
     info!("sign_in session: {session:#?}");
     if session.get::<Jwt>("token").is_some() {
         session.renew();
     }
 
-    let db = database::connect().await.map_err(Error::DbException)?;
-    let credentials = Record {
-        namespace: "toy",
-        database: "toy",
-        access: "user_access",
-        params: sign_req.0,
-    };
+    let limiter = rate_limit::limiter();
+    let email = sign_req.email.clone();
+    limiter.check_account(&email)?;
+
+    let db = database::user().await?;
+    let token: Jwt = db
+        .signin(database::user_credentials(sign_req.0))
+        .traced("db.signin")
+        .await
+        .map_err(|e| {
+            error!("sign_in error: {e}");
+            limiter.failed(&email);
+            Error::SignInFail
+        })?;
+
+    // 启用了两步验证时，验证码通过后才算登录成功
+    if totp::enabled(&db).await? {
+        info!("等待两步验证：{email}");
+        session.set(TOTP_PENDING_KEY, TotpPending::new(token, email));
+        return Ok(Speedy(SignRes::TotpRequired));
+    }
+    limiter.succeeded(&email);
+    let role = complete_sign_in(&db, session, req, token).await?;
+
+    Ok(Speedy(SignRes::SignedIn(role)))
+}
 
-    let token: Jwt = db.signin(credentials).await.map_err(|e| {
-        error!("sign_in error: {e}");
-        Error::SignInFail
-    })?;
+/// 记录登录并把token和角色写入session，db须是刚登录的用户连接
+#[trace(name = "auth.complete_sign_in")]
+pub(crate) async fn complete_sign_in(
+    db: &DbConn,
+    session: &Session,
+    req: &Request,
+    token: Jwt,
+) -> Result<Role, Error> {
+    let login = login::create(db, req).await?;
+    let role = role(db).await?;
 
     session.set("token", token);
+    session.set(LOGIN_KEY, login);
+    session.set(ROLE_KEY, role);
+    Ok(role)
+}
 
-    Ok(Speedy(()))
+/// 查询当前登录用户的角色，db须是已认证的用户连接
+async fn role(db: &DbConn) -> Result<Role, Error> {
+    let role: Option<Role> = db
+        .query("SELECT VALUE role FROM ONLY $auth")
+        .traced("db.user.role")
+        .await?
+        .take(0)?;
+    Ok(role.unwrap_or_default())
 }
 
+/// 已登录时返回角色
 #[handler]
-pub async fn sign_check(session: &Session) -> poem::Result<Speedy<bool>> {
+pub async fn sign_check(session: &Session) -> poem::Result<Speedy<SignCheckRes>> {
     info!("sign_check session: {session:#?}");
     let Some(token) = session.get::<Jwt>("token") else {
         warn!("session已失效，未获取到数据库token");
         session.purge();
-        return Ok(Speedy(false));
+        return Ok(Speedy(SignCheckRes::default()));
     };
 
-    let db = match database::connect().await {
+    let db = match database::user().await {
         Ok(d) => d,
         Err(e) => {
             warn!("数据库连接失败：{e}");
@@ -77,61 +148,175 @@ pub async fn sign_check(session: &Session) -> poem::Result<Speedy<bool>> {
         }
     };
 
-    if let Err(e) = db.authenticate(token).await {
+    if let Err(e) = db.authenticate(token).traced("db.authenticate").await {
         warn!("数据库token验证失败：{e}");
         session.purge();
-        return Ok(Speedy(false));
+        return Ok(Speedy(SignCheckRes::default()));
+    }
+
+    if !login::touch(&db, session).await? {
+        warn!("登录已被撤销");
+        session.purge();
+        return Ok(Speedy(SignCheckRes::default()));
     }
 
-    Ok(Speedy(true))
+    Ok(Speedy(SignCheckRes {
+        role: Some(session.get::<Role>(ROLE_KEY).unwrap_or_default()),
+    }))
 }
 
 #[handler]
-pub async fn logout(session: &Session) -> poem::Result<Speedy<()>> {
+pub async fn logout(db: Data<&Arc<DbConn>>, session: &Session) -> poem::Result<Speedy<Empty>> {
+    if let Some(id) = session.get::<String>(LOGIN_KEY) {
+        db.query("DELETE type::thing('login', $id)")
+            .bind(("id", id))
+            .traced("db.login.revoke")
+            .await
+            .map_err(Error::DbException)?;
+    }
     session.purge();
-    Ok(Speedy(()))
+    Ok(Speedy(Empty {}))
+}
+
+/// 认证：session登录，或者带有相应权限范围的个人访问令牌（Authorization: Bearer）
+pub struct Auth {
+    scope: Option<Scope>,
 }
 
-pub struct Auth {}
+impl Auth {
+    /// 只接受session登录
+    pub fn session() -> Self {
+        Auth { scope: None }
+    }
+
+    /// 也接受有此权限范围的个人访问令牌
+    pub fn or_token(scope: Scope) -> Self {
+        Auth { scope: Some(scope) }
+    }
+}
 
 impl<E: Endpoint> Middleware<E> for Auth {
     type Output = AuthEndpoint<E>;
 
     fn transform(&self, ep: E) -> Self::Output {
-        AuthEndpoint { ep }
+        AuthEndpoint {
+            ep,
+            scope: self.scope,
+        }
     }
 }
 
 pub struct AuthEndpoint<E> {
     ep: E,
+    scope: Option<Scope>,
 }
 
 impl<E: Endpoint> Endpoint for AuthEndpoint<E> {
     type Output = E::Output;
 
     async fn call(&self, mut req: Request) -> poem::Result<Self::Output> {
-        // 从req取session
-        let Some(session) = req.extensions().get::<Session>() else {
-            error!("未读取到session");
-            return Err(Error::UnAuthenticated.into());
+        // 带了访问令牌时只按令牌认证
+        let db = match api_token::bearer(&req) {
+            Some(token) => {
+                token_auth(&req, token, self.scope)
+                    .in_span(Span::enter_with_local_parent("auth.token"))
+                    .await?
+            }
+            None => {
+                session_auth(&req)
+                    .in_span(Span::enter_with_local_parent("auth.session"))
+                    .await?
+            }
         };
 
-        // 从session取token
-        let Some(token) = session.get::<Jwt>("token") else {
-            error!("session中未读取到token: {session:#?}");
-            return Err(Error::UnAuthenticated.into());
-        };
+        // 保存数据库连接到req，请求结束后归还连接池
+        req.extensions_mut().insert(Arc::new(db));
 
-        // 创建数据连接
-        let db = database::connect().await.map_err(Error::DbException)?;
-        info!("数据库连接成功");
+        self.ep.call(req).await
+    }
+}
+
+/// 用访问令牌认证数据库连接，令牌须有接口要求的权限范围
+async fn token_auth(req: &Request, token: String, scope: Option<Scope>) -> poem::Result<DbConn> {
+    let Some(scope) = scope else {
+        warn!("{}不接受访问令牌", req.uri().path());
+        return Err(Error::UnAuthorized.into());
+    };
+    let db = database::user().await?;
+    let scopes = api_token::authenticate(&db, token).await?;
+    if !scopes.contains(&scope) {
+        warn!("访问令牌无{scope:?}权限");
+        return Err(Error::UnAuthorized.into());
+    }
+    Ok(db)
+}
+
+/// 用session中的token认证数据库连接
+async fn session_auth(req: &Request) -> poem::Result<DbConn> {
+    // 从req取session
+    let Some(session) = req.extensions().get::<Session>() else {
+        error!("未读取到session");
+        return Err(Error::UnAuthenticated.into());
+    };
+
+    // 从session取token
+    let Some(token) = session.get::<Jwt>("token") else {
+        error!("session中未读取到token: {session:#?}");
+        return Err(Error::UnAuthenticated.into());
+    };
+
+    // 从连接池取数据库连接
+    let db = database::user().await?;
+
+    // 数据库用户认证
+    db.authenticate(token)
+        .traced("db.authenticate")
+        .await
+        .map_err(Error::DbException)?;
+    info!("数据库用户认证成功");
+
+    // 登录被撤销后立即失效
+    if !login::touch(&db, session).await? {
+        warn!("登录已被撤销");
+        session.purge();
+        return Err(Error::UnAuthenticated.into());
+    }
+    Ok(db)
+}
+
+/// 按角色授权，须放在Auth之内使用
+pub struct RequireRole(pub Role);
+
+impl<E: Endpoint> Middleware<E> for RequireRole {
+    type Output = RequireRoleEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RequireRoleEndpoint { ep, role: self.0 }
+    }
+}
 
-        // 数据库用户认证
-        db.authenticate(token).await.map_err(Error::DbException)?;
-        info!("数据库用户认证成功");
+pub struct RequireRoleEndpoint<E> {
+    ep: E,
+    role: Role,
+}
 
-        // 保存数据库连接到req
-        req.extensions_mut().insert(db);
+impl<E: Endpoint> Endpoint for RequireRoleEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        let role = req
+            .extensions()
+            .get::<Session>()
+            .and_then(|session| session.get::<Role>(ROLE_KEY))
+            .unwrap_or_default();
+        if role < self.role {
+            warn!(
+                "角色{role:?}无权访问{}，需要{:?}",
+                req.uri().path(),
+                self.role
+            );
+            return Err(Error::UnAuthorized.into());
+        }
 
         self.ep.call(req).await
     }
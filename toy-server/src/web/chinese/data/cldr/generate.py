@@ -0,0 +1,84 @@
+#!/usr/bin/env python3
+# 用ICU的uconv按CLDR转写规则生成逐字的繁简对照表和拼音，覆盖基本区和扩展A区的汉字。
+# 手工整理的词表（上级目录）优先，这里只补充其中没有的字。
+# 用法：cd toy-server/src/web/chinese/data/cldr && ./generate.py
+
+import subprocess
+import unicodedata
+
+CHARS = [chr(c) for c in [*range(0x4E00, 0xA000), *range(0x3400, 0x4DC0)]]
+
+# 带调元音对应的字母和声调，ü写作v，与上级目录的拼音词表一致
+TONES = {
+    **{m: ("a", t + 1) for t, m in enumerate("āáǎà")},
+    **{m: ("e", t + 1) for t, m in enumerate("ēéěè")},
+    **{m: ("i", t + 1) for t, m in enumerate("īíǐì")},
+    **{m: ("o", t + 1) for t, m in enumerate("ōóǒò")},
+    **{m: ("u", t + 1) for t, m in enumerate("ūúǔù")},
+    **{m: ("v", t + 1) for t, m in enumerate("ǖǘǚǜ")},
+    **{m: ("n", t + 2) for t, m in enumerate("ńňǹ")},
+    "ḿ": ("m", 2),
+    "ü": ("v", 5),
+}
+
+
+def transliterate(rule):
+    out = subprocess.run(
+        ["uconv", "-x", rule],
+        input="\n".join(CHARS) + "\n",
+        capture_output=True,
+        text=True,
+        check=True,
+    ).stdout
+    return dict(zip(CHARS, out.splitlines()))
+
+
+def number_tone(syllable):
+    """把标调的拼音转成数字声调，轻声为5；不是拼音时返回None"""
+    body, tone = "", 5
+    for c in unicodedata.normalize("NFC", syllable):
+        letter, t = TONES.get(c, (c, 5))
+        body += letter
+        if t != 5:
+            tone = t
+    if not (body.isascii() and body.isalpha()):
+        return None
+    return f"{body}{tone}"
+
+
+def version():
+    out = subprocess.run(["uconv", "--version"], capture_output=True, text=True, check=True)
+    return out.stdout.split("ICU")[-1].strip()
+
+
+def write(name, rule, pairs):
+    with open(name, "w", encoding="utf-8") as f:
+        f.write(f"# 由generate.py从ICU {version()}的CLDR转写规则{rule}生成，许可见同目录的LICENSE\n")
+        for key, value in pairs:
+            f.write(f"{key}\t{value}\n")
+
+
+def main():
+    s2t = transliterate("Simplified-Traditional")
+    t2s = transliterate("Traditional-Simplified")
+    # 只收录双向一致的字，排除沉→沈这类只在个别用法中成立的对应
+    write(
+        "STCharacters.txt",
+        "Simplified-Traditional",
+        [(s, t) for s, t in s2t.items() if s != t and t2s.get(t) == s],
+    )
+    write(
+        "TSCharacters.txt",
+        "Traditional-Simplified",
+        [(t, s) for t, s in t2s.items() if t != s],
+    )
+    readings = {c: number_tone(p) for c, p in transliterate("Han-Latin").items() if c != p}
+    write(
+        "pinyin_chars.txt",
+        "Han-Latin",
+        [(c, p) for c, p in readings.items() if p is not None],
+    )
+
+
+if __name__ == "__main__":
+    main()
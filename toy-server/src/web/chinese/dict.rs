@@ -0,0 +1,88 @@
+use std::collections::HashMap;
+
+/// OpenCC格式的转换词典：每行`原文\t候选1 候选2 ...`，取第一个候选
+pub(crate) struct Dict {
+    map: HashMap<String, String>,
+    // 最长词条的字数，用于最长匹配
+    max_len: usize,
+}
+
+impl Dict {
+    pub(crate) fn new(sources: &[&str]) -> Self {
+        let mut dict = Dict {
+            map: HashMap::new(),
+            max_len: 0,
+        };
+        for (key, values) in sources.iter().flat_map(|s| entries(s)) {
+            if let Some(value) = values.first() {
+                dict.insert(key, value);
+            }
+        }
+        dict
+    }
+
+    /// 反向词典：每个候选都映射回原文，先出现的优先
+    pub(crate) fn inverse(sources: &[&str]) -> Self {
+        let mut dict = Dict {
+            map: HashMap::new(),
+            max_len: 0,
+        };
+        for (key, values) in sources.iter().flat_map(|s| entries(s)) {
+            for value in values {
+                if !dict.map.contains_key(value) {
+                    dict.insert(value, key);
+                }
+            }
+        }
+        dict
+    }
+
+    /// 补充词条，已有的词条不覆盖
+    pub(crate) fn extend(&mut self, sources: &[&str]) {
+        for (key, values) in sources.iter().flat_map(|s| entries(s)) {
+            if let Some(value) = values.first()
+                && !self.map.contains_key(key)
+            {
+                self.insert(key, value);
+            }
+        }
+    }
+
+    fn insert(&mut self, key: &str, value: &str) {
+        self.max_len = self.max_len.max(key.chars().count());
+        self.map.insert(key.to_owned(), value.to_owned());
+    }
+
+    /// 正向最长匹配转换
+    pub(crate) fn convert(&self, text: &str) -> String {
+        let chars: Vec<char> = text.chars().collect();
+        let mut res = String::with_capacity(text.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = self.max_len.min(chars.len() - i);
+            let matched = (1..=longest).rev().find_map(|len| {
+                let word: String = chars[i..i + len].iter().collect();
+                self.map.get(&word).map(|v| (len, v))
+            });
+            match matched {
+                Some((len, value)) => {
+                    res.push_str(value);
+                    i += len;
+                }
+                None => {
+                    res.push(chars[i]);
+                    i += 1;
+                }
+            }
+        }
+        res
+    }
+}
+
+pub(crate) fn entries(source: &str) -> impl Iterator<Item = (&str, Vec<&str>)> {
+    source
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(key, values)| (key, values.split(' ').filter(|v| !v.is_empty()).collect()))
+}
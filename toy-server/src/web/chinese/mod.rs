@@ -0,0 +1,236 @@
+use std::sync::OnceLock;
+
+use log::info;
+use poem::{handler, Result};
+
+use toy_schema::chinese::{ChineseVariant, ConvertReq, ConvertRes, PinyinReq, Ruby};
+
+use crate::web::chinese::dict::Dict;
+use crate::web::speedy_data::Speedy;
+
+mod dict;
+mod pinyin;
+mod punctuation;
+
+const ST_CHARACTERS: &str = include_str!("data/STCharacters.txt");
+const ST_PHRASES: &str = include_str!("data/STPhrases.txt");
+const TW_PHRASES: &str = include_str!("data/TWPhrases.txt");
+const TW_VARIANTS: &str = include_str!("data/TWVariants.txt");
+const HK_PHRASES: &str = include_str!("data/HKPhrases.txt");
+const HK_VARIANTS: &str = include_str!("data/HKVariants.txt");
+// 台湾、香港异体字（如爲、羣、説）对应的简体
+const TS_VARIANTS: &str = include_str!("data/TSVariants.txt");
+// 由CLDR转写规则生成的逐字对照，补充上面手工整理的词表，见data/cldr
+const CLDR_ST_CHARACTERS: &str = include_str!("data/cldr/STCharacters.txt");
+const CLDR_TS_CHARACTERS: &str = include_str!("data/cldr/TSCharacters.txt");
+
+static TABLES: OnceLock<Tables> = OnceLock::new();
+
+/// 转换词典，首次使用时从内置的词表构建
+struct Tables {
+    // 台湾、香港用词还原为标准繁体，只用于已知来源的文本
+    tw_rev: Dict,
+    hk_rev: Dict,
+    s2t: Dict,
+    t2s: Dict,
+    tw: Dict,
+    hk: Dict,
+}
+
+impl Tables {
+    fn get() -> &'static Tables {
+        TABLES.get_or_init(|| {
+            let mut s2t = Dict::new(&[ST_PHRASES, ST_CHARACTERS]);
+            s2t.extend(&[CLDR_ST_CHARACTERS]);
+            let mut t2s = Dict::inverse(&[ST_CHARACTERS]);
+            t2s.extend(&[TS_VARIANTS, CLDR_TS_CHARACTERS]);
+            Tables {
+                tw_rev: Dict::inverse(&[TW_PHRASES]),
+                hk_rev: Dict::inverse(&[HK_PHRASES]),
+                s2t,
+                t2s,
+                tw: Dict::new(&[TW_PHRASES, TW_VARIANTS]),
+                hk: Dict::new(&[HK_PHRASES, HK_VARIANTS]),
+            }
+        })
+    }
+}
+
+/// 转换为目标地区的写法。地区用词有歧义（如番茄、雪糕在大陆也常用），只在已知原文来自台湾或香港时还原
+pub(crate) fn convert_text(
+    text: &str,
+    source: Option<ChineseVariant>,
+    target: ChineseVariant,
+) -> String {
+    let tables = Tables::get();
+    let standard = match source {
+        Some(ChineseVariant::Taiwan) => tables.tw_rev.convert(text),
+        Some(ChineseVariant::HongKong) => tables.hk_rev.convert(text),
+        _ => text.to_owned(),
+    };
+    match target {
+        ChineseVariant::Simplified => tables.t2s.convert(&standard),
+        ChineseVariant::Traditional => tables.s2t.convert(&standard),
+        ChineseVariant::Taiwan => tables.tw.convert(&tables.s2t.convert(&standard)),
+        ChineseVariant::HongKong => tables.hk.convert(&tables.s2t.convert(&standard)),
+    }
+}
+
+#[handler]
+pub async fn convert(req: Speedy<ConvertReq>) -> Result<Speedy<ConvertRes>> {
+    info!("繁简转换：{:?}", req.target);
+    let mut text = convert_text(&req.text, req.source, req.target);
+    if req.normalize_punctuation {
+        text = punctuation::normalize(&text);
+    }
+    Ok(Speedy(ConvertRes { text }))
+}
+
+#[handler]
+pub async fn pinyin(req: Speedy<PinyinReq>) -> Result<Speedy<Vec<Ruby>>> {
+    info!("拼音标注：{:?}", req.style);
+    Ok(Speedy(pinyin::annotate(&req.text, req.style)))
+}
+
+#[cfg(test)]
+mod test {
+    use toy_schema::chinese::ChineseVariant::{HongKong, Simplified, Taiwan, Traditional};
+    use toy_schema::chinese::ToneStyle;
+
+    use crate::web::chinese::convert_text;
+    use crate::web::chinese::dict::entries;
+    use crate::web::chinese::pinyin::{annotate, PINYIN_WORDS};
+    use crate::web::chinese::punctuation::normalize;
+
+    #[test]
+    fn convert_variants() {
+        assert_eq!(convert_text("头发和软件", None, Traditional), "頭髮和軟件");
+        assert_eq!(convert_text("头发和软件", None, Taiwan), "頭髮和軟體");
+        assert_eq!(convert_text("这里的出租车", None, HongKong), "這裏的的士");
+        assert_eq!(
+            convert_text("這裡的軟體", Some(Taiwan), Simplified),
+            "这里的软件"
+        );
+        assert_eq!(convert_text("干净的饼干", None, Traditional), "乾淨的餅乾");
+    }
+
+    #[test]
+    fn convert_cldr_characters() {
+        // 手工词表之外的字由CLDR对照表补充
+        assert_eq!(convert_text("龟兔赛跑", None, Traditional), "龜兔賽跑");
+        assert_eq!(convert_text("钥匙锁门", None, Traditional), "鑰匙鎖門");
+        assert_eq!(convert_text("燈籠蠟燭", None, Simplified), "灯笼蜡烛");
+        assert_eq!(convert_text("烏鴉喝水", None, Simplified), "乌鸦喝水");
+        // 手工词表优先
+        assert_eq!(convert_text("里面", None, Traditional), "裏面");
+        assert_eq!(convert_text("走着", None, Traditional), "走着");
+        assert_eq!(convert_text("走着", None, Taiwan), "走著");
+    }
+
+    #[test]
+    fn convert_without_source() {
+        // 来源未知时不做地区用词还原
+        assert_eq!(convert_text("番茄炒蛋", None, Traditional), "番茄炒蛋");
+        assert_eq!(convert_text("吃雪糕", None, Simplified), "吃雪糕");
+        assert_eq!(convert_text("我才来", None, Traditional), "我才來");
+        assert_eq!(convert_text("這裡的軟體", None, Simplified), "这里的软体");
+        assert_eq!(
+            convert_text("吃雪糕", Some(HongKong), Simplified),
+            "吃冰淇淋"
+        );
+    }
+
+    #[test]
+    fn convert_variant_characters() {
+        assert_eq!(convert_text("一群人", None, Simplified), "一群人");
+        assert_eq!(convert_text("一羣人", None, Simplified), "一群人");
+        assert_eq!(convert_text("為什麼", None, Simplified), "为什么");
+        assert_eq!(convert_text("爲什麼", None, Simplified), "为什么");
+        assert_eq!(convert_text("衆人啓程", None, Simplified), "众人启程");
+        assert_eq!(convert_text("他説喫飯", None, Simplified), "他说吃饭");
+    }
+
+    #[test]
+    fn normalize_punctuation() {
+        assert_eq!(normalize("你好,世界!"), "你好，世界！");
+        assert_eq!(normalize("他说\"你好\""), "他说“你好”");
+        assert_eq!(normalize("等等..."), "等等……");
+        assert_eq!(normalize("等等…"), "等等……");
+        assert_eq!(normalize("中文--破折号"), "中文——破折号");
+        assert_eq!(normalize("中文English混排"), "中文 English 混排");
+        assert_eq!(normalize("价格3.5元"), "价格 3.5 元");
+        assert_eq!(normalize("Hello, world."), "Hello, world.");
+    }
+
+    fn pinyin_of(text: &str, style: ToneStyle) -> Vec<String> {
+        annotate(text, style)
+            .into_iter()
+            .map(|r| r.pinyin.unwrap_or(r.text))
+            .collect()
+    }
+
+    #[test]
+    fn pinyin_words_aligned() {
+        for (word, syllables) in entries(PINYIN_WORDS) {
+            assert_eq!(word.chars().count(), syllables.len(), "{word}");
+        }
+    }
+
+    #[test]
+    fn annotate_polyphones() {
+        assert_eq!(
+            pinyin_of("银行行长", ToneStyle::Mark),
+            ["yín", "háng", "háng", "zhǎng"]
+        );
+        assert_eq!(
+            pinyin_of("我们一起走着", ToneStyle::Number),
+            ["wo3", "men5", "yi1", "qi3", "zou3", "zhe5"]
+        );
+        assert_eq!(pinyin_of("音乐很快乐", ToneStyle::Mark), ["yīn", "yuè", "hěn", "kuài", "lè"]);
+        assert_eq!(pinyin_of("女儿", ToneStyle::Mark), ["nǚ", "ér"]);
+        assert_eq!(pinyin_of("好吧", ToneStyle::Number), ["hao3", "ba5"]);
+        assert_eq!(pinyin_of("好吧", ToneStyle::Mark), ["hǎo", "ba"]);
+        assert_eq!(pinyin_of("说Rust好", ToneStyle::Mark), ["shuō", "Rust", "hǎo"]);
+    }
+
+    #[test]
+    fn annotate_common_polyphones() {
+        assert_eq!(
+            pinyin_of("薄荷味的薄饼", ToneStyle::Number),
+            ["bo4", "he5", "wei4", "de5", "bao2", "bing3"]
+        );
+        assert_eq!(
+            pinyin_of("尽管尽力了", ToneStyle::Number),
+            ["jin3", "guan3", "jin4", "li4", "le5"]
+        );
+        assert_eq!(
+            pinyin_of("钥匙在茶几上", ToneStyle::Number),
+            ["yao4", "shi5", "zai4", "cha2", "ji1", "shang4"]
+        );
+        assert_eq!(
+            pinyin_of("他答应去调整", ToneStyle::Number),
+            ["ta1", "da1", "ying5", "qu4", "tiao2", "zheng3"]
+        );
+        assert_eq!(pinyin_of("朝鲜", ToneStyle::Mark), ["cháo", "xiǎn"]);
+        assert_eq!(pinyin_of("重庆", ToneStyle::Mark), ["chóng", "qìng"]);
+        assert_eq!(pinyin_of("省略", ToneStyle::Mark), ["shěng", "lüè"]);
+        assert_eq!(
+            pinyin_of("厦门大厦", ToneStyle::Mark),
+            ["xià", "mén", "dà", "shà"]
+        );
+    }
+
+    #[test]
+    fn annotate_cldr_characters() {
+        // 手工词表之外的字取CLDR的默认读音
+        assert_eq!(
+            pinyin_of("龟兔赛跑", ToneStyle::Mark),
+            ["guī", "tù", "sài", "pǎo"]
+        );
+        assert_eq!(pinyin_of("鹦鹉", ToneStyle::Number), ["ying1", "wu3"]);
+        // 手工整理的读音优先
+        assert_eq!(pinyin_of("下雨", ToneStyle::Mark), ["xià", "yǔ"]);
+        assert_eq!(pinyin_of("萝卜", ToneStyle::Number), ["luo2", "bo5"]);
+        assert_eq!(pinyin_of("占卜", ToneStyle::Number), ["zhan1", "bu3"]);
+    }
+}
@@ -0,0 +1,150 @@
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use log::warn;
+
+use toy_schema::chinese::{Ruby, ToneStyle};
+
+use crate::web::chinese::dict::entries;
+use crate::web::chinese::punctuation::is_cjk;
+
+const PINYIN_CHARS: &str = include_str!("data/pinyin_chars.txt");
+// 由CLDR转写规则生成的单字读音，补充上面手工整理的常用字，见data/cldr
+const CLDR_PINYIN_CHARS: &str = include_str!("data/cldr/pinyin_chars.txt");
+pub(crate) const PINYIN_WORDS: &str = include_str!("data/pinyin_words.txt");
+
+static PINYIN_DICT: OnceLock<PinyinDict> = OnceLock::new();
+
+const TONE_MARKS: [(char, [char; 4]); 6] = [
+    ('a', ['ā', 'á', 'ǎ', 'à']),
+    ('e', ['ē', 'é', 'ě', 'è']),
+    ('i', ['ī', 'í', 'ǐ', 'ì']),
+    ('o', ['ō', 'ó', 'ǒ', 'ò']),
+    ('u', ['ū', 'ú', 'ǔ', 'ù']),
+    ('ü', ['ǖ', 'ǘ', 'ǚ', 'ǜ']),
+];
+
+/// 拼音词典：单字取第一个读音，多音字由词语读音消歧。常用字和多音词手工整理，其余基本区和扩展A区的
+/// 汉字取CLDR的默认读音，都没有的汉字不注音
+struct PinyinDict {
+    chars: HashMap<char, String>,
+    words: HashMap<String, Vec<String>>,
+    // 最长词语的字数，用于最长匹配
+    max_len: usize,
+}
+
+impl PinyinDict {
+    fn get() -> &'static PinyinDict {
+        PINYIN_DICT.get_or_init(|| {
+            let mut chars = HashMap::new();
+            // 手工整理的读音优先
+            for (key, values) in entries(PINYIN_CHARS).chain(entries(CLDR_PINYIN_CHARS)) {
+                if let (Some(c), Some(value)) = (key.chars().next(), values.first()) {
+                    chars.entry(c).or_insert_with(|| (*value).to_owned());
+                }
+            }
+            let words: HashMap<String, Vec<String>> = entries(PINYIN_WORDS)
+                .filter(|(key, values)| {
+                    // 每个字对应一个音节，否则注音会错位
+                    let matched = key.chars().count() == values.len();
+                    if !matched {
+                        warn!("拼音词表中{key}的音节数与字数不一致，已忽略");
+                    }
+                    matched
+                })
+                .map(|(key, values)| {
+                    (key.to_owned(), values.into_iter().map(str::to_owned).collect())
+                })
+                .collect();
+            let max_len = words.keys().map(|w| w.chars().count()).max().unwrap_or(1);
+            PinyinDict {
+                chars,
+                words,
+                max_len,
+            }
+        })
+    }
+
+    /// 逐字给出数字声调的读音，优先按最长词语匹配
+    fn readings(&self, chars: &[char]) -> Vec<Option<String>> {
+        let mut res = Vec::with_capacity(chars.len());
+        let mut i = 0;
+        while i < chars.len() {
+            let longest = self.max_len.min(chars.len() - i);
+            let word = (2..=longest).rev().find_map(|len| {
+                let word: String = chars[i..i + len].iter().collect();
+                self.words.get(&word).map(|syllables| (len, syllables))
+            });
+            match word {
+                Some((len, syllables)) => {
+                    res.extend(syllables.iter().cloned().map(Some));
+                    i += len;
+                }
+                None => {
+                    res.push(self.chars.get(&chars[i]).cloned());
+                    i += 1;
+                }
+            }
+        }
+        res
+    }
+}
+
+/// 给文本注音，连续的非汉字合并为一段
+pub(crate) fn annotate(text: &str, style: ToneStyle) -> Vec<Ruby> {
+    let chars: Vec<char> = text.chars().collect();
+    let readings = PinyinDict::get().readings(&chars);
+
+    let mut res: Vec<Ruby> = Vec::new();
+    for (c, reading) in chars.into_iter().zip(readings) {
+        match reading {
+            Some(syllable) if is_cjk(c) => res.push(Ruby {
+                text: c.to_string(),
+                pinyin: Some(format_syllable(&syllable, style)),
+            }),
+            _ => match res.last_mut() {
+                Some(last) if last.pinyin.is_none() => last.text.push(c),
+                _ => res.push(Ruby {
+                    text: c.to_string(),
+                    pinyin: None,
+                }),
+            },
+        }
+    }
+    res
+}
+
+/// 把词典中的数字声调（如`lv4`）转换成需要的格式，轻声为5
+fn format_syllable(syllable: &str, style: ToneStyle) -> String {
+    let (body, tone) = match syllable.chars().last().and_then(|c| c.to_digit(10)) {
+        Some(tone) => (&syllable[..syllable.len() - 1], tone as usize),
+        None => (syllable, 5),
+    };
+    let body = body.replace('v', "ü");
+
+    match style {
+        ToneStyle::Number => format!("{body}{tone}"),
+        ToneStyle::Mark if (1..=4).contains(&tone) => {
+            // 标调规则：有a标a，没a找o、e，iu并列标在后
+            let target = if body.contains('a') {
+                'a'
+            } else if body.contains('e') {
+                'e'
+            } else if body.contains("ou") {
+                'o'
+            } else {
+                match body.chars().rev().find(|c| "aeiouü".contains(*c)) {
+                    Some(c) => c,
+                    None => return body,
+                }
+            };
+            let marked = TONE_MARKS
+                .iter()
+                .find(|(vowel, _)| *vowel == target)
+                .map(|(_, marks)| marks[tone - 1])
+                .unwrap_or(target);
+            body.replacen(target, &marked.to_string(), 1)
+        }
+        ToneStyle::Mark => body,
+    }
+}
@@ -0,0 +1,135 @@
+/// 标点符号规范化：省略号与破折号、全角标点、中文引号、中英文间距
+pub(crate) fn normalize(text: &str) -> String {
+    let chars: Vec<char> = text.chars().collect();
+    let chars = ellipsis_and_dash(&chars);
+    let chars = full_width(&chars);
+    let chars = quotes(chars);
+    spacing(&chars)
+}
+
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{3400}'..='\u{4dbf}'
+        | '\u{4e00}'..='\u{9fff}'
+        | '\u{f900}'..='\u{faff}'
+        | '\u{20000}'..='\u{2ffff}')
+}
+
+fn is_cjk_punctuation(c: char) -> bool {
+    matches!(c,
+        '\u{3000}'..='\u{303f}'
+        | '\u{ff00}'..='\u{ffef}'
+        | '“' | '”' | '‘' | '’' | '…' | '—')
+}
+
+fn wide_form(c: char) -> Option<char> {
+    match c {
+        ',' => Some('，'),
+        '.' => Some('。'),
+        ';' => Some('；'),
+        ':' => Some('：'),
+        '?' => Some('？'),
+        '!' => Some('！'),
+        '(' => Some('（'),
+        ')' => Some('）'),
+        _ => None,
+    }
+}
+
+// 省略号统一为“……”，破折号统一为“——”
+fn ellipsis_and_dash(chars: &[char]) -> Vec<char> {
+    let mut res = Vec::with_capacity(chars.len());
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let run = chars[i..].iter().take_while(|&&x| x == c).count();
+        let near_cjk = res.last().is_some_and(|&p| is_cjk(p))
+            || chars.get(i + run).is_some_and(|&n| is_cjk(n));
+
+        match c {
+            '…' | '—' => res.extend([c, c]),
+            '.' | '。' if run >= 3 && near_cjk => res.extend(['…', '…']),
+            '-' if run >= 2 && near_cjk => res.extend(['—', '—']),
+            _ => res.extend(std::iter::repeat_n(c, run)),
+        }
+        i += run;
+    }
+    res
+}
+
+// 紧跟中文的半角标点转为全角，并去掉两侧多余的空格
+fn full_width(chars: &[char]) -> Vec<char> {
+    let mut res: Vec<char> = Vec::with_capacity(chars.len());
+    let mut skip_space = false;
+    for (i, &c) in chars.iter().enumerate() {
+        if c == ' ' && skip_space {
+            continue;
+        }
+        skip_space = false;
+
+        let Some(wide) = wide_form(c) else {
+            res.push(c);
+            continue;
+        };
+
+        let prev = res.iter().rev().find(|&&p| p != ' ').copied();
+        let next = chars[i + 1..].iter().find(|&&n| n != ' ').copied();
+        let after_cjk = prev.is_some_and(|p| is_cjk(p) || is_cjk_punctuation(p));
+        let convert = match c {
+            '(' => next.is_some_and(is_cjk),
+            // 避免把小数点、网址中的点转换掉
+            '.' => after_cjk && next.is_none_or(|n| !n.is_ascii_alphanumeric()),
+            _ => after_cjk,
+        };
+
+        if convert {
+            while res.last() == Some(&' ') {
+                res.pop();
+            }
+            res.push(wide);
+            skip_space = true;
+        } else {
+            res.push(c);
+        }
+    }
+    res
+}
+
+// 包含中文的英文双引号转为中文引号
+fn quotes(mut chars: Vec<char>) -> Vec<char> {
+    let positions: Vec<usize> = chars
+        .iter()
+        .enumerate()
+        .filter(|&(_, &c)| c == '"')
+        .map(|(i, _)| i)
+        .collect();
+
+    for pair in positions.chunks_exact(2) {
+        if chars[pair[0] + 1..pair[1]].iter().any(|&c| is_cjk(c)) {
+            chars[pair[0]] = '“';
+            chars[pair[1]] = '”';
+        }
+    }
+    chars
+}
+
+// 中文与英文、数字之间加空格，全角标点两侧不留空格
+fn spacing(chars: &[char]) -> String {
+    let mut res = String::with_capacity(chars.len() * 3);
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = res.chars().last();
+        if c == ' ' {
+            let next = chars[i + 1..].iter().find(|&&n| n != ' ').copied();
+            if prev.is_some_and(is_cjk_punctuation) || next.is_some_and(is_cjk_punctuation) {
+                continue;
+            }
+        } else if let Some(prev) = prev
+            && ((is_cjk(prev) && c.is_ascii_alphanumeric())
+                || (prev.is_ascii_alphanumeric() && is_cjk(c)))
+        {
+            res.push(' ');
+        }
+        res.push(c);
+    }
+    res
+}
@@ -1,62 +1,320 @@
-use surrealdb::engine::remote::ws::{Client, Ws};
-use surrealdb::opt::auth::Root;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use deadpool::managed::{Manager, Metrics, Object, Pool, RecycleResult};
+use deadpool::{Runtime, Status};
+use fastrace::future::InSpan;
+use fastrace::prelude::{FutureExt, Span};
+use poem::{Endpoint, Middleware, Request};
+use surrealdb::engine::any::{self, Any};
+use surrealdb::opt::auth::{Record, Root};
 use surrealdb::Surreal;
+use tokio::sync::{Mutex, OnceCell};
+use log::{info, warn};
+
+use crate::config::DatabaseCfg;
+use crate::error::Error;
+
+const USER_ACCESS: &str = "user_access";
+const TOKEN_ACCESS: &str = "api_token_access";
+const POOL_TIMEOUT: Duration = Duration::from_secs(5);
+
+static DATABASE: OnceLock<Database> = OnceLock::new();
+// 内嵌数据库只能打开一次，连接池中的连接都是它的克隆
+static EMBEDDED: OnceCell<Surreal<Any>> = OnceCell::const_new();
+// 内嵌数据库的连接共享同一个会话，接口请求逐个处理，见Exclusive
+static EXCLUSIVE: Mutex<()> = Mutex::const_new(());
+
+pub(crate) type DbConn = Object<DbManager>;
+
+/// 数据库连接池：用户连接按请求认证，root连接只给session存储等内部功能使用
+struct Database {
+    cfg: DatabaseCfg,
+    user: Pool<DbManager>,
+    root: Pool<DbManager>,
+}
+
+/// 初始化数据库连接池，在web::start中调用一次
+pub(crate) fn init(cfg: &DatabaseCfg) {
+    DATABASE.get_or_init(|| {
+        info!("数据库地址：{}", cfg.endpoint);
+        Database {
+            cfg: cfg.clone(),
+            user: pool(cfg, false),
+            root: pool(cfg, true),
+        }
+    });
+}
+
+fn pool(cfg: &DatabaseCfg, root: bool) -> Pool<DbManager> {
+    Pool::builder(DbManager {
+        cfg: cfg.clone(),
+        root,
+    })
+    .max_size(cfg.pool_size)
+    .wait_timeout(Some(POOL_TIMEOUT))
+    .create_timeout(Some(POOL_TIMEOUT))
+    .recycle_timeout(Some(POOL_TIMEOUT))
+    .runtime(Runtime::Tokio1)
+    .build()
+    .expect("数据库连接池配置有误")
+}
+
+fn database() -> &'static Database {
+    DATABASE.get().expect("数据库连接池未初始化")
+}
+
+/// 取一个未认证的连接，用于注册、登录以及按请求做用户认证
+pub(crate) async fn user() -> Result<DbConn, Error> {
+    let conn = database().user.get().traced("db.pool.user").await;
+    #[cfg(feature = "metrics")]
+    if conn.is_err() {
+        crate::metrics::db_error("user");
+    }
+    shared_session(conn?).await
+}
+
+/// 取一个root连接
+pub(crate) async fn root() -> Result<DbConn, Error> {
+    let conn = database().root.get().traced("db.pool.root").await;
+    #[cfg(feature = "metrics")]
+    if conn.is_err() {
+        crate::metrics::db_error("root");
+    }
+    shared_session(conn?).await
+}
+
+/// 内嵌数据库的连接共享同一个会话，可能还带着上一个用户的认证信息，
+/// 取用时先清除：未认证的会话在没有开启认证的内嵌数据库上有root权限
+async fn shared_session(conn: DbConn) -> Result<DbConn, Error> {
+    if database().cfg.is_embedded() {
+        conn.invalidate().traced("db.invalidate").await?;
+    }
+    Ok(conn)
+}
 
-pub(crate) const ROOT_CREDENTIALS: Root = Root {
-    username: "root",
-    password: "root_pass",
-};
+/// 使用内嵌数据库时与接口请求互斥地执行，接口之外用到数据库的任务（清理、就绪检查、指标）须经过这里，
+/// 否则会和请求中的用户连接互相覆盖认证信息，见Exclusive
+pub(crate) async fn exclusive<F: Future>(f: F) -> F::Output {
+    if !database().cfg.is_embedded() {
+        return f.await;
+    }
+    let _guard = EXCLUSIVE.lock().await;
+    f.await
+}
+
+/// 取一个root连接并检查数据库是否可用
+pub(crate) async fn check() -> Result<(), Error> {
+    Ok(root().await?.health().traced("db.health").await?)
+}
+
+/// 连接池状态：(用户连接, root连接)
+pub(crate) fn status() -> (Status, Status) {
+    let db = database();
+    (db.user.status(), db.root.status())
+}
 
-pub(crate) async fn connect() -> Result<Surreal<Client>, surrealdb::Error> {
-    // let db: Surreal<Client> = Surreal::init();
-    // db.connect::<Ws>("ws://localhost:8000").await?;
-    let db = Surreal::new::<Ws>("localhost:8000").await?;
-    db.use_ns("toy").use_db("toy").await?;
+/// 用户注册、登录使用的记录访问凭证
+pub(crate) fn user_credentials<P>(params: P) -> Record<'static, P> {
+    credentials(USER_ACCESS, params)
+}
+
+/// 用个人访问令牌认证，params中须有token
+pub(crate) fn token_credentials<P>(params: P) -> Record<'static, P> {
+    credentials(TOKEN_ACCESS, params)
+}
+
+fn credentials<P>(access: &'static str, params: P) -> Record<'static, P> {
+    let cfg = &database().cfg;
+    Record {
+        namespace: &cfg.namespace,
+        database: &cfg.database,
+        access,
+        params,
+    }
+}
+
+pub(crate) async fn connect(cfg: &DatabaseCfg) -> Result<Surreal<Any>, surrealdb::Error> {
+    if cfg.is_embedded() {
+        let db = EMBEDDED
+            .get_or_try_init(|| async {
+                let db = any::connect(&cfg.endpoint).traced("db.connect").await?;
+                db.use_ns(&cfg.namespace)
+                    .use_db(&cfg.database)
+                    .traced("db.use_ns")
+                    .await?;
+                Ok::<_, surrealdb::Error>(db)
+            })
+            .await?;
+        return Ok(db.clone());
+    }
+
+    let db = any::connect(&cfg.endpoint).traced("db.connect").await?;
+    db.use_ns(&cfg.namespace)
+        .use_db(&cfg.database)
+        .traced("db.use_ns")
+        .await?;
 
     Ok(db)
 }
 
+/// 在当前请求的链路下为一次数据库调用创建子span，如db.query(..).traced("db.login.touch")，
+/// 没有链路时不记录
+pub(crate) trait Traced: IntoFuture + Sized {
+    fn traced(self, name: &'static str) -> InSpan<Self::IntoFuture> {
+        let span = Span::enter_with_local_parent(name).with_property(|| ("db.system", "surrealdb"));
+        self.into_future().in_span(span)
+    }
+}
+
+impl<F: IntoFuture> Traced for F {}
+
+/// 使用内嵌数据库时同一时间只处理一个接口请求：所有连接共享同一个会话，
+/// 并发的请求会互相覆盖认证信息。取连接时会清除会话的认证信息，
+/// 所以一个请求内取过root连接后，之前认证的用户连接就不能再用了
+pub struct Exclusive;
+
+impl<E: Endpoint> Middleware<E> for Exclusive {
+    type Output = ExclusiveEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ExclusiveEndpoint { ep }
+    }
+}
+
+pub struct ExclusiveEndpoint<E> {
+    ep: E,
+}
+
+impl<E: Endpoint> Endpoint for ExclusiveEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        exclusive(self.ep.call(req)).await
+    }
+}
+
+pub(crate) struct DbManager {
+    cfg: DatabaseCfg,
+    root: bool,
+}
+
+impl Manager for DbManager {
+    type Type = Surreal<Any>;
+    type Error = surrealdb::Error;
+
+    async fn create(&self) -> Result<Self::Type, Self::Error> {
+        let db = connect(&self.cfg).await?;
+        // 内嵌数据库没有开启认证，root连接不用登录
+        if self.root && !self.cfg.is_embedded() {
+            db.signin(Root {
+                username: &self.cfg.username,
+                password: &self.cfg.password,
+            })
+            .traced("db.signin_root")
+            .await?;
+        }
+        info!("数据库连接成功");
+        Ok(db)
+    }
+
+    async fn recycle(&self, db: &mut Self::Type, _: &Metrics) -> RecycleResult<Self::Error> {
+        // 用户连接归还时还带着上一个请求的认证信息，复用前先清除，内嵌数据库在取用时清除
+        if !self.root && !self.cfg.is_embedded() {
+            db.invalidate().traced("db.invalidate").await?;
+        }
+        // 健康检查失败的连接会被丢弃，下次取用时重新连接
+        db.health()
+            .traced("db.health")
+            .await
+            .inspect_err(|e| warn!("数据库连接已失效：{e}"))?;
+        Ok(())
+    }
+}
+
+/// 使用mem://数据库的测试共用一个运行时并逐个执行：内嵌数据库只能打开一次，
+/// 它的后台任务跑在第一次连接时的运行时上，测试之间也共用同一份数据
+#[cfg(test)]
+pub(crate) mod testing {
+    use std::future::Future;
+    use std::sync::{LazyLock, Mutex, PoisonError};
+
+    use tokio::runtime::Runtime;
+    use tokio::sync::OnceCell;
+
+    use crate::config::DatabaseCfg;
+    use crate::migration;
+    use crate::web::database;
+
+    static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| Runtime::new().expect("创建运行时失败"));
+    static SERIAL: Mutex<()> = Mutex::new(());
+    static MIGRATED: OnceCell<()> = OnceCell::const_new();
+
+    pub(crate) fn cfg() -> DatabaseCfg {
+        DatabaseCfg {
+            endpoint: "mem://".to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// 初始化数据库并执行全部迁移后运行f
+    pub(crate) fn run<F: Future>(f: F) -> F::Output {
+        let _serial = SERIAL.lock().unwrap_or_else(PoisonError::into_inner);
+        RUNTIME.block_on(async {
+            database::init(&cfg());
+            MIGRATED
+                .get_or_init(|| async {
+                    migration::apply(None).await.expect("数据库迁移失败")
+                })
+                .await;
+            f.await
+        })
+    }
+}
+
 #[cfg(test)]
 mod test {
     use std::collections::BTreeMap;
-    use futures::executor::block_on;
-    use serde_json::Value;
+    use std::time::Instant;
 
-    use crate::web::database::{connect, ROOT_CREDENTIALS};
+    use serde_json::{json, Value};
+    use surrealdb::RecordId;
+
+    use crate::config::DatabaseCfg;
+    use crate::web::database;
+    use crate::web::database::{connect, testing};
 
     #[test]
     fn db_select() {
-        block_on(test_db());
+        testing::run(db_select_async());
     }
 
-    async fn test_db() {
-        println!("1");
-        let db = match connect().await {
+    async fn db_select_async() {
+        let db = match connect(&testing::cfg()).await {
             Ok(db) => db,
             Err(e) => {
                 panic!("连接数据库失败：{e}");
             }
         };
-        println!("2");
-
-        if let Err(e) = db.signin(ROOT_CREDENTIALS).await {
-            panic!("root用户登录失败: {e}");
-        }
-        println!("3");
 
         const SESSION_ID: &str = "9ncx3SiLjSVPq2T2s1niQlg6JiChCRVoG3iEIJ4kCVI";
 
+        if let Err(e) = db
+            .upsert::<Option<BTreeMap<String, Value>>>(("session", SESSION_ID))
+            .content(json!({ "session": "{}" }))
+            .await
+        {
+            panic!("upsert session 失败：{e}");
+        }
+
         let entries = match db
-            // .select(("session", "9ncx3SiLjSVPq2T2s1niQlg6JiChCRVoG3iEIJ4kCVI"))
             .query("select * omit id from session where id = $id")
-            .bind(("id", SESSION_ID))
+            .bind(("id", RecordId::from_table_key("session", SESSION_ID)))
             .await
             .and_then(|mut res| res.take(0))
         {
-            Ok(Some::<BTreeMap<String, Value>>(e)) => {
-                // e.remove("id");
-                e
-            }
+            Ok(Some::<BTreeMap<String, Value>>(e)) => e,
             Ok(None) => {
                 panic!("select session 为空");
             }
@@ -74,6 +332,41 @@ mod test {
         {
             panic!("update session 失败：{e}");
         }
-        println!("success");
+    }
+
+    /// 压测连接池，默认连接本地SurrealDB：cargo test pool_load -- --ignored --nocapture
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn pool_load() {
+        const REQUESTS: usize = 2000;
+        database::init(&DatabaseCfg::default());
+
+        let start = Instant::now();
+        let tasks: Vec<_> = (0..REQUESTS)
+            .map(|_| {
+                tokio::spawn(async {
+                    let db = database::user().await?;
+                    db.query("RETURN 1")
+                        .await
+                        .map_err(crate::error::Error::DbException)?;
+                    Ok::<_, crate::error::Error>(())
+                })
+            })
+            .collect();
+
+        let mut failed = 0;
+        for task in tasks {
+            if !matches!(task.await, Ok(Ok(()))) {
+                failed += 1;
+            }
+        }
+        let elapsed = start.elapsed();
+
+        println!(
+            "{REQUESTS}次请求，失败{failed}次，耗时{elapsed:?}，{:.0} req/s，连接池：{:?}",
+            REQUESTS as f64 / elapsed.as_secs_f64(),
+            database::status().0
+        );
+        assert_eq!(failed, 0);
     }
 }
@@ -0,0 +1,124 @@
+use std::net::IpAddr;
+
+use poem::http::HeaderMap;
+use poem::{Endpoint, Middleware, Request, Result};
+
+/// 反向代理转发的客户端IP
+#[derive(Clone, Debug)]
+pub(crate) struct ForwardedFor(pub(crate) IpAddr);
+
+/// 在反向代理后以HTTP运行时，从X-Forwarded-For（或X-Real-IP）中取得客户端IP，
+/// 只采信来自可信代理的请求，否则客户端可以伪造
+pub struct Forwarded {
+    proxies: Vec<IpAddr>,
+}
+
+impl Forwarded {
+    /// 代理地址在配置校验时已检查，这里忽略无法解析的
+    pub fn new(proxies: &[String]) -> Self {
+        Forwarded {
+            proxies: proxies.iter().filter_map(|p| p.parse().ok()).collect(),
+        }
+    }
+}
+
+impl<E: Endpoint> Middleware<E> for Forwarded {
+    type Output = ForwardedEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        ForwardedEndpoint {
+            ep,
+            proxies: self.proxies.clone(),
+        }
+    }
+}
+
+pub struct ForwardedEndpoint<E> {
+    ep: E,
+    proxies: Vec<IpAddr>,
+}
+
+impl<E: Endpoint> Endpoint for ForwardedEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, mut req: Request) -> Result<Self::Output> {
+        let peer = req.remote_addr().as_socket_addr().map(|a| a.ip());
+        if let Some(peer) = peer
+            && let Some(ip) = client_ip(req.headers(), peer, &self.proxies)
+        {
+            req.extensions_mut().insert(ForwardedFor(ip));
+        }
+        self.ep.call(req).await
+    }
+}
+
+/// 对端是可信代理时才采信转发头。X-Forwarded-For由各级代理依次在后面追加，
+/// 最左边的可能是客户端伪造的，所以从右往左跳过可信代理，第一个不可信的地址就是客户端
+fn client_ip(headers: &HeaderMap, peer: IpAddr, proxies: &[IpAddr]) -> Option<IpAddr> {
+    // 监听IPv6时IPv4对端是::ffff:a.b.c.d的形式
+    let trusted = |ip: &IpAddr| proxies.contains(&ip.to_canonical());
+    if !trusted(&peer) {
+        return None;
+    }
+    let header = |name| headers.get(name).and_then(|v| v.to_str().ok());
+    let Some(forwarded) = header("x-forwarded-for") else {
+        return header("x-real-ip").and_then(|v| v.trim().parse().ok());
+    };
+    let mut client = None;
+    for hop in forwarded.rsplit(',') {
+        let ip = hop.trim().parse().ok()?;
+        if !trusted(&ip) {
+            return Some(ip);
+        }
+        client = Some(ip);
+    }
+    // 都是可信代理时取最左边的
+    client
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::IpAddr;
+
+    use poem::http::HeaderMap;
+
+    use crate::web::forwarded::client_ip;
+
+    #[test]
+    fn forwarded_for() {
+        let proxies: Vec<IpAddr> = vec!["10.0.0.1".parse().unwrap(), "10.0.0.2".parse().unwrap()];
+        let ip = |peer: &str, headers: &[(&'static str, &str)]| {
+            let mut map = HeaderMap::new();
+            for (name, value) in headers {
+                map.insert(*name, value.parse().unwrap());
+            }
+            client_ip(&map, peer.parse().unwrap(), &proxies).map(|ip| ip.to_string())
+        };
+        assert_eq!(ip("10.0.0.1", &[]), None);
+        assert_eq!(
+            ip("10.0.0.1", &[("x-forwarded-for", "203.0.113.7")]).as_deref(),
+            Some("203.0.113.7")
+        );
+        // 客户端伪造的最左边地址不被采信
+        assert_eq!(
+            ip(
+                "10.0.0.1",
+                &[("x-forwarded-for", "1.2.3.4, 203.0.113.7, 10.0.0.2")]
+            )
+            .as_deref(),
+            Some("203.0.113.7")
+        );
+        assert_eq!(
+            ip("::ffff:10.0.0.1", &[("x-forwarded-for", "203.0.113.7")]).as_deref(),
+            Some("203.0.113.7")
+        );
+        // 对端不是可信代理时忽略转发头
+        assert_eq!(ip("203.0.113.9", &[("x-forwarded-for", "1.2.3.4")]), None);
+        assert_eq!(ip("203.0.113.9", &[("x-real-ip", "1.2.3.4")]), None);
+        assert_eq!(
+            ip("10.0.0.1", &[("x-real-ip", "2001:db8::1")]).as_deref(),
+            Some("2001:db8::1")
+        );
+        assert_eq!(ip("10.0.0.1", &[("x-forwarded-for", "unknown")]), None);
+    }
+}
@@ -0,0 +1,98 @@
+use std::fmt::Display;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use poem::http::StatusCode;
+use poem::web::{Data, Json};
+use poem::{handler, IntoResponse, Response};
+use serde_json::{json, Map, Value};
+use tokio::time::error::Elapsed;
+use tokio::time::timeout;
+use log::{info, warn};
+
+use toy_schema::protocol::PROTOCOL_VERSION;
+
+use crate::datetime;
+use crate::web::database;
+use crate::web::session::SessionStore;
+
+/// 就绪检查中每一项的超时时间
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+// 收到停机信号后置位，优雅停机期间就绪检查返回未就绪
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+
+/// 等待停机信号，之后的就绪检查都返回未就绪
+pub(crate) async fn watch(shutdown: impl Future<Output = ()>) {
+    shutdown.await;
+    SHUTTING_DOWN.store(true, Ordering::Relaxed);
+    info!("开始停机，就绪检查返回未就绪");
+}
+
+/// 进程存活
+#[handler]
+pub async fn healthz() -> Json<Value> {
+    Json(json!({"status": "ok"}))
+}
+
+/// 数据库和session存储都可用时就绪，否则返回503和出错的项
+#[handler]
+pub async fn readyz(store: Data<&SessionStore>) -> Response {
+    if SHUTTING_DOWN.load(Ordering::Relaxed) {
+        return not_ready(json!({"status": "shutting_down"}));
+    }
+    let (db, session) = tokio::join!(
+        timeout(CHECK_TIMEOUT, database::exclusive(database::check())),
+        timeout(CHECK_TIMEOUT, database::exclusive(store.check()))
+    );
+    let checks: Map<String, Value> = [
+        ("database", outcome("database", db)),
+        ("session", outcome("session", session)),
+    ]
+    .into_iter()
+    .map(|(name, status)| (name.to_owned(), json!(status)))
+    .collect();
+    let ready = checks.values().all(|status| status == "ok");
+    if ready {
+        Json(json!({"status": "ready", "checks": checks})).into_response()
+    } else {
+        not_ready(json!({"status": "not_ready", "checks": checks}))
+    }
+}
+
+/// 版本、git提交和构建时间（UTC），见build.rs
+#[handler]
+pub async fn version() -> Json<Value> {
+    let build_time = env!("TOYS_BUILD_TIME")
+        .parse()
+        .map(|secs| format!("{} UTC", datetime::readable(&datetime::timestamp(secs))))
+        .unwrap_or_default();
+    Json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_hash": env!("TOYS_GIT_HASH"),
+        "build_time": build_time,
+        "protocol": PROTOCOL_VERSION,
+    }))
+}
+
+/// 检查结果：ok、error或timeout。未认证也能访问，出错的原因只写日志，不返回给调用方
+fn outcome<E: Display>(name: &str, res: Result<Result<(), E>, Elapsed>) -> &'static str {
+    match res {
+        Ok(Ok(())) => "ok",
+        Ok(Err(e)) => {
+            warn!("就绪检查失败 {name}: {e}");
+            "error"
+        }
+        Err(_) => {
+            warn!("就绪检查失败 {name}: {}秒内未响应", CHECK_TIMEOUT.as_secs());
+            "timeout"
+        }
+    }
+}
+
+fn not_ready(body: Value) -> Response {
+    let mut resp = Json(body).into_response();
+    resp.set_status(StatusCode::SERVICE_UNAVAILABLE);
+    resp
+}
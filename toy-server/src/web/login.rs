@@ -0,0 +1,156 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use poem::http::header;
+use poem::session::Session;
+use poem::web::Data;
+use poem::{handler, Request};
+use surrealdb::RecordId;
+use log::info;
+
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{LoginSession, RevokeReq};
+
+use crate::error::Error;
+use crate::web::database::{self, DbConn, Traced};
+use crate::web::forwarded::ForwardedFor;
+use crate::web::speedy_data::Speedy;
+
+/// session中保存登录记录id的键
+pub(crate) const LOGIN_KEY: &str = "login";
+
+const CREATE_LOGIN: &str =
+    "CREATE ONLY login SET user = $auth.id, user_agent = $user_agent, ip = $ip
+    RETURN VALUE record::id(id)";
+const TOUCH_LOGIN: &str =
+    "UPDATE type::thing('login', $id) SET last_seen = time::now() RETURN VALUE id";
+const LIST_LOGINS: &str = "SELECT record::id(id) AS id, user_agent, ip,
+    time::format(created_at, '%Y-%m-%d %H:%M:%S') AS created_at,
+    time::format(last_seen, '%Y-%m-%d %H:%M:%S') AS last_seen
+    FROM login ORDER BY last_seen DESC";
+const DELETE_STALE: &str =
+    "DELETE login WHERE last_seen < time::now() - duration::from::secs($max_age)";
+#[cfg(feature = "metrics")]
+const COUNT_ACTIVE: &str =
+    "RETURN count(SELECT id FROM login WHERE last_seen >= time::now() - duration::from::secs($max_age))";
+
+/// 记录一次登录，db须是刚登录的用户连接，返回登录记录id
+pub(crate) async fn create(db: &DbConn, req: &Request) -> Result<String, Error> {
+    let user_agent = req
+        .headers()
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let ip = client_ip(req);
+    info!("新登录：{ip} {user_agent}");
+
+    let id: Option<String> = db
+        .query(CREATE_LOGIN)
+        .bind(("user_agent", user_agent))
+        .bind(("ip", ip))
+        .traced("db.login.create")
+        .await?
+        .take(0)?;
+    id.ok_or(Error::SignInFail)
+}
+
+/// 检查登录未被撤销，并刷新最后活动时间
+pub(crate) async fn touch(db: &DbConn, session: &Session) -> Result<bool, Error> {
+    let Some(id) = session.get::<String>(LOGIN_KEY) else {
+        return Ok(false);
+    };
+    let touched: Vec<RecordId> = db
+        .query(TOUCH_LOGIN)
+        .bind(("id", id))
+        .traced("db.login.touch")
+        .await?
+        .take(0)?;
+    Ok(!touched.is_empty())
+}
+
+/// 删除不活跃超过max_age的登录记录，由session清理任务调用
+pub(crate) async fn delete_stale(max_age: Duration) -> Result<(), Error> {
+    let db = database::root().await?;
+    db.query(DELETE_STALE)
+        .bind(("max_age", max_age.as_secs()))
+        .traced("db.login.delete_stale")
+        .await?
+        .check()?;
+    Ok(())
+}
+
+/// max_age内有活动的登录数
+#[cfg(feature = "metrics")]
+pub(crate) async fn count_active(max_age: Duration) -> Result<u64, Error> {
+    let db = database::root().await?;
+    let count: Option<u64> = db
+        .query(COUNT_ACTIVE)
+        .bind(("max_age", max_age.as_secs()))
+        .traced("db.login.count_active")
+        .await?
+        .take(0)?;
+    Ok(count.unwrap_or_default())
+}
+
+pub(crate) fn client_ip(req: &Request) -> String {
+    if let Some(ForwardedFor(ip)) = req.extensions().get::<ForwardedFor>() {
+        return ip.to_string();
+    }
+    let addr = req.remote_addr();
+    addr.as_socket_addr()
+        .map(|a| a.ip().to_string())
+        .unwrap_or_else(|| addr.to_string())
+}
+
+#[handler]
+pub async fn sessions(
+    db: Data<&Arc<DbConn>>,
+    session: &Session,
+) -> poem::Result<Speedy<Vec<LoginSession>>> {
+    let current = session.get::<String>(LOGIN_KEY);
+    let mut logins: Vec<LoginSession> = db
+        .query(LIST_LOGINS)
+        .traced("db.login.list")
+        .await
+        .and_then(|mut res| res.take(0))
+        .map_err(Error::DbException)?;
+    for login in logins.iter_mut() {
+        login.current = current.as_ref() == Some(&login.id);
+    }
+    Ok(Speedy(logins))
+}
+
+#[handler]
+pub async fn revoke_session(
+    req: Speedy<RevokeReq>,
+    db: Data<&Arc<DbConn>>,
+    session: &Session,
+) -> poem::Result<Speedy<Empty>> {
+    let current = session.get::<String>(LOGIN_KEY);
+    match &req.id {
+        Some(id) => {
+            info!("撤销登录 {id}");
+            db.query("DELETE type::thing('login', $id)")
+                .bind(("id", id.clone()))
+                .traced("db.login.revoke")
+                .await
+                .and_then(|res| res.check())
+                .map_err(Error::DbException)?;
+        }
+        None => {
+            info!("撤销全部登录");
+            db.query("DELETE login")
+                .traced("db.login.revoke_all")
+                .await
+                .and_then(|res| res.check())
+                .map_err(Error::DbException)?;
+        }
+    }
+
+    // 撤销的包括当前登录时，当前session一并清除
+    if req.id.is_none() || req.id == current {
+        session.purge();
+    }
+    Ok(Speedy(Empty {}))
+}
@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use tokio::fs;
+use log::{error, info};
+
+use crate::config::{MailCfg, MailerKind};
+use crate::error::Error;
+
+static MAILER: OnceLock<Box<dyn Mailer>> = OnceLock::new();
+
+pub(crate) struct Mail {
+    pub(crate) to: String,
+    pub(crate) subject: String,
+    pub(crate) body: String,
+}
+
+/// 邮件发送方式，按配置选择实现
+#[async_trait::async_trait]
+pub(crate) trait Mailer: Send + Sync {
+    async fn send(&self, mail: Mail) -> Result<(), Error>;
+}
+
+/// 初始化邮件发送，在web::start中调用一次
+pub(crate) fn init(cfg: &MailCfg) {
+    MAILER.get_or_init(|| {
+        info!("邮件发送方式：{:?}", cfg.mailer);
+        match cfg.mailer {
+            MailerKind::File => Box::new(FileMailer {
+                from: cfg.from.clone(),
+                dir: PathBuf::from(&cfg.dir),
+            }),
+        }
+    });
+}
+
+pub(crate) fn mailer() -> &'static dyn Mailer {
+    MAILER.get().expect("邮件发送未初始化").as_ref()
+}
+
+/// 把邮件写成目录下的.eml文件，不真正发送
+struct FileMailer {
+    from: String,
+    dir: PathBuf,
+}
+
+#[async_trait::async_trait]
+impl Mailer for FileMailer {
+    async fn send(&self, mail: Mail) -> Result<(), Error> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_nanos());
+        let path = self.dir.join(format!("{nanos}.eml"));
+        let content = format!(
+            "From: {}\r\nTo: {}\r\nSubject: {}\r\nContent-Type: text/plain; charset=utf-8\r\n\r\n{}\r\n",
+            self.from, mail.to, mail.subject, mail.body
+        );
+
+        let res = match fs::create_dir_all(&self.dir).await {
+            Ok(()) => fs::write(&path, content).await,
+            Err(e) => Err(e),
+        };
+        res.map_err(|e| {
+            error!("邮件写入{}失败：{e}", path.display());
+            Error::MailFail
+        })?;
+        info!("邮件已写入{}", path.display());
+        Ok(())
+    }
+}
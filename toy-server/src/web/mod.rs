@@ -1,86 +1,379 @@
-use std::fs;
 use std::future::Future;
+use std::io;
+use std::sync::{Arc, LazyLock};
 use std::time::Duration;
 
-use arc_swap::access::Access;
-use poem::endpoint::StaticFilesEndpoint;
-use poem::listener::{Listener, RustlsCertificate, RustlsConfig, TcpListener};
+use futures::FutureExt;
+use poem::listener::{Listener, TcpListener};
 use poem::middleware::{CatchPanic, Compression, NormalizePath, Tracing, TrailingSlash};
 use poem::session::{CookieConfig, ServerSession};
-use poem::{handler, post, EndpointExt, IntoEndpoint, Route, Server};
+use poem::{get, handler, post, EndpointExt, IntoEndpoint, Route, Server};
+use serde::Deserialize;
+use tokio::sync::Notify;
+use toml::Value;
+
+use toy_schema::config::{ConfigChange, ReloadRes};
+use toy_schema::sign::{Role, Scope};
 
 use crate::config;
-use crate::web::auth::{logout, sign_check, sign_in, sign_up, Auth};
+use crate::config::{Config, WebCfg};
+use crate::error::Error;
+use crate::init;
+#[cfg(feature = "metrics")]
+use crate::metrics;
+use crate::migration;
+use crate::web::assets::Assets;
+use crate::web::auth::{logout, sign_check, sign_in, sign_up, Auth, RequireRole};
 use crate::web::content_type_utf8_mw::ContentTypeUtf8;
-use crate::web::session::SurrealStorage;
+use crate::web::database::Exclusive;
+use crate::web::forwarded::Forwarded;
+use crate::web::rate_limit::RateLimit;
+use crate::web::root_span::RootSpan;
+use crate::web::session::SessionStore;
+use crate::web::speedy_data::{Negotiate, Speedy};
 use crate::GLOBAL_CONFIG;
 
+pub(crate) mod account;
+mod api_token;
+mod assets;
 pub(crate) mod auth;
+mod chinese;
 mod content_type_utf8_mw;
 pub(crate) mod database;
+mod forwarded;
+mod health;
+pub(crate) mod login;
+mod mailer;
+mod openapi;
+mod rate_limit;
+mod root_span;
 pub(crate) mod session;
+mod settings;
 pub(crate) mod speedy_data;
 mod sudoku;
+mod tls;
+mod totp;
 
-pub(crate) async fn start(signal: impl Future<Output = ()>) {
-    let cfg = GLOBAL_CONFIG
-        .get()
-        .unwrap()
-        .map(|cfg: &config::Config| &cfg.web)
-        .load();
-
-    let route = Route::new()
-        .nest("/api", apis().await)
-        .nest(
-            "/",
-            StaticFilesEndpoint::new(&cfg.assets_path)
-                .prefer_utf8(true)
-                .index_file("index.html")
-                .fallback_to_index()
-                .with(ContentTypeUtf8),
-        )
+// 重新加载配置时，这些配置项有变化需要重新监听
+const REBIND_KEYS: [&str; 5] = [
+    "web.address",
+    "web.tls.",
+    "web.trusted_proxies",
+    "web.openapi.",
+    "web.metrics.",
+];
+
+/// 通知正在运行的服务停止，按新配置重新监听
+static REBIND: LazyLock<Notify> = LazyLock::new(Notify::new);
+
+pub(crate) async fn start(signal: impl Future<Output = ()> + Send + 'static) {
+    let config = GLOBAL_CONFIG.get().unwrap().load_full();
+    database::init(&config.database);
+    mailer::init(&config.mail);
+    rate_limit::init(&config.web.rate_limit);
+    assets::init(&config.web.assets_path);
+    #[cfg(feature = "metrics")]
+    metrics::init();
+    #[cfg(not(feature = "metrics"))]
+    if config.web.metrics.enabled {
+        log::warn!("已配置web.metrics，但编译时未启用metrics特性，不导出指标");
+    }
+    if let Err(e) = migration::apply(None).await {
+        log::error!("数据库迁移失败: {e:?}");
+        return;
+    }
+    // 停机信号同时通知web服务和后台任务
+    let signal = signal.shared();
+    tokio::spawn(health::watch(signal.clone()));
+    let store = match SessionStore::new(&config.web).await {
+        Ok(store) => store,
+        Err(e) => {
+            log::error!("session存储初始化失败: {e:?}");
+            return;
+        }
+    };
+    let sweeper = tokio::spawn(session::sweep(
+        store.clone(),
+        Duration::from_secs(config.web.session_max_age),
+        signal.clone(),
+    ));
+
+    // 重新加载配置后需要重新监听时，停止当前的服务，按新配置再启动
+    let mut config = config;
+    let mut previous = None;
+    loop {
+        if let Err(e) = serve(&config.web, store.clone(), signal.clone()).await {
+            log::error!("服务异常: {e}");
+            // 按新配置监听失败时退回原来的配置
+            match previous.take() {
+                Some(cfg) => {
+                    restore_listen(&cfg);
+                    config = cfg;
+                    continue;
+                }
+                None => {
+                    sweeper.abort();
+                    return;
+                }
+            }
+        }
+        if signal.clone().now_or_never().is_some() {
+            break;
+        }
+        previous = Some(config);
+        config = GLOBAL_CONFIG.get().unwrap().load_full();
+        log::info!("按新配置重新监听 {}", config.web.address);
+    }
+    let _ = sweeper.await;
+}
+
+/// 按配置监听，收到停机信号或需要重新监听时返回
+async fn serve(
+    cfg: &WebCfg,
+    store: SessionStore,
+    signal: impl Future<Output = ()> + Clone + Send + 'static,
+) -> io::Result<()> {
+    let listener = if cfg.tls.enabled {
+        let certificates = tls::certificates(cfg.tls.clone()).map_err(|e| {
+            io::Error::new(
+                e.kind(),
+                format!("证书加载失败（{}、{}）: {e}", cfg.tls.cert, cfg.tls.key),
+            )
+        })?;
+        TcpListener::bind(cfg.address.clone())
+            .rustls(certificates)
+            .boxed()
+    } else {
+        log::info!("未启用HTTPS，以HTTP运行并采信可信代理的X-Forwarded-For");
+        TcpListener::bind(cfg.address.clone()).boxed()
+    };
+    let stop = async move {
+        tokio::select! {
+            _ = signal => {}
+            _ = REBIND.notified() => {}
+        }
+    }
+    .shared();
+
+    let mut route = Route::new()
+        .at("/healthz", get(health::healthz))
+        .at("/readyz", get(health::readyz).data(store.clone()))
+        .at("/version", get(health::version))
+        .nest("/api", apis(cfg, store));
+    if cfg.openapi.enabled {
+        route = route.nest(&cfg.openapi.path, openapi::routes(&cfg.openapi));
+    }
+    #[cfg(feature = "metrics")]
+    if cfg.metrics.enabled && cfg.metrics.address.is_empty() {
+        route = route.at(&cfg.metrics.path, get(metrics::export));
+    }
+    let route = route
+        .nest("/", Assets.with(ContentTypeUtf8))
+        .with_if(!cfg.tls.enabled, Forwarded::new(&cfg.trusted_proxies))
         .with(NormalizePath::new(TrailingSlash::Trim))
         .with(Compression::new())
         .with(Tracing)
         .with(CatchPanic::new());
 
-    let res = Server::new(
-        TcpListener::bind(&cfg.address).rustls(
-            RustlsConfig::new().fallback(
-                RustlsCertificate::new()
-                    .key(fs::read("localhost+3-key.pem").unwrap())
-                    .cert(fs::read("localhost+3.pem").unwrap()),
-            ),
-        ),
-    )
-    .run_with_graceful_shutdown(route, signal, Some(Duration::from_secs(30)))
-    .await;
-
-    if let Err(e) = res {
-        log::error!("服务异常: {}", e);
+    let redirect = (cfg.tls.enabled && !cfg.tls.redirect_address.is_empty()).then(|| {
+        let server = Server::new(TcpListener::bind(cfg.tls.redirect_address.clone()))
+            .run_with_graceful_shutdown(
+                tls::redirect(tls::port(&cfg.address)),
+                stop.clone(),
+                Some(Duration::from_secs(5)),
+            );
+        tokio::spawn(async {
+            if let Err(e) = server.await {
+                log::error!("HTTP跳转服务异常: {e}");
+            }
+        })
+    });
+
+    // 指标单独监听时只以HTTP提供，不经过主服务的中间件
+    #[cfg(feature = "metrics")]
+    let exporter = (cfg.metrics.enabled && !cfg.metrics.address.is_empty()).then(|| {
+        let route = Route::new().at(&cfg.metrics.path, get(metrics::export));
+        let server = Server::new(TcpListener::bind(cfg.metrics.address.clone()))
+            .run_with_graceful_shutdown(route, stop.clone(), Some(Duration::from_secs(5)));
+        tokio::spawn(async {
+            if let Err(e) = server.await {
+                log::error!("指标服务异常: {e}");
+            }
+        })
+    });
+
+    let res = Server::new(listener)
+        .run_with_graceful_shutdown(route, stop, Some(Duration::from_secs(30)))
+        .await;
+    if let Some(redirect) = redirect {
+        let _ = redirect.await;
+    }
+    #[cfg(feature = "metrics")]
+    if let Some(exporter) = exporter {
+        let _ = exporter.await;
     }
+    res
 }
 
-async fn apis() -> impl IntoEndpoint {
-    Route::new()
-        .at("/sign_up", post(sign_up))
-        .at("/sign_in", post(sign_in))
-        .at("/sign_check", post(sign_check))
-        .nest("/", need_auth())
+/// 登记路由的同时记下路径，指标按它归类路由，openapi的测试按它核对文档
+struct Routes {
+    route: Route,
+    paths: Vec<&'static str>,
+}
+
+impl Routes {
+    fn new() -> Self {
+        Self {
+            route: Route::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    fn at<E>(mut self, path: &'static str, ep: E) -> Self
+    where
+        E: IntoEndpoint,
+        E::Endpoint: 'static,
+    {
+        self.route = self.route.at(path, ep);
+        self.paths.push(path);
+        self
+    }
+}
+
+/// 所有接口的路径，不含/api前缀
+#[cfg(any(test, feature = "metrics"))]
+pub(crate) fn api_paths() -> Vec<&'static str> {
+    [public().paths, need_auth().paths].concat()
+}
+
+/// 接口路由，增删接口时同步修改openapi::document，测试会核对两边的路径
+fn apis(cfg: &WebCfg, store: SessionStore) -> impl IntoEndpoint {
+    let route = public()
+        .route
+        .nest("/", need_auth().route.with(Auth::session()))
         .with(ServerSession::new(
-            CookieConfig::default(),
-            SurrealStorage::new().await.expect("Session数据库异常"),
+            CookieConfig::default().max_age(Duration::from_secs(cfg.session_max_age)),
+            store,
         ))
+        .with(Exclusive)
+        .with(Negotiate)
+        .with(RootSpan);
+    #[cfg(feature = "metrics")]
+    let route = route.with(metrics::Metrics::new(api_paths()));
+    route
+}
+fn public() -> Routes {
+    Routes::new()
+        .at("/sign_up", post(sign_up).with(RateLimit))
+        .at("/sign_in", post(sign_in).with(RateLimit))
+        .at("/sign_in_totp", post(totp::sign_in_totp).with(RateLimit))
+        .at("/sign_check", post(sign_check))
+        .at(
+            "/forgot_password",
+            post(account::forgot_password).with(RateLimit),
+        )
+        .at(
+            "/reset_password",
+            post(account::reset_password).with(RateLimit),
+        )
+        // 以下接口也可以用个人访问令牌调用
+        .at(
+            "/sudoku",
+            post(sudoku::resolve).with(Auth::or_token(Scope::Sudoku)),
+        )
+        .at(
+            "/convert",
+            post(chinese::convert).with(Auth::or_token(Scope::Chinese)),
+        )
+        .at(
+            "/pinyin",
+            post(chinese::pinyin).with(Auth::or_token(Scope::Chinese)),
+        )
 }
-fn need_auth() -> impl IntoEndpoint {
-    Route::new()
+fn need_auth() -> Routes {
+    Routes::new()
         .at("/logout", post(logout))
-        .at("/reload", post(reload))
-        .at("/sudoku", post(sudoku::resolve))
-        .with(Auth {})
+        .at("/sessions", post(login::sessions))
+        .at("/revoke_session", post(login::revoke_session))
+        .at("/profile", post(account::profile))
+        .at("/update_profile", post(account::update_profile))
+        .at("/change_password", post(account::change_password))
+        .at("/delete_account", post(account::delete_account))
+        .at("/totp_setup", post(totp::totp_setup))
+        .at("/totp_enable", post(totp::totp_enable))
+        .at("/totp_disable", post(totp::totp_disable))
+        .at("/api_tokens", post(api_token::api_tokens))
+        .at("/create_token", post(api_token::create_token))
+        .at("/revoke_token", post(api_token::revoke_token))
+        .at("/reload", post(reload).with(RequireRole(Role::Admin)))
+        .at(
+            "/config",
+            post(settings::config_view).with(RequireRole(Role::Admin)),
+        )
+        .at(
+            "/save_config",
+            post(settings::save_config).with(RequireRole(Role::Admin)),
+        )
+        .at(
+            "/rollback_config",
+            post(settings::rollback_config).with(RequireRole(Role::Admin)),
+        )
 }
 
 #[handler]
-async fn reload() {
-    config::reload();
+async fn reload() -> poem::Result<Speedy<ReloadRes>> {
+    // 配置有误时保留原配置，返回所有错误
+    let mut changes = config::reload().map_err(Error::Validation)?;
+    reconfigure(&mut changes);
+    Ok(Speedy(ReloadRes { changes }))
+}
+
+/// 按新配置监听失败时，把需要重新监听的配置项恢复为正在使用的值，其他已生效的配置项保留
+fn restore_listen(cfg: &Config) {
+    let global = GLOBAL_CONFIG.get().unwrap();
+    let (Ok(mut restored), Ok(listening)) =
+        (Value::try_from(&**global.load()), Value::try_from(cfg))
+    else {
+        return;
+    };
+    for key in REBIND_KEYS {
+        let key = key.trim_end_matches('.');
+        if let Some(value) = key.split('.').try_fold(&listening, |v, k| v.get(k)) {
+            config::merge(&mut restored, config::nested(key, ".", value.clone()));
+        }
+    }
+    match Config::deserialize(restored) {
+        Ok(restored) => {
+            global.store(Arc::new(restored));
+            log::warn!("重新监听失败，监听配置已恢复为 {}", cfg.web.address);
+        }
+        Err(e) => log::error!("恢复监听配置失败: {e}"),
+    }
+}
+
+/// 让变化的配置项立即生效，并标记出已生效的项，其余的需要重启
+fn reconfigure(changes: &mut [ConfigChange]) {
+    let config = GLOBAL_CONFIG.get().unwrap().load();
+    let mut rebind = false;
+    for change in changes.iter_mut() {
+        change.applied = match change.key.as_str() {
+            "log.level" => init::set_log_level(&config.log.level),
+            "log.directory" => init::reopen_log(&config.log.directory),
+            "web.assets_path" => {
+                assets::reload(&config.web.assets_path);
+                true
+            }
+            // 每次使用时读取
+            "mail.reset_url" | "mail.reset_ttl" | "trace.sample_ratio" => true,
+            key if config.web.rebind && REBIND_KEYS.iter().any(|k| key.starts_with(k)) => {
+                rebind = true;
+                true
+            }
+            _ => false,
+        };
+    }
+    if rebind {
+        log::info!("监听配置有变化，重新监听");
+        REBIND.notify_waiters();
+    }
 }
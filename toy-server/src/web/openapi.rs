@@ -0,0 +1,371 @@
+use std::sync::OnceLock;
+
+use poem::endpoint::make_sync;
+use poem::http::header;
+use poem::web::Html;
+use poem::{IntoEndpoint, Response, Route};
+use schemars::generate::{SchemaGenerator, SchemaSettings};
+use schemars::{JsonSchema, Schema};
+use serde_json::{json, Map, Value};
+
+use toy_schema::chinese::{ConvertReq, ConvertRes, PinyinReq, Ruby};
+use toy_schema::config::{ConfigView, ReloadRes, RollbackConfigReq, SaveConfigReq};
+use toy_schema::error::ApiError;
+use toy_schema::protocol::{Empty, PROTOCOL_HEADER, PROTOCOL_VERSION, TRACE_ID_HEADER};
+use toy_schema::sign::{
+    ApiToken, ChangePasswordReq, CreateTokenReq, CreateTokenRes, DeleteAccountReq,
+    ForgotPasswordReq, LoginSession, Profile, RecoveryCodes, ResetPasswordReq, RevokeReq,
+    RevokeTokenReq, Scope, SignCheckRes, SignReq, SignRes, TotpCodeReq, TotpSetup,
+};
+use toy_schema::sudoku::SudokuBoard;
+
+use crate::config::OpenApiCfg;
+
+static DOCUMENT: OnceLock<String> = OnceLock::new();
+
+/// 脚本和样式由前端构建时从固定版本的swagger-ui-dist复制到静态文件目录
+const EXPLORER: &str = r##"<!DOCTYPE html>
+<html lang="zh-CN">
+<head>
+  <meta charset="utf-8" />
+  <title>toys API</title>
+  <link rel="stylesheet" href="/swagger-ui/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="/swagger-ui/swagger-ui-bundle.js"></script>
+  <script>
+    SwaggerUIBundle({ url: "{spec_url}", dom_id: "#swagger-ui", withCredentials: true });
+  </script>
+</body>
+</html>
+"##;
+
+/// 接口的访问方式
+enum Access {
+    Public,
+    /// 公开，按IP限流
+    RateLimited,
+    Session,
+    /// session或有此权限范围的访问令牌
+    Token(Scope),
+    Admin,
+}
+
+struct Operation {
+    path: &'static str,
+    tag: &'static str,
+    summary: &'static str,
+    access: Access,
+    request: Option<Schema>,
+    response: Schema,
+}
+
+/// 按web::apis中的路由逐个登记接口，请求和响应类型都来自toy-schema
+struct Document {
+    generator: SchemaGenerator,
+    operations: Vec<Operation>,
+}
+
+impl Document {
+    fn new() -> Self {
+        let settings = SchemaSettings::draft2020_12().with(|s| {
+            s.definitions_path = "#/components/schemas/".into();
+        });
+        Document {
+            generator: settings.into_generator(),
+            operations: Vec::new(),
+        }
+    }
+
+    fn post<Req: JsonSchema, Res: JsonSchema>(
+        mut self,
+        path: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+        access: Access,
+    ) -> Self {
+        let request = Some(self.generator.subschema_for::<Req>());
+        self.add::<Res>(path, tag, summary, access, request)
+    }
+
+    /// 没有请求体的接口
+    fn post_empty<Res: JsonSchema>(
+        self,
+        path: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+        access: Access,
+    ) -> Self {
+        self.add::<Res>(path, tag, summary, access, None)
+    }
+
+    fn add<Res: JsonSchema>(
+        mut self,
+        path: &'static str,
+        tag: &'static str,
+        summary: &'static str,
+        access: Access,
+        request: Option<Schema>,
+    ) -> Self {
+        let response = self.generator.subschema_for::<Res>();
+        self.operations.push(Operation {
+            path,
+            tag,
+            summary,
+            access,
+            request,
+            response,
+        });
+        self
+    }
+
+    fn build(mut self) -> Value {
+        let error = self.generator.subschema_for::<ApiError>();
+        let mut paths = Map::new();
+        for op in &self.operations {
+            let (security, description) = match &op.access {
+                Access::Public => (json!([]), String::new()),
+                Access::RateLimited => (json!([]), "按IP限流，超出时返回429".to_owned()),
+                Access::Session => (json!([{"session": []}]), String::new()),
+                Access::Token(scope) => (
+                    json!([{"session": []}, {"token": []}]),
+                    format!("也可以使用有`{}`权限范围的访问令牌", scope_name(*scope)),
+                ),
+                Access::Admin => (json!([{"session": []}]), "仅限管理员".to_owned()),
+            };
+            let mut operation = json!({
+                "tags": [op.tag],
+                "summary": op.summary,
+                "operationId": op.path.trim_start_matches('/'),
+                "security": security,
+                "parameters": [
+                    {"$ref": "#/components/parameters/protocol"},
+                    {"$ref": "#/components/parameters/traceparent"},
+                ],
+                "responses": {
+                    "200": {
+                        "description": "成功",
+                        "headers": response_headers(),
+                        "content": content(&op.response),
+                    },
+                    "default": {
+                        "description": "失败",
+                        "headers": response_headers(),
+                        "content": content(&error),
+                    },
+                },
+            });
+            if !description.is_empty() {
+                operation["description"] = json!(description);
+            }
+            if let Some(request) = &op.request {
+                operation["requestBody"] = json!({"required": true, "content": content(request)});
+            }
+            paths.insert(op.path.to_owned(), json!({ "post": operation }));
+        }
+
+        json!({
+            "openapi": "3.1.0",
+            "info": {
+                "title": "toys",
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": "请求体和响应体可以是JSON，也可以是speedy二进制，按Content-Type和Accept协商",
+            },
+            "servers": [{"url": "/api"}],
+            "paths": paths,
+            "components": {
+                "schemas": self.generator.take_definitions(true),
+                "parameters": {
+                    "protocol": {
+                        "name": PROTOCOL_HEADER,
+                        "in": "header",
+                        "description": "客户端的协议版本，过旧时返回protocol_mismatch，不带时按当前版本处理",
+                        "schema": {"type": "integer", "example": PROTOCOL_VERSION},
+                    },
+                    "traceparent": {
+                        "name": "traceparent",
+                        "in": "header",
+                        "description": "W3C Trace Context，带上时接入调用方的链路",
+                        "schema": {"type": "string"},
+                    },
+                },
+                "securitySchemes": {
+                    "session": {"type": "apiKey", "in": "cookie", "name": "poem-session"},
+                    "token": {"type": "http", "scheme": "bearer"},
+                },
+            },
+        })
+    }
+}
+
+fn content(schema: &Schema) -> Value {
+    json!({
+        "application/json": {"schema": schema},
+        "application/octet-stream": {
+            "schema": {"type": "string", "format": "binary", "description": "speedy二进制编码"},
+        },
+    })
+}
+
+fn response_headers() -> Value {
+    json!({
+        PROTOCOL_HEADER: {
+            "description": "服务端的协议版本",
+            "schema": {"type": "integer", "example": PROTOCOL_VERSION},
+        },
+        TRACE_ID_HEADER: {
+            "description": "记录了链路时返回trace id",
+            "schema": {"type": "string"},
+        },
+    })
+}
+
+fn scope_name(scope: Scope) -> String {
+    serde_json::to_value(scope)
+        .ok()
+        .and_then(|v| v.as_str().map(str::to_owned))
+        .unwrap_or_default()
+}
+
+fn document() -> Value {
+    Document::new()
+        .post::<SignReq, SignRes>("/sign_up", "登录", "注册", Access::RateLimited)
+        .post::<SignReq, SignRes>("/sign_in", "登录", "登录", Access::RateLimited)
+        .post::<TotpCodeReq, SignRes>(
+            "/sign_in_totp",
+            "登录",
+            "提交两步验证码完成登录",
+            Access::RateLimited,
+        )
+        .post_empty::<SignCheckRes>("/sign_check", "登录", "检查登录状态", Access::Public)
+        .post::<ForgotPasswordReq, Empty>(
+            "/forgot_password",
+            "账户",
+            "申请重置密码",
+            Access::RateLimited,
+        )
+        .post::<ResetPasswordReq, Empty>(
+            "/reset_password",
+            "账户",
+            "使用重置令牌设置新密码",
+            Access::RateLimited,
+        )
+        .post::<SudokuBoard, SudokuBoard>("/sudoku", "工具", "解数独", Access::Token(Scope::Sudoku))
+        .post::<ConvertReq, ConvertRes>(
+            "/convert",
+            "工具",
+            "繁简转换",
+            Access::Token(Scope::Chinese),
+        )
+        .post::<PinyinReq, Vec<Ruby>>("/pinyin", "工具", "拼音标注", Access::Token(Scope::Chinese))
+        .post_empty::<Empty>("/logout", "登录", "登出", Access::Session)
+        .post_empty::<Vec<LoginSession>>("/sessions", "登录", "登录设备列表", Access::Session)
+        .post::<RevokeReq, Empty>("/revoke_session", "登录", "退出登录设备", Access::Session)
+        .post_empty::<Profile>("/profile", "账户", "查询用户资料", Access::Session)
+        .post::<Profile, Empty>("/update_profile", "账户", "修改用户资料", Access::Session)
+        .post::<ChangePasswordReq, Empty>("/change_password", "账户", "修改密码", Access::Session)
+        .post::<DeleteAccountReq, Empty>("/delete_account", "账户", "注销账户", Access::Session)
+        .post_empty::<TotpSetup>("/totp_setup", "账户", "生成两步验证密钥", Access::Session)
+        .post::<TotpCodeReq, RecoveryCodes>(
+            "/totp_enable",
+            "账户",
+            "启用两步验证，返回恢复码",
+            Access::Session,
+        )
+        .post::<TotpCodeReq, Empty>("/totp_disable", "账户", "关闭两步验证", Access::Session)
+        .post_empty::<Vec<ApiToken>>("/api_tokens", "访问令牌", "访问令牌列表", Access::Session)
+        .post::<CreateTokenReq, CreateTokenRes>(
+            "/create_token",
+            "访问令牌",
+            "创建访问令牌，令牌只返回这一次",
+            Access::Session,
+        )
+        .post::<RevokeTokenReq, Empty>("/revoke_token", "访问令牌", "撤销访问令牌", Access::Session)
+        .post_empty::<ReloadRes>(
+            "/reload",
+            "管理",
+            "重新加载配置，返回有变化的配置项",
+            Access::Admin,
+        )
+        .post_empty::<ConfigView>(
+            "/config",
+            "管理",
+            "查询可编辑的配置和历史版本，敏感项不返回原值",
+            Access::Admin,
+        )
+        .post::<SaveConfigReq, ReloadRes>(
+            "/save_config",
+            "管理",
+            "保存修改过的配置项并重新加载，原配置自动备份",
+            Access::Admin,
+        )
+        .post::<RollbackConfigReq, ReloadRes>(
+            "/rollback_config",
+            "管理",
+            "回滚到历史版本并重新加载",
+            Access::Admin,
+        )
+        .build()
+}
+
+/// OpenAPI文档和接口浏览页，挂在配置的路径下
+pub(crate) fn routes(cfg: &OpenApiCfg) -> impl IntoEndpoint {
+    let spec_url = format!("{}/openapi.json", cfg.path.trim_end_matches('/'));
+    let explorer = EXPLORER.replace("{spec_url}", &spec_url);
+    Route::new()
+        .at("/", make_sync(move |_| Html(explorer.clone())))
+        .at(
+            "/openapi.json",
+            make_sync(|_| {
+                let document = DOCUMENT.get_or_init(|| document().to_string());
+                Response::builder()
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(document.as_str())
+            }),
+        )
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::Value;
+
+    use crate::web::api_paths;
+    use crate::web::openapi::document;
+
+    fn refs<'a>(value: &'a Value, found: &mut Vec<&'a str>) {
+        match value {
+            Value::Object(map) => {
+                if let Some(Value::String(r)) = map.get("$ref") {
+                    found.push(r);
+                }
+                map.values().for_each(|v| refs(v, found));
+            }
+            Value::Array(items) => items.iter().for_each(|v| refs(v, found)),
+            _ => {}
+        }
+    }
+
+    #[test]
+    fn refs_resolve() {
+        let doc = document();
+        let mut documented: Vec<_> = doc["paths"]
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(String::as_str)
+            .collect();
+        let mut routed = api_paths();
+        documented.sort();
+        routed.sort();
+        assert_eq!(documented, routed);
+
+        let mut found = Vec::new();
+        refs(&doc, &mut found);
+        assert!(!found.is_empty());
+        for r in found {
+            let pointer = r.strip_prefix('#').unwrap();
+            assert!(doc.pointer(pointer).is_some(), "{r}");
+        }
+    }
+}
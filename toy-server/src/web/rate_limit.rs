@@ -0,0 +1,353 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use poem::{Endpoint, Middleware, Request};
+use log::warn;
+
+use crate::config::RateLimitCfg;
+use crate::error::Error;
+use crate::web::login::client_ip;
+
+static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+
+// 桶或失败记录的数量超过此值时清理已回满的桶、已过期的失败记录
+const PRUNE_THRESHOLD: usize = 10_000;
+// 失败记录最多保留的账户数，防止用随机邮箱撑大
+const MAX_FAILURES: usize = 100_000;
+
+/// 令牌桶：容量为burst，每秒补充rate个令牌
+#[derive(Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, burst: f64, rate: f64, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(burst);
+        self.updated = now;
+    }
+}
+
+/// 一组按键区分的令牌桶
+struct Buckets {
+    burst: f64,
+    rate: f64,
+    buckets: HashMap<String, Bucket>,
+}
+
+impl Buckets {
+    fn new(burst: u32, per_minute: u32) -> Self {
+        Buckets {
+            burst: burst.max(1) as f64,
+            rate: per_minute as f64 / 60.0,
+            buckets: HashMap::new(),
+        }
+    }
+
+    /// 取一个令牌，不够时返回需要等待的秒数
+    fn take(&mut self, key: &str, now: Instant) -> Result<(), u64> {
+        let (burst, rate) = (self.burst, self.rate);
+        if self.buckets.len() > PRUNE_THRESHOLD {
+            self.buckets.retain(|_, b| {
+                b.refill(burst, rate, now);
+                b.tokens < burst
+            });
+        }
+
+        let bucket = self.buckets.entry(key.to_owned()).or_insert(Bucket {
+            tokens: burst,
+            updated: now,
+        });
+        bucket.refill(burst, rate, now);
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Ok(());
+        }
+        if rate <= 0.0 {
+            return Err(u64::MAX);
+        }
+        Err(((1.0 - bucket.tokens) / rate).ceil() as u64)
+    }
+}
+
+/// 连续登录失败的次数和锁定截止时间
+struct Failures {
+    count: u32,
+    locked_until: Option<Instant>,
+    // 最近一次失败的时间，超过锁定时长没有再失败时重新计数
+    updated: Instant,
+}
+
+impl Failures {
+    fn expired(&self, idle: Duration, now: Instant) -> bool {
+        match self.locked_until {
+            Some(until) => until <= now,
+            None => now.saturating_duration_since(self.updated) >= idle,
+        }
+    }
+}
+
+struct State {
+    ip: Buckets,
+    account: Buckets,
+    failures: HashMap<String, Failures>,
+}
+
+impl State {
+    /// 清理已过期的失败记录，仍然过多时只保留锁定中的账户
+    fn prune_failures(&mut self, idle: Duration, now: Instant) {
+        self.failures.retain(|_, f| !f.expired(idle, now));
+        if self.failures.len() >= MAX_FAILURES {
+            warn!("登录失败记录过多，清除未锁定账户的失败次数");
+            self.failures.retain(|_, f| f.locked_until.is_some());
+        }
+    }
+}
+
+/// 认证接口的限流：按IP和按账户的令牌桶，以及连续失败后的临时锁定
+pub(crate) struct RateLimiter {
+    lockout_failures: u32,
+    lockout: Duration,
+    state: Mutex<State>,
+}
+
+/// 初始化限流器，在web::start中调用一次
+pub(crate) fn init(cfg: &RateLimitCfg) {
+    LIMITER.get_or_init(|| RateLimiter::new(cfg));
+}
+
+pub(crate) fn limiter() -> &'static RateLimiter {
+    LIMITER.get().expect("限流器未初始化")
+}
+
+impl RateLimiter {
+    fn new(cfg: &RateLimitCfg) -> Self {
+        RateLimiter {
+            lockout_failures: cfg.lockout_failures,
+            lockout: Duration::from_secs(cfg.lockout_secs),
+            state: Mutex::new(State {
+                ip: Buckets::new(cfg.ip_burst, cfg.ip_per_minute),
+                account: Buckets::new(cfg.account_burst, cfg.account_per_minute),
+                failures: HashMap::new(),
+            }),
+        }
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn check_ip_at(&self, ip: &str, now: Instant) -> Result<(), Error> {
+        self.state().ip.take(ip, now).map_err(|retry_after| {
+            warn!("{ip} 请求过于频繁");
+            Error::TooManyRequests(retry_after)
+        })
+    }
+
+    fn check_account_at(&self, account: &str, now: Instant) -> Result<(), Error> {
+        let account = account.trim().to_lowercase();
+        let mut state = self.state();
+        if let Some(until) = state.failures.get(&account).and_then(|f| f.locked_until) {
+            if until > now {
+                return Err(Error::AccountLocked(
+                    until.duration_since(now).as_secs().max(1),
+                ));
+            }
+            state.failures.remove(&account);
+        }
+        state.account.take(&account, now).map_err(|retry_after| {
+            warn!("账户{account}请求过于频繁");
+            Error::TooManyRequests(retry_after)
+        })
+    }
+
+    // 重置密码与登录分开计数，也不受登录失败锁定的影响：被锁定的用户仍可申请重置，
+    // 别人也不能借申请重置耗尽受害者的登录次数
+    fn check_reset_at(&self, account: &str, now: Instant) -> Result<(), Error> {
+        let account = account.trim().to_lowercase();
+        self.state()
+            .account
+            .take(&format!("reset:{account}"), now)
+            .map_err(|retry_after| {
+                warn!("账户{account}申请重置密码过于频繁");
+                Error::TooManyRequests(retry_after)
+            })
+    }
+
+    fn failed_at(&self, account: &str, now: Instant) {
+        if self.lockout_failures == 0 {
+            return;
+        }
+        let account = account.trim().to_lowercase();
+        let mut state = self.state();
+        if state.failures.len() > PRUNE_THRESHOLD {
+            state.prune_failures(self.lockout, now);
+        }
+        if state.failures.len() >= MAX_FAILURES && !state.failures.contains_key(&account) {
+            warn!("登录失败记录已满，不记录账户{account}");
+            return;
+        }
+        let failures = state.failures.entry(account.clone()).or_insert(Failures {
+            count: 0,
+            locked_until: None,
+            updated: now,
+        });
+        if failures.expired(self.lockout, now) {
+            failures.count = 0;
+            failures.locked_until = None;
+        }
+        failures.count += 1;
+        failures.updated = now;
+        if failures.count >= self.lockout_failures {
+            warn!(
+                "账户{account}连续{}次登录失败，锁定{:?}",
+                failures.count, self.lockout
+            );
+            failures.count = 0;
+            failures.locked_until = Some(now + self.lockout);
+        }
+    }
+
+    /// 按账户限流，账户被锁定时直接拒绝
+    pub(crate) fn check_account(&self, account: &str) -> Result<(), Error> {
+        self.check_account_at(account, Instant::now())
+    }
+
+    /// 按账户限制申请重置密码的频率
+    pub(crate) fn check_reset(&self, account: &str) -> Result<(), Error> {
+        self.check_reset_at(account, Instant::now())
+    }
+
+    /// 记录一次登录失败，连续失败达到阈值后锁定账户
+    pub(crate) fn failed(&self, account: &str) {
+        self.failed_at(account, Instant::now())
+    }
+
+    /// 登录成功后清除失败计数
+    pub(crate) fn succeeded(&self, account: &str) {
+        self.state().failures.remove(&account.trim().to_lowercase());
+    }
+}
+
+/// 按客户端IP限流的中间件
+pub struct RateLimit;
+
+impl<E: Endpoint> Middleware<E> for RateLimit {
+    type Output = RateLimitEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RateLimitEndpoint { ep }
+    }
+}
+
+pub struct RateLimitEndpoint<E> {
+    ep: E,
+}
+
+impl<E: Endpoint> Endpoint for RateLimitEndpoint<E> {
+    type Output = E::Output;
+
+    async fn call(&self, req: Request) -> poem::Result<Self::Output> {
+        limiter().check_ip_at(&client_ip(&req), Instant::now())?;
+        self.ep.call(req).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::config::RateLimitCfg;
+    use crate::error::Error;
+    use crate::web::rate_limit::{RateLimiter, PRUNE_THRESHOLD};
+
+    fn limiter() -> RateLimiter {
+        RateLimiter::new(&RateLimitCfg {
+            ip_burst: 2,
+            ip_per_minute: 60,
+            account_burst: 5,
+            account_per_minute: 5,
+            lockout_failures: 3,
+            lockout_secs: 60,
+        })
+    }
+
+    #[test]
+    fn ip_bucket() {
+        let limiter = limiter();
+        let now = Instant::now();
+        assert!(limiter.check_ip_at("127.0.0.1", now).is_ok());
+        assert!(limiter.check_ip_at("127.0.0.1", now).is_ok());
+        assert!(matches!(
+            limiter.check_ip_at("127.0.0.1", now),
+            Err(Error::TooManyRequests(1))
+        ));
+        assert!(limiter.check_ip_at("::1", now).is_ok());
+        assert!(limiter
+            .check_ip_at("127.0.0.1", now + Duration::from_secs(1))
+            .is_ok());
+    }
+
+    #[test]
+    fn account_lockout() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            assert!(limiter.check_account_at("a@b.c", now).is_ok());
+            limiter.failed_at("A@b.c", now);
+        }
+        assert!(matches!(
+            limiter.check_account_at("a@b.c", now + Duration::from_secs(10)),
+            Err(Error::AccountLocked(50))
+        ));
+        assert!(limiter
+            .check_account_at("a@b.c", now + Duration::from_secs(61))
+            .is_ok());
+    }
+
+    #[test]
+    fn reset_separate() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for _ in 0..3 {
+            limiter.failed_at("a@b.c", now);
+        }
+        // 锁定的账户仍可申请重置，申请重置不占用登录的令牌
+        for _ in 0..5 {
+            assert!(limiter.check_reset_at("a@b.c", now).is_ok());
+        }
+        assert!(matches!(
+            limiter.check_reset_at("A@b.c", now),
+            Err(Error::TooManyRequests(12))
+        ));
+        for _ in 0..5 {
+            assert!(limiter
+                .check_account_at("x@b.c", now)
+                .and(limiter.check_reset_at("x@b.c", now))
+                .is_ok());
+        }
+    }
+
+    #[test]
+    fn failures_pruned() {
+        let limiter = limiter();
+        let now = Instant::now();
+        for i in 0..=PRUNE_THRESHOLD {
+            limiter.failed_at(&format!("{i}@b.c"), now);
+        }
+        assert_eq!(limiter.state().failures.len(), PRUNE_THRESHOLD + 1);
+        // 超过锁定时长没有再失败的记录被清理
+        limiter.failed_at("a@b.c", now + Duration::from_secs(61));
+        assert_eq!(limiter.state().failures.len(), 1);
+        // 间隔超过锁定时长的失败重新计数
+        limiter.failed_at("a@b.c", now + Duration::from_secs(62));
+        limiter.failed_at("a@b.c", now + Duration::from_secs(200));
+        limiter.failed_at("a@b.c", now + Duration::from_secs(201));
+        assert!(limiter
+            .check_account_at("a@b.c", now + Duration::from_secs(202))
+            .is_ok());
+    }
+}
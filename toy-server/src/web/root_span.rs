@@ -0,0 +1,64 @@
+use fastrace::prelude::{FutureExt, LocalSpan, Span, SpanContext};
+use poem::http::HeaderValue;
+use poem::{Endpoint, IntoResponse, Middleware, Request, Response, Result};
+
+use toy_schema::protocol::TRACE_ID_HEADER;
+
+use crate::init;
+
+/// W3C Trace Context的请求头，上游已有链路时沿用
+const TRACEPARENT: &str = "traceparent";
+
+/// 为每个接口请求创建链路的根span。请求带有traceparent时接入上游的链路并沿用其采样决定，
+/// 否则按配置的比例采样；记录链路时在响应头中返回trace id
+pub struct RootSpan;
+
+impl<E: Endpoint> Middleware<E> for RootSpan {
+    type Output = RootSpanEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        RootSpanEndpoint { ep }
+    }
+}
+
+pub struct RootSpanEndpoint<E> {
+    ep: E,
+}
+
+impl<E: Endpoint> Endpoint for RootSpanEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let parent = match upstream(&req) {
+            Some(parent) => parent.sampled(parent.sampled && init::tracing()),
+            None => SpanContext::random().sampled(init::sampled()),
+        };
+        let root = Span::root(format!("{} {}", req.method(), req.uri().path()), parent);
+        let res = async {
+            let res = self.ep.call(req).await.map(IntoResponse::into_response);
+            let status = match &res {
+                Ok(resp) => resp.status(),
+                Err(e) => e.status(),
+            };
+            LocalSpan::add_property(|| ("http.status_code", status.as_u16().to_string()));
+            res
+        }
+        .in_span(root)
+        .await;
+
+        // Negotiate已把错误转为响应，这里只是兜底
+        let mut resp = res.unwrap_or_else(|e| e.into_response());
+        let trace_id = parent
+            .sampled
+            .then(|| HeaderValue::from_str(&format!("{:032x}", parent.trace_id.0)));
+        if let Some(Ok(trace_id)) = trace_id {
+            resp.headers_mut().insert(TRACE_ID_HEADER, trace_id);
+        }
+        Ok(resp)
+    }
+}
+
+fn upstream(req: &Request) -> Option<SpanContext> {
+    let value = req.headers().get(TRACEPARENT)?.to_str().ok()?;
+    SpanContext::decode_w3c_traceparent(value)
+}
@@ -0,0 +1,216 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::io::ErrorKind::NotFound;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use poem::session::SessionStorage;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::fs;
+use log::{debug, error, warn};
+
+use crate::error::Error;
+
+/// session存储，每个session保存为目录下的一个JSON文件，适合单机部署
+pub struct FileStorage {
+    dir: PathBuf,
+}
+
+#[derive(Deserialize, Serialize)]
+struct SessionFile {
+    // 过期时间，UNIX时间戳（秒）
+    expires_at: Option<u64>,
+    session: BTreeMap<String, Value>,
+}
+
+impl SessionFile {
+    fn expired(&self) -> bool {
+        self.expires_at.is_some_and(|t| t <= now())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl FileStorage {
+    pub(crate) async fn new(dir: impl Into<PathBuf>) -> Result<Self, Error> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).await?;
+        Ok(FileStorage { dir })
+    }
+
+    /// session id来自客户端cookie，只接受base64url字符，防止拼出目录外的路径
+    fn path(&self, session_id: &str) -> Option<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        valid.then(|| self.dir.join(format!("{session_id}.json")))
+    }
+
+    /// 目录被删除后读取session也只是找不到，需要单独检查
+    pub(crate) async fn check(&self) -> Result<(), Error> {
+        fs::read_dir(&self.dir).await?;
+        Ok(())
+    }
+
+    pub(crate) async fn delete_expired(&self) -> Result<(), Error> {
+        let mut dir = fs::read_dir(&self.dir).await?;
+        while let Some(entry) = dir.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            // 无法解析的文件也一并清理
+            let expired = fs::read(&path)
+                .await
+                .map(|data| {
+                    serde_json::from_slice::<SessionFile>(&data).is_ok_and(|f| !f.expired())
+                })
+                .is_ok_and(|alive| !alive);
+            if expired {
+                remove(path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &str) -> Result<Option<BTreeMap<String, Value>>, Error> {
+        let Some(path) = self.path(session_id) else {
+            warn!("非法的session id：{session_id}");
+            return Ok(None);
+        };
+        let data = match fs::read(&path).await {
+            Ok(data) => data,
+            Err(e) if e.kind() == NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        match serde_json::from_slice::<SessionFile>(&data) {
+            Ok(file) if file.expired() => Ok(None),
+            Ok(file) => Ok(Some(file.session)),
+            Err(e) => {
+                // 损坏的session直接删除，客户端下次请求会拿到新的session
+                warn!("session {session_id} 数据损坏，已删除：{e}");
+                remove(path).await?;
+                Err(Error::SessionCorrupted)
+            }
+        }
+    }
+
+    async fn save(
+        &self,
+        session_id: &str,
+        entries: &BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> Result<(), Error> {
+        let Some(path) = self.path(session_id) else {
+            return Err(Error::SessionCorrupted);
+        };
+        let file = SessionFile {
+            expires_at: expires.map(|d| now() + d.as_secs()),
+            session: entries.clone(),
+        };
+        let data = serde_json::to_vec(&file).map_err(|_| Error::SessionCorrupted)?;
+        // 先写临时文件再改名，避免并发读到写了一半的文件
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, data).await?;
+        fs::rename(tmp, path).await?;
+        Ok(())
+    }
+}
+
+async fn remove(path: PathBuf) -> Result<(), Error> {
+    match fs::remove_file(path).await {
+        Err(e) if e.kind() != NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+impl SessionStorage for FileStorage {
+    fn load_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = poem::Result<Option<BTreeMap<String, Value>>>> + Send + 'a {
+        debug!("load session {session_id}");
+        async move {
+            self.load(session_id).await.map_err(|e| {
+                error!("读取session失败：{e:?}");
+                e.into()
+            })
+        }
+    }
+
+    fn update_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> impl Future<Output = poem::Result<()>> + Send + 'a {
+        debug!("save session {session_id}");
+        async move {
+            self.save(session_id, entries, expires).await.map_err(|e| {
+                error!("保存session失败：{e:?}");
+                e.into()
+            })
+        }
+    }
+
+    fn remove_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = poem::Result<()>> + Send + 'a {
+        debug!("remove session {session_id}");
+        async move {
+            let Some(path) = self.path(session_id) else {
+                return Ok(());
+            };
+            remove(path).await.map_err(|e| {
+                error!("删除session失败：{e:?}");
+                e.into()
+            })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use poem::session::SessionStorage;
+    use serde_json::json;
+
+    use crate::web::session::file::FileStorage;
+
+    #[tokio::test]
+    async fn file_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("toy-session-{}", std::process::id()));
+        let storage = FileStorage::new(&dir).await.unwrap();
+        let entries = BTreeMap::from([("token".to_owned(), json!("jwt"))]);
+
+        storage
+            .update_session("alive", &entries, Some(Duration::from_secs(60)))
+            .await
+            .unwrap();
+        storage
+            .update_session("expired", &entries, Some(Duration::ZERO))
+            .await
+            .unwrap();
+        std::fs::write(dir.join("broken.json"), "{").unwrap();
+
+        assert_eq!(storage.load_session("alive").await.unwrap(), Some(entries));
+        assert_eq!(storage.load_session("expired").await.unwrap(), None);
+        assert!(storage.load_session("broken").await.is_err());
+        assert_eq!(storage.load_session("../alive").await.unwrap(), None);
+
+        storage.delete_expired().await.unwrap();
+        assert!(!dir.join("expired.json").exists());
+        assert!(dir.join("alive.json").exists());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
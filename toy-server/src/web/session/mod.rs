@@ -0,0 +1,131 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use poem::session::{MemoryStorage, SessionStorage};
+use serde_json::Value;
+use log::{debug, error, info};
+
+use crate::config::{SessionBackend, WebCfg};
+use crate::error::Error;
+use crate::web::{account, database, login};
+use crate::web::session::file::FileStorage;
+use crate::web::session::surreal::SurrealStorage;
+
+mod file;
+mod surreal;
+
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10 * 60);
+// 检查存储时读取的session，不会真的存在
+const PROBE_ID: &str = "readiness-probe";
+
+/// 按配置选择的session存储，克隆后共享同一个后端，供清理任务使用
+#[derive(Clone)]
+pub(crate) struct SessionStore(Arc<Backend>);
+
+enum Backend {
+    Surreal(SurrealStorage),
+    Memory(MemoryStorage),
+    File(FileStorage),
+}
+
+impl SessionStore {
+    pub(crate) async fn new(cfg: &WebCfg) -> Result<Self, Error> {
+        info!("session存储：{:?}", cfg.session_storage);
+        let backend = match cfg.session_storage {
+            SessionBackend::Surreal => Backend::Surreal(SurrealStorage::new().await?),
+            SessionBackend::Memory => Backend::Memory(MemoryStorage::new()),
+            SessionBackend::File => Backend::File(FileStorage::new(&cfg.session_dir).await?),
+        };
+        Ok(SessionStore(Arc::new(backend)))
+    }
+
+    /// 读取一个不存在的session，检查存储是否可用
+    pub(crate) async fn check(&self) -> poem::Result<()> {
+        if let Backend::File(s) = self.0.as_ref() {
+            s.check().await?;
+        }
+        self.load_session(PROBE_ID).await.map(|_| ())
+    }
+
+    async fn delete_expired(&self) -> Result<(), Error> {
+        match self.0.as_ref() {
+            Backend::Surreal(s) => s.delete_expired().await,
+            // 内存存储自己处理过期
+            Backend::Memory(_) => Ok(()),
+            Backend::File(s) => s.delete_expired().await,
+        }
+    }
+}
+
+impl SessionStorage for SessionStore {
+    fn load_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = poem::Result<Option<BTreeMap<String, Value>>>> + Send + 'a {
+        async move {
+            match self.0.as_ref() {
+                Backend::Surreal(s) => s.load_session(session_id).await,
+                Backend::Memory(s) => s.load_session(session_id).await,
+                Backend::File(s) => s.load_session(session_id).await,
+            }
+        }
+    }
+
+    fn update_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> impl Future<Output = poem::Result<()>> + Send + 'a {
+        async move {
+            match self.0.as_ref() {
+                Backend::Surreal(s) => s.update_session(session_id, entries, expires).await,
+                Backend::Memory(s) => s.update_session(session_id, entries, expires).await,
+                Backend::File(s) => s.update_session(session_id, entries, expires).await,
+            }
+        }
+    }
+
+    fn remove_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = poem::Result<()>> + Send + 'a {
+        async move {
+            match self.0.as_ref() {
+                Backend::Surreal(s) => s.remove_session(session_id).await,
+                Backend::Memory(s) => s.remove_session(session_id).await,
+                Backend::File(s) => s.remove_session(session_id).await,
+            }
+        }
+    }
+}
+
+/// 定时清理过期session、不活跃的登录记录和过期的重置令牌，收到停机信号后退出
+pub(crate) async fn sweep(
+    store: SessionStore,
+    max_age: Duration,
+    shutdown: impl Future<Output = ()>,
+) {
+    let mut interval = tokio::time::interval(SWEEP_INTERVAL);
+    tokio::pin!(shutdown);
+    loop {
+        tokio::select! {
+            _ = &mut shutdown => break,
+            _ = interval.tick() => database::exclusive(async {
+                match store.delete_expired().await {
+                    Ok(()) => debug!("过期session清理完成"),
+                    Err(e) => error!("过期session清理失败：{e:?}"),
+                }
+                if let Err(e) = login::delete_stale(max_age).await {
+                    error!("不活跃登录记录清理失败：{e:?}");
+                }
+                if let Err(e) = account::delete_expired_resets().await {
+                    error!("过期重置令牌清理失败：{e:?}");
+                }
+            }).await,
+        }
+    }
+    info!("session清理任务已退出");
+}
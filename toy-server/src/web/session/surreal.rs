@@ -0,0 +1,251 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::time::Duration;
+
+use poem::session::SessionStorage;
+use serde_json::Value;
+use log::{debug, error, info, warn};
+
+use crate::error::Error;
+use crate::web::database::{self, Traced};
+
+/// session存储，使用连接池中的root连接，session内容以对象形式保存
+pub struct SurrealStorage;
+
+// 过期的session视为不存在，等待清理任务删除
+const LOAD_SESSION: &str = "SELECT VALUE session FROM ONLY type::thing('session', $id)
+    WHERE expires_at = NONE OR expires_at > time::now()";
+const UPSERT_SESSION: &str = "UPSERT type::thing('session', $id) CONTENT {
+    session: $session,
+    expires_at: IF $ttl THEN time::now() + duration::from::secs($ttl) END
+}";
+const DELETE_EXPIRED: &str = "DELETE session WHERE expires_at != NONE AND expires_at < time::now()";
+
+impl SurrealStorage {
+    pub(crate) async fn new() -> Result<Self, Error> {
+        // 启动时先取一次连接，数据库不可用时尽早失败
+        database::root().await?;
+        Ok(SurrealStorage)
+    }
+
+    pub(crate) async fn delete_expired(&self) -> Result<(), Error> {
+        let db = database::root().await?;
+        db.query(DELETE_EXPIRED)
+            .traced("db.session.delete_expired")
+            .await?
+            .check()?;
+        Ok(())
+    }
+
+    async fn load(&self, session_id: &str) -> Result<Option<BTreeMap<String, Value>>, Error> {
+        let db = database::root().await?;
+        // 直接反序列化成serde_json::Value会报错：
+        // Serialization error: invalid type: enum, expected any valid JSON value
+        // 所以先取SurrealDB的Value，再转换成JSON
+        let session: surrealdb::Value = db
+            .query(LOAD_SESSION)
+            .bind(("id", session_id.to_owned()))
+            .traced("db.session.load")
+            .await?
+            .take(0)?;
+        match session.into_inner().into_json() {
+            Value::Null => Ok(None),
+            Value::Object(entries) => Ok(Some(entries.into_iter().collect())),
+            other => {
+                // 损坏的session直接删除，客户端下次请求会拿到新的session
+                warn!("session {session_id} 数据损坏，已删除：{other}");
+                db.delete::<Option<surrealdb::Value>>(("session", session_id))
+                    .traced("db.session.delete")
+                    .await?;
+                Err(Error::SessionCorrupted)
+            }
+        }
+    }
+}
+
+impl SessionStorage for SurrealStorage {
+    fn load_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = poem::Result<Option<BTreeMap<String, Value>>>> + Send + 'a {
+        info!("load session {session_id}");
+        async move {
+            self.load(session_id).await.map_err(|e| {
+                error!("select session 失败：{e:?}");
+                e.into()
+            })
+        }
+    }
+
+    fn update_session<'a>(
+        &'a self,
+        session_id: &'a str,
+        entries: &'a BTreeMap<String, Value>,
+        expires: Option<Duration>,
+    ) -> impl Future<Output = poem::Result<()>> + Send + 'a {
+        info!("upsert session {session_id}: {entries:?}");
+        async move {
+            let db = database::root().await?;
+            db.query(UPSERT_SESSION)
+                .bind(("id", session_id.to_owned()))
+                .bind(("session", entries.clone()))
+                .bind(("ttl", expires.map(|d| d.as_secs())))
+                .traced("db.session.upsert")
+                .await
+                .and_then(|res| res.check())
+                .map(|_| ())
+                .map_err(|e| {
+                    error!("upsert session 失败：{e}");
+                    Error::DbException(e).into()
+                })
+        }
+    }
+
+    fn remove_session<'a>(
+        &'a self,
+        session_id: &'a str,
+    ) -> impl Future<Output = poem::Result<()>> + Send + 'a {
+        debug!("remove session {session_id}");
+        async move {
+            let db = database::root().await?;
+            db.delete::<Option<surrealdb::Value>>(("session", session_id))
+                .traced("db.session.delete")
+                .await
+                .map(|_| ())
+                .map_err(|e| {
+                    error!("delete session 失败：{e}");
+                    Error::DbException(e).into()
+                })
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::Duration;
+
+    use poem::session::SessionStorage;
+    use serde_json::json;
+    use surrealdb::RecordId;
+
+    use crate::config::WebCfg;
+    use crate::error::Error;
+    use crate::web::database::{self, testing};
+    use crate::web::session::surreal::SurrealStorage;
+    use crate::web::session::{sweep, SessionStore};
+
+    /// 写入一个已过期的session
+    async fn expired(id: &str) {
+        let db = database::root().await.unwrap();
+        db.query("UPSERT type::thing('session', $id) CONTENT { session: {}, expires_at: time::now() - 1m }")
+            .bind(("id", id.to_owned()))
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+    }
+
+    /// 不论是否过期，session记录是否还在
+    async fn exists(id: &str) -> bool {
+        let db = database::root().await.unwrap();
+        let ids: Vec<RecordId> = db
+            .query("SELECT VALUE id FROM type::thing('session', $id)")
+            .bind(("id", id.to_owned()))
+            .await
+            .unwrap()
+            .take(0)
+            .unwrap();
+        !ids.is_empty()
+    }
+
+    /// 内嵌数据库的连接共享会话：请求中用户登录后，写session不能以该用户的身份执行
+    #[test]
+    fn update_after_sign_in() {
+        testing::run(async {
+            let user = database::user().await.unwrap();
+            user.signup(database::user_credentials(json!({
+                "name": "测试",
+                "email": "shared@example.com",
+                "password": "secret",
+            })))
+            .await
+            .unwrap();
+            let email: Option<String> = user
+                .query("RETURN $auth.email")
+                .await
+                .unwrap()
+                .take(0)
+                .unwrap();
+            assert_eq!(email.as_deref(), Some("shared@example.com"));
+
+            // 用户连接还在请求中持有时写session
+            let store = SurrealStorage;
+            let entries = BTreeMap::from([("login".to_owned(), json!("shared"))]);
+            store
+                .update_session("shared", &entries, None)
+                .await
+                .unwrap();
+            assert_eq!(store.load_session("shared").await.unwrap(), Some(entries));
+            drop(user);
+        });
+    }
+
+    #[test]
+    fn expired_session() {
+        testing::run(async {
+            let store = SurrealStorage;
+            let entries = BTreeMap::from([("login".to_owned(), json!("live"))]);
+            store
+                .update_session("live", &entries, Some(Duration::from_secs(600)))
+                .await
+                .unwrap();
+            expired("expired").await;
+
+            // 过期的session视为不存在，清理时删除
+            assert_eq!(store.load_session("expired").await.unwrap(), None);
+            assert!(exists("expired").await);
+            store.delete_expired().await.unwrap();
+            assert!(!exists("expired").await);
+            assert_eq!(store.load_session("live").await.unwrap(), Some(entries));
+        });
+    }
+
+    #[test]
+    fn sweeper() {
+        testing::run(async {
+            expired("swept").await;
+            let store = SessionStore::new(&WebCfg::default()).await.unwrap();
+            // 启动后立即清理一次，收到停机信号后退出
+            let shutdown = tokio::time::sleep(Duration::from_millis(200));
+            sweep(store, Duration::from_secs(600), shutdown).await;
+            assert!(!exists("swept").await);
+        });
+    }
+
+    #[test]
+    fn corrupted_session() {
+        testing::run(async {
+            // 迁移0004之前的JSON字符串，以及其他不是对象的内容，需临时放开字段类型才能写入
+            let db = database::root().await.unwrap();
+            db.query(
+                "DEFINE FIELD OVERWRITE session ON session TYPE any;
+                UPSERT session:legacy SET session = '{\"login\":\"legacy\"}';
+                UPSERT session:corrupted SET session = 42;
+                DEFINE FIELD OVERWRITE session ON session FLEXIBLE TYPE object;",
+            )
+            .await
+            .unwrap()
+            .check()
+            .unwrap();
+
+            let store = SurrealStorage;
+            for id in ["legacy", "corrupted"] {
+                assert!(matches!(store.load(id).await, Err(Error::SessionCorrupted)));
+                // 损坏的session已删除，再次读取时不存在
+                assert!(!exists(id).await);
+                assert_eq!(store.load(id).await.unwrap(), None);
+            }
+        });
+    }
+}
@@ -0,0 +1,376 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::ErrorKind::NotFound;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{env, fs};
+
+use poem::handler;
+use toml::{Table, Value};
+use toml_edit::{DocumentMut, Item};
+use log::{error, info};
+
+use toy_schema::config::{
+    ConfigField, ConfigKind, ConfigVersion, ConfigView, ReloadRes, RollbackConfigReq,
+    SaveConfigReq,
+};
+use toy_schema::error::FieldError;
+
+use crate::config::{self, GLOBAL_CONFIG};
+use crate::datetime::{readable, timestamp};
+use crate::error::Error;
+use crate::web::reconfigure;
+use crate::web::speedy_data::Speedy;
+
+/// 设置页可以编辑的配置
+const SECTIONS: [&str; 3] = ["log", "web", "trace"];
+/// 保存配置前把原文件备份到配置文件旁的这个目录
+const HISTORY_DIR: &str = "config-history";
+/// 最多保留的历史版本数
+const HISTORY_LIMIT: usize = 20;
+
+// 同一时间只有一次保存或回滚，从读取配置文件到重新加载都持有
+static WRITING: Mutex<()> = Mutex::new(());
+
+/// 当前生效的配置和历史版本，敏感项不返回原值
+#[handler]
+pub async fn config_view() -> Speedy<ConfigView> {
+    Speedy(ConfigView {
+        fields: fields(),
+        versions: versions(&config::file()),
+    })
+}
+
+/// 把修改过的配置项写入配置文件并重新加载
+#[handler]
+pub async fn save_config(req: Speedy<SaveConfigReq>) -> poem::Result<Speedy<ReloadRes>> {
+    // 类型和是否敏感以服务端的配置为准
+    let current: BTreeMap<String, ConfigField> =
+        fields().into_iter().map(|f| (f.key.clone(), f)).collect();
+    let file = config::file();
+    let _writing = writing();
+    let mut doc = read_document(&file).map_err(Error::Validation)?;
+    let mut details = Vec::new();
+    for field in req.0.fields {
+        let Some(known) = current.get(&field.key) else {
+            details.push(FieldError::new(&field.key, "不能在设置页修改"));
+            continue;
+        };
+        // 敏感项不返回原值，为空表示不修改
+        if known.secret && field.value.is_empty() {
+            continue;
+        }
+        if let Err(message) =
+            parse(known.kind, &field.value).and_then(|v| set(&mut doc, &field.key, v))
+        {
+            details.push(FieldError::new(&field.key, message));
+        }
+    }
+    if !details.is_empty() {
+        return Err(Error::Validation(details).into());
+    }
+    write(&file, doc.to_string())
+}
+
+/// 回滚到某个历史版本，当前的配置同样会先备份
+#[handler]
+pub async fn rollback_config(req: Speedy<RollbackConfigReq>) -> poem::Result<Speedy<ReloadRes>> {
+    let file = config::file();
+    let RollbackConfigReq { id } = req.0;
+    let _writing = writing();
+    if !versions(&file).iter().any(|v| v.id == id) {
+        return Err(Error::Validation(vec![FieldError::new("id", "历史版本不存在")]).into());
+    }
+    let version = version_path(&file, &id);
+    let content = fs::read_to_string(&version).map_err(Error::ConfigIo)?;
+    info!("配置回滚到{}", version.display());
+    write(&file, content)
+}
+
+fn writing() -> MutexGuard<'static, ()> {
+    WRITING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+/// 校验通过后备份原文件、写入新配置并重新加载，调用前须持有WRITING
+fn write(file: &str, content: String) -> poem::Result<Speedy<ReloadRes>> {
+    let table = content.parse::<Table>().map_err(|e| {
+        Error::Validation(vec![FieldError::new(file, format!("配置格式有误: {e}"))])
+    })?;
+    config::build(file, table).map_err(Error::Validation)?;
+
+    backup(file).map_err(Error::ConfigIo)?;
+    replace(file, &content).map_err(|e| {
+        error!("配置写入失败: {e}");
+        Error::ConfigIo(e)
+    })?;
+    info!("配置已保存到{file}");
+
+    let mut changes = config::reload().map_err(Error::Validation)?;
+    reconfigure(&mut changes);
+    Ok(Speedy(ReloadRes { changes }))
+}
+
+/// 读取配置文件用于原地修改，保留注释和格式，文件不存在时为空
+fn read_document(file: &str) -> Result<DocumentMut, Vec<FieldError>> {
+    match fs::read_to_string(file) {
+        Ok(s) => s
+            .parse()
+            .map_err(|e| vec![FieldError::new(file, format!("配置格式有误: {e}"))]),
+        Err(e) if e.kind() == NotFound => Ok(DocumentMut::new()),
+        Err(e) => Err(vec![FieldError::new(file, format!("配置读取失败: {e}"))]),
+    }
+}
+
+/// 修改一个配置项，如web.rate_limit.ip_burst，缺少的表自动添加；原有的值保留其后的注释
+fn set(doc: &mut DocumentMut, key: &str, value: Value) -> Result<(), &'static str> {
+    let mut value: toml_edit::Value = value.to_string().parse().map_err(|_| "值的格式有误")?;
+    let names: Vec<&str> = key.split('.').collect();
+    let mut item = doc.as_item_mut();
+    for (i, name) in names.iter().enumerate() {
+        if !item.is_table_like() {
+            return Err("配置文件中该项的上级不是表");
+        }
+        // 在普通的表下添加[web.rate_limit]这样的表，内联的表下仍是内联的
+        let inline = !item.is_table();
+        item = &mut item[name];
+        if item.is_none() && i + 1 < names.len() {
+            *item = if inline {
+                Item::Value(toml_edit::Value::InlineTable(Default::default()))
+            } else {
+                toml_edit::table()
+            };
+        }
+    }
+    if let Some(old) = item.as_value() {
+        *value.decor_mut() = old.decor().clone();
+    }
+    *item = Item::Value(value);
+    Ok(())
+}
+
+/// 先写入同目录下的临时文件再改名替换，写到一半出错时原文件不受影响
+fn replace(file: &str, content: &str) -> std::io::Result<()> {
+    let tmp = format!("{file}.tmp");
+    let mut out = File::create(&tmp)?;
+    out.write_all(content.as_bytes())?;
+    out.sync_all()?;
+    fs::rename(&tmp, file)
+}
+
+/// 可编辑的配置项，按路径排序
+fn fields() -> Vec<ConfigField> {
+    let config = GLOBAL_CONFIG.get().unwrap().load();
+    let mut overridden = BTreeSet::new();
+    leaf_keys("", &config::env_overrides(env::vars()), &mut overridden);
+
+    let mut fields = Vec::new();
+    if let Ok(Value::Table(table)) = Value::try_from(&**config) {
+        for section in SECTIONS {
+            if let Some(value) = table.get(section) {
+                flatten(section, value, &overridden, &mut fields);
+            }
+        }
+    }
+    fields
+}
+
+fn flatten(key: &str, value: &Value, overridden: &BTreeSet<String>, fields: &mut Vec<ConfigField>) {
+    let kind = match value {
+        Value::Table(table) => {
+            for (name, value) in table {
+                flatten(&format!("{key}.{name}"), value, overridden, fields);
+            }
+            return;
+        }
+        Value::Boolean(_) => ConfigKind::Bool,
+        Value::Integer(_) => ConfigKind::Integer,
+        Value::Float(_) => ConfigKind::Float,
+        Value::String(_) => ConfigKind::Text,
+        // 数组和时间不在设置页编辑
+        _ => return,
+    };
+    let secret = config::is_secret(key);
+    fields.push(ConfigField {
+        key: key.to_owned(),
+        kind,
+        value: match value {
+            _ if secret => String::new(),
+            Value::String(s) => s.clone(),
+            value => value.to_string(),
+        },
+        secret,
+        overridden: overridden.contains(key),
+    });
+}
+
+fn leaf_keys(key: &str, value: &Value, keys: &mut BTreeSet<String>) {
+    match value {
+        Value::Table(table) => {
+            for (name, value) in table {
+                let key = if key.is_empty() {
+                    name.clone()
+                } else {
+                    format!("{key}.{name}")
+                };
+                leaf_keys(&key, value, keys);
+            }
+        }
+        _ => {
+            keys.insert(key.to_owned());
+        }
+    }
+}
+
+fn parse(kind: ConfigKind, raw: &str) -> Result<Value, &'static str> {
+    let raw = raw.trim();
+    match kind {
+        ConfigKind::Bool => raw
+            .parse()
+            .map(Value::Boolean)
+            .map_err(|_| "应为true或false"),
+        ConfigKind::Integer => raw.parse().map(Value::Integer).map_err(|_| "应为整数"),
+        ConfigKind::Float => raw.parse().map(Value::Float).map_err(|_| "应为数字"),
+        ConfigKind::Text => Ok(Value::String(raw.to_owned())),
+    }
+}
+
+fn history_dir(file: &str) -> PathBuf {
+    Path::new(file)
+        .parent()
+        .unwrap_or(Path::new(""))
+        .join(HISTORY_DIR)
+}
+
+/// 配置文件名去掉.toml，如config.prd
+fn stem(file: &str) -> String {
+    Path::new(file)
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn version_path(file: &str, id: &str) -> PathBuf {
+    history_dir(file).join(format!("{}.{id}.toml", stem(file)))
+}
+
+/// 历史版本，id为备份时间，新的在前
+fn versions(file: &str) -> Vec<ConfigVersion> {
+    let prefix = format!("{}.", stem(file));
+    let Ok(entries) = fs::read_dir(history_dir(file)) else {
+        return Vec::new();
+    };
+    let mut ids: Vec<String> = entries
+        .filter_map(|e| e.ok())
+        .filter_map(|e| {
+            let name = e.file_name().to_string_lossy().into_owned();
+            let id = name.strip_prefix(&prefix)?.strip_suffix(".toml")?;
+            valid_id(id).then(|| id.to_owned())
+        })
+        .collect();
+    ids.sort_unstable_by(|a, b| b.cmp(a));
+    ids.into_iter()
+        .map(|id| ConfigVersion {
+            saved_at: readable(&id),
+            id,
+        })
+        .collect()
+}
+
+/// 形如20261019-120000
+fn valid_id(id: &str) -> bool {
+    id.len() == 15
+        && id
+            .char_indices()
+            .all(|(i, c)| if i == 8 { c == '-' } else { c.is_ascii_digit() })
+}
+
+/// 备份当前的配置文件，超出数量的旧版本删除
+fn backup(file: &str) -> std::io::Result<()> {
+    if !Path::new(file).exists() {
+        return Ok(());
+    }
+    fs::create_dir_all(history_dir(file))?;
+    let mut secs = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default();
+    // 同一秒内多次保存时顺延，不覆盖已有的版本
+    while version_path(file, &timestamp(secs)).exists() {
+        secs += 1;
+    }
+    fs::copy(file, version_path(file, &timestamp(secs)))?;
+    for old in versions(file).iter().skip(HISTORY_LIMIT) {
+        fs::remove_file(version_path(file, &old.id))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use toml::Value;
+
+    use toy_schema::config::ConfigKind;
+
+    use crate::datetime::timestamp;
+    use crate::web::settings::{parse, replace, set, valid_id};
+
+    #[test]
+    fn history_id() {
+        assert!(valid_id(&timestamp(1792411200)));
+        assert!(!valid_id("../../etc/passwd"));
+        assert!(!valid_id("20261019x120000"));
+    }
+
+    #[test]
+    fn parse_field() {
+        assert_eq!(parse(ConfigKind::Bool, "true"), Ok(Value::Boolean(true)));
+        assert_eq!(parse(ConfigKind::Integer, " 20 "), Ok(Value::Integer(20)));
+        assert!(parse(ConfigKind::Integer, "twenty").is_err());
+        assert_eq!(
+            parse(ConfigKind::Text, "INFO"),
+            Ok(Value::String("INFO".to_owned()))
+        );
+    }
+
+    #[test]
+    fn edit_in_place() {
+        let mut doc = "# 日志
+[log]
+level = \"INFO\" # 上线后改为WARN
+
+[web]
+address = \"0.0.0.0:8080\"
+"
+        .parse()
+        .unwrap();
+        set(&mut doc, "log.level", Value::String("DEBUG".to_owned())).unwrap();
+        set(&mut doc, "web.rate_limit.ip_burst", Value::Integer(3)).unwrap();
+        set(&mut doc, "trace.enabled", Value::Boolean(false)).unwrap();
+        assert!(set(&mut doc, "web.address.port", Value::Integer(1)).is_err());
+        assert_eq!(
+            doc.to_string(),
+            "# 日志
+[log]
+level = \"DEBUG\" # 上线后改为WARN
+
+[web]
+address = \"0.0.0.0:8080\"
+
+[web.rate_limit]
+ip_burst = 3
+
+[trace]
+enabled = false
+"
+        );
+
+        let file = std::env::temp_dir().join(format!("settings-{}.toml", std::process::id()));
+        let file = file.to_string_lossy();
+        replace(&file, &doc.to_string()).unwrap();
+        replace(&file, "[log]\n").unwrap();
+        assert_eq!(std::fs::read_to_string(&*file).unwrap(), "[log]\n");
+        std::fs::remove_file(&*file).unwrap();
+    }
+}
@@ -2,12 +2,74 @@ use std::fmt::Debug;
 use std::ops::{Deref, DerefMut};
 
 use poem::http::{header, StatusCode};
-use poem::{FromRequest, IntoResponse, Request, RequestBody, Response, Result};
+use poem::{
+    Endpoint, FromRequest, IntoResponse, Middleware, Request, RequestBody, Response, Result,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
 use speedy::{Endianness, Readable, Writable};
-use log::info;
+use log::debug;
+
+use toy_schema::error::ApiError;
+use toy_schema::protocol::{self, PROTOCOL_HEADER, PROTOCOL_VERSION};
 
 use crate::error::Error;
 
+tokio::task_local! {
+    /// 当前请求协商出的响应格式，由Negotiate中间件设置
+    static FORMAT: Format;
+}
+
+/// 请求体和响应体的格式：前端使用speedy二进制，其他客户端使用JSON
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Format {
+    Speedy,
+    Json,
+}
+
+impl Format {
+    fn from_mime(value: &str) -> Option<Format> {
+        let mime = value.trim().parse::<mime::Mime>().ok()?;
+        if mime.type_() != "application" {
+            return None;
+        }
+        let is = |name: &str| mime.subtype() == name || mime.suffix().is_some_and(|v| v == name);
+        if is("octet-stream") {
+            Some(Format::Speedy)
+        } else if is("json") {
+            Some(Format::Json)
+        } else {
+            None
+        }
+    }
+
+    /// 按Accept中第一个能提供的格式协商，未指定时与请求体的格式相同，都没有时使用speedy
+    fn negotiate(req: &Request) -> Format {
+        let header = |name| req.headers().get(name).and_then(|v| v.to_str().ok());
+        header(header::ACCEPT)
+            .and_then(|accept| {
+                accept
+                    .split(',')
+                    .find_map(|v| Format::from_mime(v.split(';').next()?))
+            })
+            .or_else(|| header(header::CONTENT_TYPE).and_then(Format::from_mime))
+            .unwrap_or(Format::Speedy)
+    }
+
+    /// 当前请求协商出的响应格式，不在Negotiate中时为speedy
+    pub(crate) fn current() -> Format {
+        FORMAT.try_with(|f| *f).unwrap_or(Format::Speedy)
+    }
+
+    fn content_type(self) -> &'static str {
+        match self {
+            Format::Speedy => "application/octet-stream",
+            Format::Json => "application/json",
+        }
+    }
+}
+
+/// speedy二进制或JSON格式的请求体和响应体，按Content-Type和Accept协商
 #[derive(Debug, Clone, Eq, PartialEq, Default)]
 pub struct Speedy<T>(pub T);
 
@@ -25,47 +87,166 @@ impl<T> DerefMut for Speedy<T> {
     }
 }
 
-impl<'a, T: Debug + Readable<'a, Endianness>> FromRequest<'a> for Speedy<T> {
+impl<'a, T: Debug + Readable<'a, Endianness> + DeserializeOwned> FromRequest<'a> for Speedy<T> {
     async fn from_request(req: &'a Request, body: &mut RequestBody) -> Result<Self> {
         let content_type = req
             .headers()
             .get(header::CONTENT_TYPE)
             .and_then(|content_type| content_type.to_str().ok())
             .ok_or(Error::ContentTypeRequired)?;
-        if !is_octet_stream(content_type) {
-            return Err(Error::InvalidContentType(content_type.into()).into());
-        }
+        let format = Format::from_mime(content_type)
+            .ok_or_else(|| Error::InvalidContentType(content_type.into()))?;
         let body_data = body.take()?.into_bytes().await?;
-        let s = T::read_from_buffer_copying_data_with_ctx(Endianness::LittleEndian, &body_data)
-            .map_err(Error::Parse)?;
-        info!("Body解析成功：{s:?}");
+        let s = match format {
+            Format::Speedy => {
+                T::read_from_buffer_copying_data_with_ctx(Endianness::LittleEndian, &body_data)
+                    .map_err(Error::Parse)?
+            }
+            Format::Json => serde_json::from_slice(&body_data).map_err(Error::ParseJson)?,
+        };
+        debug!("Body解析成功：{s:?}");
 
         Ok(Self(s))
     }
 }
 
-fn is_octet_stream(content_type: &str) -> bool {
-    matches!(content_type.parse::<mime::Mime>(), 
-        Ok(content_type) if content_type.type_() == "application" 
-        && (content_type.subtype() == "octet-stream"
-        || content_type
-            .suffix()
-            .map_or(false, |v| v == "octet-stream")))
+impl<T: Debug + Writable<Endianness> + Serialize + Send> IntoResponse for Speedy<T> {
+    fn into_response(self) -> Response {
+        debug!("Body序列化开始：{:?}", self.0);
+        let format = Format::current();
+        let data = match format {
+            Format::Speedy => self
+                .0
+                .write_to_vec_with_ctx(Endianness::LittleEndian)
+                .map_err(|e| e.to_string()),
+            Format::Json => serde_json::to_vec(&self.0).map_err(|e| e.to_string()),
+        };
+        match data {
+            Ok(data) => Response::builder()
+                .header(header::CONTENT_TYPE, format.content_type())
+                .body(data),
+            Err(err) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(err),
+        }
+    }
 }
 
-impl<T: Debug + Writable<Endianness> + Send> IntoResponse for Speedy<T> {
-    fn into_response(self) -> Response {
-        info!("Body序列化开始：{:?}", self.0);
-        let data = match self.0.write_to_vec_with_ctx(Endianness::LittleEndian) {
-            Ok(data) => data,
-            Err(err) => {
-                return Response::builder()
-                    .status(StatusCode::INTERNAL_SERVER_ERROR)
-                    .body(err.to_string())
+/// 检查请求的协议版本，未带版本头的请求（如curl）按当前版本处理
+fn check_protocol(req: &Request) -> Result<(), Error> {
+    let Some(value) = req.headers().get(PROTOCOL_HEADER) else {
+        return Ok(());
+    };
+    let value = value.to_str().unwrap_or_default();
+    match value.trim().parse::<u16>() {
+        Ok(version) if protocol::compatible(version) => Ok(()),
+        _ => Err(Error::ProtocolMismatch(value.to_owned())),
+    }
+}
+
+/// 协商响应格式，接口的错误也以ApiError按协商出的格式返回；同时检查协议版本，
+/// 并在所有响应上带上服务端的协议版本
+pub struct Negotiate;
+
+impl<E: Endpoint> Middleware<E> for Negotiate {
+    type Output = NegotiateEndpoint<E>;
+
+    fn transform(&self, ep: E) -> Self::Output {
+        NegotiateEndpoint { ep }
+    }
+}
+
+pub struct NegotiateEndpoint<E> {
+    ep: E,
+}
+
+impl<E: Endpoint> Endpoint for NegotiateEndpoint<E> {
+    type Output = Response;
+
+    async fn call(&self, req: Request) -> Result<Self::Output> {
+        let format = Format::negotiate(&req);
+        let res = match check_protocol(&req) {
+            Ok(()) => FORMAT.scope(format, self.ep.call(req)).await,
+            Err(err) => Err(err.into()),
+        };
+        let mut res = match res {
+            Ok(res) => res.into_response(),
+            Err(err) => FORMAT.sync_scope(format, || error_response(err)),
+        };
+        res.headers_mut()
+            .insert(PROTOCOL_HEADER, PROTOCOL_VERSION.into());
+        Ok(res)
+    }
+}
+
+/// 错误响应统一为ApiError，poem自身的错误（如404）也一样
+fn error_response(err: poem::Error) -> Response {
+    if err.is::<Error>() {
+        return err.into_response();
+    }
+    let status = err.status();
+    let mut res = Speedy(ApiError {
+        code: format!("http_{}", status.as_u16()),
+        message: err.to_string(),
+        details: Vec::new(),
+    })
+    .into_response();
+    res.set_status(status);
+    res
+}
+
+#[cfg(test)]
+mod test {
+    use poem::http::header;
+    use poem::Request;
+
+    use toy_schema::protocol::PROTOCOL_HEADER;
+
+    use crate::web::speedy_data::{check_protocol, Format};
+
+    #[test]
+    fn negotiate() {
+        let req = |accept: Option<&str>, content_type: Option<&str>| {
+            let mut builder = Request::builder();
+            if let Some(v) = accept {
+                builder = builder.header(header::ACCEPT, v);
+            }
+            if let Some(v) = content_type {
+                builder = builder.header(header::CONTENT_TYPE, v);
+            }
+            Format::negotiate(&builder.finish())
+        };
+        assert_eq!(req(None, None), Format::Speedy);
+        assert_eq!(req(None, Some("application/json")), Format::Json);
+        assert_eq!(
+            req(Some("text/html, application/json;q=0.9"), None),
+            Format::Json
+        );
+        assert_eq!(
+            req(Some("application/octet-stream"), Some("application/json")),
+            Format::Speedy
+        );
+        assert_eq!(
+            req(Some("*/*"), Some("application/octet-stream")),
+            Format::Speedy
+        );
+        assert_eq!(req(Some("application/problem+json"), None), Format::Json);
+    }
+
+    #[test]
+    fn protocol_version() {
+        let req = |version: Option<&str>| {
+            let mut builder = Request::builder();
+            if let Some(v) = version {
+                builder = builder.header(PROTOCOL_HEADER, v);
             }
+            check_protocol(&builder.finish())
         };
-        Response::builder()
-            .header(header::CONTENT_TYPE, "application/octet-stream")
-            .body(data)
+        assert!(req(None).is_ok());
+        assert!(req(Some("1")).is_ok());
+        // 比服务端新的客户端由客户端自己判断能否解码
+        assert!(req(Some("999")).is_ok());
+        assert!(req(Some("0")).is_err());
+        assert!(req(Some("v1")).is_err());
     }
 }
@@ -1,12 +1,18 @@
 use std::fmt::{Display, Formatter};
 use std::ops::{Deref, DerefMut};
+use std::time::Instant;
 
+use fastrace::prelude::LocalSpan;
 use poem::{handler, Result};
 use speedy::{Readable, Writable};
 use log::info;
 
+use toy_schema::sudoku::SudokuBoard;
+
 use crate::error::Error;
 use crate::error::Error::{SudokuNumErr, SudokuUnsolvable};
+#[cfg(feature = "metrics")]
+use crate::metrics;
 use crate::web::speedy_data::Speedy;
 
 mod traversal;
@@ -178,10 +184,42 @@ impl Sudoku {
 }
 
 #[handler]
-pub async fn resolve(req: Speedy<[u16; 81]>) -> Result<Speedy<[u16; 81]>> {
+pub async fn resolve(req: Speedy<SudokuBoard>) -> Result<Speedy<SudokuBoard>> {
+    let start = Instant::now();
+    let (sudoku, depth) = solve(req.0)?;
+    let elapsed = start.elapsed();
+    info!("数独求解完成，用时{elapsed:?}，回溯深度{depth}");
+    #[cfg(feature = "metrics")]
+    metrics::sudoku_solved(elapsed, depth);
+    Ok(Speedy(SudokuBoard(sudoku.0.map(|a| a >> 9))))
+}
+
+/// 先按唯一可能性填充，填不下去时再枚举，返回解和枚举的回溯深度
+fn solve(board: SudokuBoard) -> Result<(Sudoku, usize)> {
+    let sudoku = propagate(board)?;
+    if sudoku.finished() {
+        return Ok((sudoku, 0));
+    }
+
+    info!("找不到唯一可能性的格子了，接下来开始枚举");
+    let _span = LocalSpan::enter_with_local_parent("sudoku.traversal");
+    match traversal::resolve(sudoku) {
+        None => Err(SudokuUnsolvable.into()),
+        Some((sudoku, depth)) => {
+            LocalSpan::add_property(|| ("sudoku.backtrack_depth", depth.to_string()));
+            info!("{sudoku}");
+            Ok((sudoku, depth))
+        }
+    }
+}
+
+/// 按入参初始化，并反复按唯一可能性填充，直到填不下去
+fn propagate(board: SudokuBoard) -> Result<Sudoku> {
+    let _span = LocalSpan::enter_with_local_parent("sudoku.propagation");
+    let SudokuBoard(cells) = board;
     let mut sudoku = Sudoku::default();
     // 按照入参初始化数独，不过初始化的过程中发现唯一可能的时候，也会直接填充
-    for (i, &n) in req.iter().enumerate() {
+    for (i, &n) in cells.iter().enumerate() {
         // 没有值
         if n == 0 {
             continue;
@@ -206,19 +244,7 @@ pub async fn resolve(req: Speedy<[u16; 81]>) -> Result<Speedy<[u16; 81]>> {
         }
     }
 
-    if sudoku.finished() {
-        return Ok(Speedy(sudoku.0.map(|a| a >> 9)));
-    }
-
-    info!("找不到唯一可能性的格子了，接下来开始枚举");
-    match traversal::resolve(sudoku) {
-        None => Err(SudokuUnsolvable.into()),
-        Some(sudoku) => {
-            info!("{sudoku}");
-            Ok(Speedy(sudoku.0.map(|a| a >> 9)))
-            // Ok(Speedy(sudoku.0))
-        }
-    }
+    Ok(sudoku)
 }
 
 const fn rows() -> [[usize; 9]; 9] {
@@ -3,7 +3,8 @@ use log::info;
 
 use crate::web::sudoku::{Sudoku, ALL_CONDITION};
 
-pub fn resolve(sudoku: Sudoku) -> Option<Sudoku> {
+/// 枚举求解，返回解和枚举过程中达到的最大回溯深度（连续猜测的层数）
+pub fn resolve(sudoku: Sudoku) -> Option<(Sudoku, usize)> {
     let mut arena = Arena::new();
     let root = arena.new_node(sudoku);
 
@@ -11,20 +12,25 @@ pub fn resolve(sudoku: Sudoku) -> Option<Sudoku> {
         arena,
         root,
         resolved: None,
+        depth: 0,
     };
-    tree.traversal()
+    tree.traversal().map(|sudoku| (sudoku, tree.depth))
 }
 
 struct SudokuTree {
     arena: Arena<Sudoku>,
     root: NodeId,
     resolved: Option<Sudoku>,
+    depth: usize,
 }
 
 impl SudokuTree {
     fn traversal(&mut self) -> Option<Sudoku> {
         let mut next_node_id = Some(self.root);
         while let Some(node_id) = next_node_id {
+            // 根节点自身计为0层
+            let depth = node_id.ancestors(&self.arena).count() - 1;
+            self.depth = self.depth.max(depth);
             next_node_id = self.expand(node_id);
         }
 
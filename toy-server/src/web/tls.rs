@@ -0,0 +1,121 @@
+use std::fs;
+use std::io;
+use std::time::{Duration, SystemTime};
+
+use futures::stream::{self, Stream, StreamExt};
+use poem::endpoint::make_sync;
+use poem::http::uri::Authority;
+use poem::http::{header, StatusCode};
+use poem::listener::{IntoTlsConfigStream, RustlsCertificate, RustlsConfig};
+use poem::{Endpoint, Request, Response};
+use log::{error, info};
+
+use crate::config::TlsCfg;
+
+fn read(cfg: &TlsCfg) -> io::Result<RustlsConfig> {
+    Ok(RustlsConfig::new().fallback(
+        RustlsCertificate::new()
+            .key(fs::read(&cfg.key)?)
+            .cert(fs::read(&cfg.cert)?),
+    ))
+}
+
+/// 读取并校验证书，RustlsConfig不能克隆，校验通过后重新读取一次
+fn load(cfg: &TlsCfg) -> io::Result<RustlsConfig> {
+    let _ = read(cfg)?.into_stream()?;
+    read(cfg)
+}
+
+/// 证书和私钥文件的修改时间
+fn modified(cfg: &TlsCfg) -> Option<(SystemTime, SystemTime)> {
+    let mtime = |path: &str| fs::metadata(path).and_then(|m| m.modified()).ok();
+    Some((mtime(&cfg.cert)?, mtime(&cfg.key)?))
+}
+
+/// 当前的证书，以及之后文件更新时重新加载的证书；更新后的证书有误时继续使用原证书
+pub(crate) fn certificates(
+    cfg: TlsCfg,
+) -> io::Result<impl Stream<Item = RustlsConfig> + Send + 'static> {
+    let current = load(&cfg)?;
+    let interval = Duration::from_secs(cfg.reload_interval);
+    let updates = (cfg.reload_interval > 0).then(|| {
+        let last = modified(&cfg);
+        stream::unfold((cfg, last), move |(cfg, mut last)| async move {
+            loop {
+                tokio::time::sleep(interval).await;
+                let now = modified(&cfg);
+                if now.is_none() || now == last {
+                    continue;
+                }
+                // 证书和私钥可能不是同时写入的，下次文件变化时再试
+                last = now;
+                match load(&cfg) {
+                    Ok(config) => {
+                        info!("证书已更新：{}", cfg.cert);
+                        return Some((config, (cfg, last)));
+                    }
+                    Err(e) => error!("证书加载失败，继续使用原证书：{e}"),
+                }
+            }
+        })
+    });
+    Ok(stream::once(async { current }).chain(stream::iter(updates).flatten()))
+}
+
+/// 监听地址中的端口
+pub(crate) fn port(address: &str) -> Option<u16> {
+    address.rsplit_once(':')?.1.parse().ok()
+}
+
+/// 跳转到HTTPS的地址，host中的端口换成HTTPS的端口
+fn location(host: &str, https_port: Option<u16>, path: &str) -> Option<String> {
+    let host = host.parse::<Authority>().ok()?;
+    let host = host.host();
+    Some(match https_port {
+        Some(443) | None => format!("https://{host}{path}"),
+        Some(port) => format!("https://{host}:{port}{path}"),
+    })
+}
+
+/// HTTP跳转HTTPS，由单独的监听地址提供
+pub(crate) fn redirect(https_port: Option<u16>) -> impl Endpoint {
+    make_sync(move |req: Request| {
+        let path = req.uri().path_and_query().map_or("/", |p| p.as_str());
+        let location = req
+            .header(header::HOST)
+            .and_then(|host| location(host, https_port, path));
+        match location {
+            Some(location) => Response::builder()
+                .status(StatusCode::PERMANENT_REDIRECT)
+                .header(header::LOCATION, location)
+                .finish(),
+            None => StatusCode::BAD_REQUEST.into(),
+        }
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::web::tls::{location, port};
+
+    #[test]
+    fn redirect_location() {
+        assert_eq!(port("0.0.0.0:8443"), Some(8443));
+        assert_eq!(port("[::]:443"), Some(443));
+        assert_eq!(port("localhost"), None);
+
+        assert_eq!(
+            location("example.com", Some(443), "/a?b=1").as_deref(),
+            Some("https://example.com/a?b=1")
+        );
+        assert_eq!(
+            location("example.com:8080", Some(8443), "/").as_deref(),
+            Some("https://example.com:8443/")
+        );
+        assert_eq!(
+            location("[::1]:80", None, "/").as_deref(),
+            Some("https://[::1]/")
+        );
+        assert_eq!(location("bad host", Some(443), "/"), None);
+    }
+}
@@ -0,0 +1,351 @@
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fastrace::trace;
+use poem::session::Session;
+use poem::web::Data;
+use poem::{handler, Request};
+use rand::distr::{Alphanumeric, SampleString};
+use serde::{Deserialize, Serialize};
+use surrealdb::opt::auth::Jwt;
+use surrealdb::RecordId;
+use totp_rs::{Algorithm, Secret, TOTP};
+use log::{error, info, warn};
+
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{RecoveryCodes, SignRes, TotpCodeReq, TotpSetup};
+
+use crate::error::Error;
+use crate::web::auth::complete_sign_in;
+use crate::web::database::{self, DbConn, Traced};
+use crate::web::rate_limit;
+use crate::web::speedy_data::Speedy;
+
+/// session中保存“等待两步验证”状态的键
+pub(crate) const TOTP_PENDING_KEY: &str = "totp_pending";
+
+const ISSUER: &str = "toys";
+const STEP: u64 = 30;
+const RECOVERY_CODES: usize = 10;
+// 输入密码后必须在此时间内完成两步验证
+const PENDING_TTL: u64 = 5 * 60;
+
+const CONSUME_RECOVERY_CODE: &str = "UPDATE $auth SET recovery_codes -= crypto::sha256($code)
+    WHERE recovery_codes CONTAINS crypto::sha256($code) RETURN VALUE id";
+const ENABLE_TOTP: &str = "UPDATE $auth SET totp_secret = totp_pending, totp_pending = NONE,
+    totp_last_step = $step, recovery_codes = array::map($codes, |$c| crypto::sha256($c))
+    WHERE totp_secret = NONE RETURN NONE";
+const DISABLE_TOTP: &str =
+    "UPDATE $auth SET totp_secret = NONE, totp_last_step = NONE, recovery_codes = [] RETURN NONE";
+
+/// 密码验证通过、等待两步验证时保存在session中的状态
+#[derive(Serialize, Deserialize)]
+pub(crate) struct TotpPending {
+    pub(crate) token: Jwt,
+    pub(crate) email: String,
+    pub(crate) expires_at: u64,
+}
+
+impl TotpPending {
+    pub(crate) fn new(token: Jwt, email: String) -> Self {
+        TotpPending {
+            token,
+            email,
+            expires_at: now() + PENDING_TTL,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct TotpUser {
+    email: String,
+    totp_secret: Option<String>,
+    totp_pending: Option<String>,
+    totp_last_step: Option<u64>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+fn totp(secret: &str, account: String) -> Result<TOTP, Error> {
+    let secret = Secret::Encoded(secret.to_owned()).to_bytes().map_err(|e| {
+        error!("两步验证密钥有误：{e:?}");
+        Error::InternalServerErr
+    })?;
+    TOTP::new(
+        Algorithm::SHA1,
+        6,
+        1,
+        STEP,
+        secret,
+        Some(ISSUER.to_owned()),
+        account,
+    )
+    .map_err(|e| {
+        error!("两步验证初始化失败：{e:?}");
+        Error::InternalServerErr
+    })
+}
+
+/// 校验验证码，允许前后各一个时间步的误差；已用过的时间步不再接受，返回匹配的时间步
+fn verify(totp: &TOTP, code: &str, last_step: Option<u64>, now: u64) -> Option<u64> {
+    let step = now / STEP;
+    (step.saturating_sub(1)..=step + 1)
+        .filter(|s| last_step.is_none_or(|last| *s > last))
+        .find(|s| totp.generate(s * STEP) == code)
+}
+
+fn recovery_codes() -> Vec<String> {
+    let mut rng = rand::rng();
+    (0..RECOVERY_CODES)
+        .map(|_| {
+            let code = Alphanumeric.sample_string(&mut rng, 10).to_lowercase();
+            format!("{}-{}", &code[..5], &code[5..])
+        })
+        .collect()
+}
+
+async fn totp_user(db: &DbConn) -> Result<TotpUser, Error> {
+    let user: Option<TotpUser> = db
+        .query("SELECT email, totp_secret, totp_pending, totp_last_step FROM ONLY $auth")
+        .traced("db.totp.user")
+        .await?
+        .take(0)?;
+    user.ok_or(Error::UnAuthenticated)
+}
+
+/// 用验证码或恢复码完成验证，db须是已认证的用户连接；未启用两步验证时直接通过
+#[trace(name = "auth.totp")]
+async fn check_code(db: &DbConn, code: &str) -> Result<bool, Error> {
+    let user = totp_user(db).await?;
+    let Some(secret) = user.totp_secret else {
+        return Ok(true);
+    };
+
+    let code = code.trim().to_lowercase();
+    if let Some(step) = verify(
+        &totp(&secret, user.email)?,
+        &code,
+        user.totp_last_step,
+        now(),
+    ) {
+        db.query("UPDATE $auth SET totp_last_step = $step RETURN NONE")
+            .bind(("step", step))
+            .traced("db.totp.use_code")
+            .await?
+            .check()?;
+        return Ok(true);
+    }
+
+    // 恢复码只能使用一次
+    let used: Vec<RecordId> = db
+        .query(CONSUME_RECOVERY_CODE)
+        .bind(("code", code))
+        .traced("db.totp.use_recovery_code")
+        .await?
+        .take(0)?;
+    if !used.is_empty() {
+        warn!("使用恢复码完成两步验证");
+    }
+    Ok(!used.is_empty())
+}
+
+/// 账户是否启用了两步验证，db须是已认证的用户连接
+pub(crate) async fn enabled(db: &DbConn) -> Result<bool, Error> {
+    Ok(totp_user(db).await?.totp_secret.is_some())
+}
+
+/// 生成新的密钥，确认验证码后才启用
+#[handler]
+pub async fn totp_setup(db: Data<&Arc<DbConn>>) -> poem::Result<Speedy<TotpSetup>> {
+    Ok(Speedy(setup(&db).await?))
+}
+
+/// 确认验证码并启用两步验证，返回只展示这一次的恢复码
+#[handler]
+pub async fn totp_enable(
+    req: Speedy<TotpCodeReq>,
+    db: Data<&Arc<DbConn>>,
+) -> poem::Result<Speedy<RecoveryCodes>> {
+    Ok(Speedy(enable(&db, &req.code).await?))
+}
+
+// 已启用时不能直接换绑，否则拿到登录态的人不需要验证码就能绑定自己的设备，须先用验证码关闭
+async fn setup(db: &DbConn) -> Result<TotpSetup, Error> {
+    let user = totp_user(db).await?;
+    if user.totp_secret.is_some() {
+        return Err(Error::TotpEnabled);
+    }
+    let secret = Secret::generate_secret().to_encoded().to_string();
+    let totp = totp(&secret, user.email)?;
+    let qr_png = totp.get_qr_base64().map_err(|e| {
+        error!("生成二维码失败：{e}");
+        Error::InternalServerErr
+    })?;
+
+    db.query("UPDATE $auth SET totp_pending = $secret RETURN NONE")
+        .bind(("secret", secret.clone()))
+        .traced("db.totp.setup")
+        .await?
+        .check()?;
+
+    Ok(TotpSetup {
+        otpauth_uri: totp.get_url(),
+        qr_png,
+        secret,
+    })
+}
+
+async fn enable(db: &DbConn, code: &str) -> Result<RecoveryCodes, Error> {
+    let user = totp_user(db).await?;
+    if user.totp_secret.is_some() {
+        return Err(Error::TotpEnabled);
+    }
+    let Some(pending) = user.totp_pending else {
+        return Err(Error::TotpInvalid);
+    };
+    let Some(step) = verify(&totp(&pending, user.email)?, code.trim(), None, now()) else {
+        return Err(Error::TotpInvalid);
+    };
+
+    let codes = recovery_codes();
+    db.query(ENABLE_TOTP)
+        .bind(("step", step))
+        .bind(("codes", codes.clone()))
+        .traced("db.totp.enable")
+        .await?
+        .check()?;
+    info!("两步验证已启用");
+    Ok(RecoveryCodes { codes })
+}
+
+#[handler]
+pub async fn totp_disable(
+    req: Speedy<TotpCodeReq>,
+    db: Data<&Arc<DbConn>>,
+) -> poem::Result<Speedy<Empty>> {
+    if !check_code(&db, &req.code).await? {
+        return Err(Error::TotpInvalid.into());
+    }
+    db.query(DISABLE_TOTP)
+        .traced("db.totp.disable")
+        .await
+        .and_then(|res| res.check())
+        .map_err(Error::DbException)?;
+    warn!("两步验证已关闭");
+    Ok(Speedy(Empty {}))
+}
+
+/// 登录第二步：校验验证码后才把token放入session
+#[handler]
+pub async fn sign_in_totp(
+    code_req: Speedy<TotpCodeReq>,
+    session: &Session,
+    req: &Request,
+) -> poem::Result<Speedy<SignRes>> {
+    let Some(pending) = session.get::<TotpPending>(TOTP_PENDING_KEY) else {
+        warn!("未找到待验证的登录");
+        return Err(Error::SignInFail.into());
+    };
+    if pending.expires_at <= now() {
+        warn!("两步验证已超时");
+        session.remove(TOTP_PENDING_KEY);
+        return Err(Error::SignInFail.into());
+    }
+
+    let limiter = rate_limit::limiter();
+    limiter.check_account(&pending.email)?;
+
+    let db = database::user().await?;
+    db.authenticate(pending.token.clone())
+        .traced("db.authenticate")
+        .await
+        .map_err(Error::DbException)?;
+    if !check_code(&db, &code_req.code).await? {
+        limiter.failed(&pending.email);
+        return Err(Error::TotpInvalid.into());
+    }
+    limiter.succeeded(&pending.email);
+
+    session.remove(TOTP_PENDING_KEY);
+    let role = complete_sign_in(&db, session, req, pending.token).await?;
+    Ok(Speedy(SignRes::SignedIn(role)))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use totp_rs::Secret;
+
+    use crate::error::Error;
+    use crate::web::database::{self, testing};
+    use crate::web::totp::{enable, now, recovery_codes, setup, totp, verify, STEP};
+
+    #[test]
+    fn verify_steps() {
+        let secret = Secret::generate_secret().to_encoded().to_string();
+        let totp = totp(&secret, "a@b.c".to_owned()).unwrap();
+        let now = 1_700_000_000;
+        let step = now / STEP;
+
+        let code = totp.generate(now);
+        assert_eq!(verify(&totp, &code, None, now), Some(step));
+        // 用过的验证码不能再用
+        assert_eq!(verify(&totp, &code, Some(step), now), None);
+        // 允许相邻时间步的误差
+        assert_eq!(verify(&totp, &code, None, now + STEP), Some(step));
+        assert_eq!(verify(&totp, &code, None, now + 3 * STEP), None);
+    }
+
+    #[test]
+    fn recovery_code_format() {
+        let codes = recovery_codes();
+        assert_eq!(codes.len(), 10);
+        assert!(codes
+            .iter()
+            .all(|c| c.len() == 11 && c.as_bytes()[5] == b'-'));
+    }
+
+    /// 已启用两步验证时不能重新生成密钥，也不能覆盖已有的密钥
+    #[test]
+    fn enrol_once() {
+        testing::run(async {
+            let db = database::user().await.unwrap();
+            db.signup(database::user_credentials(json!({
+                "name": "测试",
+                "email": "totp@example.com",
+                "password": "secret",
+            })))
+            .await
+            .unwrap();
+
+            let first = setup(&db).await.unwrap();
+            let code = |secret: &str| {
+                totp(secret, "totp@example.com".to_owned())
+                    .unwrap()
+                    .generate(now())
+            };
+            assert!(matches!(
+                enable(&db, "abcdef").await,
+                Err(Error::TotpInvalid)
+            ));
+            enable(&db, &code(&first.secret)).await.unwrap();
+
+            assert!(matches!(setup(&db).await, Err(Error::TotpEnabled)));
+            assert!(matches!(
+                enable(&db, &code(&first.secret)).await,
+                Err(Error::TotpEnabled)
+            ));
+            let secret: Option<String> = db
+                .query("SELECT VALUE totp_secret FROM ONLY $auth")
+                .await
+                .unwrap()
+                .take(0)
+                .unwrap();
+            assert_eq!(secret, Some(first.secret));
+        });
+    }
+}
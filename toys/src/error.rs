@@ -1,5 +1,7 @@
 use reqwest::StatusCode;
 
+use toy_schema::error::{ApiError, FieldError};
+
 pub type Result<T> = std::result::Result<T, Error>;
 
 #[derive(thiserror::Error, Debug)]
@@ -10,6 +12,40 @@ pub enum Error {
     Parse(#[from] speedy::Error),
     #[error("响应失败: {status}")]
     Response { status: StatusCode },
+    #[error("{}", .error.message)]
+    Api { status: StatusCode, error: ApiError },
+    #[error("请求过于频繁，请{retry_after}秒后再试")]
+    TooManyRequests { retry_after: u64 },
+    #[error("服务端已更新（协议版本{server}），请刷新页面")]
+    Protocol { server: u16 },
     // #[error("系统异常")]
     // ServerException,
 }
+
+impl Error {
+    /// 展示给用户的提示：优先使用服务端返回的提示，没有时使用调用方给出的提示
+    pub fn alert(&self, fallback: &str) -> String {
+        match self {
+            Error::TooManyRequests { .. } | Error::Api { .. } | Error::Protocol { .. } => {
+                self.to_string()
+            }
+            _ => fallback.to_owned(),
+        }
+    }
+
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Error::Response { status } | Error::Api { status, .. } => Some(*status),
+            Error::TooManyRequests { .. } => Some(StatusCode::TOO_MANY_REQUESTS),
+            _ => None,
+        }
+    }
+
+    /// 服务端返回的字段级错误
+    pub fn details(&self) -> Vec<FieldError> {
+        match self {
+            Error::Api { error, .. } => error.details.clone(),
+            _ => Vec::new(),
+        }
+    }
+}
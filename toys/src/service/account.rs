@@ -0,0 +1,117 @@
+use reqwest::Method;
+use tracing::{error, info};
+
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{
+    ChangePasswordReq, DeleteAccountReq, ForgotPasswordReq, Profile, RecoveryCodes,
+    ResetPasswordReq, TotpCodeReq, TotpSetup,
+};
+
+use crate::service::http;
+use crate::ui::{
+    AlertMsg, AlertType, ALERT_MSG, AUTHENTICATED, PROFILE, RECOVERY_CODES, TOTP_SETUP,
+};
+
+pub async fn profile() {
+    match http::<(), Profile>(Method::POST, "/api/profile", None).await {
+        Ok(res) => *PROFILE.write() = res,
+        Err(e) => error!("查询用户资料失败： {e}"),
+    }
+}
+
+pub async fn update_profile(req: Profile) {
+    if let Err(e) = http::<Profile, Empty>(Method::POST, "/api/update_profile", Some(&req)).await {
+        error!("修改用户资料失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "邮箱格式有误或已被使用");
+    } else {
+        *PROFILE.write() = req;
+        *ALERT_MSG.write() = AlertMsg::new(Some(AlertType::Info), "用户资料已保存");
+    }
+}
+
+pub async fn totp_setup() {
+    match http::<(), TotpSetup>(Method::POST, "/api/totp_setup", None).await {
+        Ok(res) => *TOTP_SETUP.write() = Some(res),
+        Err(e) => {
+            error!("生成两步验证密钥失败： {e}");
+            *ALERT_MSG.write() = AlertMsg::error(&e, "生成密钥失败");
+        }
+    }
+}
+
+pub async fn totp_enable(req: TotpCodeReq) {
+    match http::<TotpCodeReq, RecoveryCodes>(Method::POST, "/api/totp_enable", Some(&req)).await {
+        Ok(RecoveryCodes { codes }) => {
+            *TOTP_SETUP.write() = None;
+            *RECOVERY_CODES.write() = codes;
+            PROFILE.write().totp_enabled = true;
+            *ALERT_MSG.write() =
+                AlertMsg::new(Some(AlertType::Info), "两步验证已启用，请妥善保存恢复码");
+        }
+        Err(e) => {
+            error!("启用两步验证失败： {e}");
+            *ALERT_MSG.write() = AlertMsg::error(&e, "验证码错误");
+        }
+    }
+}
+
+pub async fn totp_disable(req: TotpCodeReq) {
+    if let Err(e) = http::<TotpCodeReq, Empty>(Method::POST, "/api/totp_disable", Some(&req)).await
+    {
+        error!("关闭两步验证失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "验证码错误");
+    } else {
+        RECOVERY_CODES.write().clear();
+        PROFILE.write().totp_enabled = false;
+        *ALERT_MSG.write() = AlertMsg::new(Some(AlertType::Info), "两步验证已关闭");
+    }
+}
+
+pub async fn change_password(req: ChangePasswordReq) {
+    if let Err(e) =
+        http::<ChangePasswordReq, Empty>(Method::POST, "/api/change_password", Some(&req)).await
+    {
+        error!("修改密码失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "原密码错误");
+    } else {
+        *ALERT_MSG.write() =
+            AlertMsg::new(Some(AlertType::Info), "密码已修改，其他设备需要重新登录");
+    }
+}
+
+pub async fn delete_account(req: DeleteAccountReq) {
+    if let Err(e) =
+        http::<DeleteAccountReq, Empty>(Method::POST, "/api/delete_account", Some(&req)).await
+    {
+        error!("注销账户失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "密码错误，注销失败");
+    } else {
+        info!("账户已注销");
+        *AUTHENTICATED.write() = false;
+    }
+}
+
+pub async fn forgot_password(req: ForgotPasswordReq) {
+    if let Err(e) =
+        http::<ForgotPasswordReq, Empty>(Method::POST, "/api/forgot_password", Some(&req)).await
+    {
+        error!("申请重置密码失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "重置邮件发送失败");
+    } else {
+        *ALERT_MSG.write() = AlertMsg::new(
+            Some(AlertType::Info),
+            "如果邮箱已注册，重置令牌已发送到该邮箱",
+        );
+    }
+}
+
+pub async fn reset_password(req: ResetPasswordReq) {
+    if let Err(e) =
+        http::<ResetPasswordReq, Empty>(Method::POST, "/api/reset_password", Some(&req)).await
+    {
+        error!("重置密码失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "重置令牌无效或已过期");
+    } else {
+        *ALERT_MSG.write() = AlertMsg::new(Some(AlertType::Info), "密码已重置，请使用新密码登录");
+    }
+}
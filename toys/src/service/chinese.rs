@@ -0,0 +1,21 @@
+use reqwest::Method;
+use tracing::error;
+
+use toy_schema::chinese::{ConvertReq, ConvertRes, PinyinReq, Ruby};
+
+use crate::service::http;
+use crate::ui::{CONVERTED, PINYIN};
+
+pub async fn convert(req: ConvertReq) {
+    match http::<ConvertReq, ConvertRes>(Method::POST, "/api/convert", Some(&req)).await {
+        Ok(res) => *CONVERTED.write() = res.text,
+        Err(e) => error!("繁简转换失败： {e}"),
+    }
+}
+
+pub async fn pinyin(req: PinyinReq) {
+    match http::<PinyinReq, Vec<Ruby>>(Method::POST, "/api/pinyin", Some(&req)).await {
+        Ok(res) => *PINYIN.write() = res,
+        Err(e) => error!("拼音标注失败： {e}"),
+    }
+}
@@ -1,14 +1,51 @@
 use reqwest::Method;
 use tracing::{error, info};
 
+use toy_schema::config::{ConfigView, ReloadRes, RollbackConfigReq, SaveConfigReq};
+
+use crate::error::Result;
 use crate::service::http;
-use crate::ui::SETTINGS_BTN_DISABLE;
+use crate::ui::{CONFIG_VIEW, RELOAD_CHANGES, RELOAD_ERRORS, SETTINGS_BTN_DISABLE};
 
 pub async fn reload() {
-    if let Err(e) = http::<(), ()>(Method::POST, "/api/reload", None).await {
-        error!("更新配置失败：{e}");
-    } else {
-        info!("配置加载成功");
+    let res = http::<(), ReloadRes>(Method::POST, "/api/reload", None).await;
+    reloaded(res, "更新配置失败").await;
+}
+
+pub async fn config() {
+    match http::<(), ConfigView>(Method::POST, "/api/config", None).await {
+        Ok(res) => *CONFIG_VIEW.write() = Some(res),
+        Err(e) => error!("查询配置失败：{e}"),
+    }
+}
+
+pub async fn save_config(req: SaveConfigReq) {
+    let res = http::<SaveConfigReq, ReloadRes>(Method::POST, "/api/save_config", Some(&req)).await;
+    reloaded(res, "保存配置失败").await;
+}
+
+pub async fn rollback_config(req: RollbackConfigReq) {
+    let res =
+        http::<RollbackConfigReq, ReloadRes>(Method::POST, "/api/rollback_config", Some(&req))
+            .await;
+    reloaded(res, "回滚配置失败").await;
+}
+
+/// 重新加载、保存和回滚都返回有变化的配置项，成功后刷新表单
+async fn reloaded(res: Result<ReloadRes>, fail: &str) {
+    match res {
+        Ok(res) => {
+            info!("配置加载成功，{}项有变化", res.changes.len());
+            *RELOAD_CHANGES.write() = Some(res.changes);
+            RELOAD_ERRORS.write().clear();
+            config().await;
+        }
+        Err(e) => {
+            error!("{fail}：{e}");
+            *RELOAD_CHANGES.write() = None;
+            // 配置有误时服务端保留原配置，列出所有错误
+            *RELOAD_ERRORS.write() = e.details();
+        }
     }
     *SETTINGS_BTN_DISABLE.write() = false;
 }
@@ -2,29 +2,61 @@ use std::sync::OnceLock;
 
 use dioxus::prelude::*;
 use futures_util::stream::StreamExt;
+use reqwest::header::{ACCEPT, RETRY_AFTER};
 use reqwest::{Client, Method, StatusCode, Url};
 use speedy::{LittleEndian, Readable, Writable};
+use tracing::{debug, warn};
 
-use toy_schema::sign::SignReq;
+use toy_schema::chinese::{ConvertReq, PinyinReq};
+use toy_schema::config::{RollbackConfigReq, SaveConfigReq};
+use toy_schema::error::ApiError;
+use toy_schema::protocol::{self, PROTOCOL_HEADER, PROTOCOL_VERSION, TRACE_ID_HEADER};
+use toy_schema::sign::{
+    ChangePasswordReq, DeleteAccountReq, ForgotPasswordReq, Profile, ResetPasswordReq, RevokeReq,
+    CreateTokenReq, RevokeTokenReq, SignReq, TotpCodeReq,
+};
 
-use crate::error::Error::Response;
+use crate::error::Error::{Api, Protocol, Response, TooManyRequests};
 use crate::error::{Error, Result};
 use crate::ui::AUTHENTICATED;
 
 pub static HTTP_CLIENT: OnceLock<Client> = OnceLock::new();
 pub static HTTP_URL: OnceLock<Url> = OnceLock::new();
 
+mod account;
+mod chinese;
 mod config;
 mod sign;
 mod sudoku;
+mod token;
 
 pub enum Api {
     SignUp(SignReq),
     SignIn(SignReq),
+    SignInTotp(String),
     SignCheck,
     Logout,
+    Sessions,
+    RevokeSession(Option<String>),
+    Profile,
+    UpdateProfile(Profile),
+    ChangePassword(ChangePasswordReq),
+    DeleteAccount(DeleteAccountReq),
+    ForgotPassword(ForgotPasswordReq),
+    ResetPassword(ResetPasswordReq),
+    TotpSetup,
+    TotpEnable(String),
+    TotpDisable(String),
+    ApiTokens,
+    CreateToken(CreateTokenReq),
+    RevokeToken(String),
     ConfigReload,
+    Config,
+    SaveConfig(SaveConfigReq),
+    RollbackConfig(String),
     Sudoku([u16; 81]),
+    Convert(ConvertReq),
+    Pinyin(PinyinReq),
 }
 
 pub async fn api_service(mut rx: UnboundedReceiver<Api>) {
@@ -32,10 +64,30 @@ pub async fn api_service(mut rx: UnboundedReceiver<Api>) {
         match msg {
             Api::SignUp(req) => sign::sign_up(req).await,
             Api::SignIn(req) => sign::sign_in(req).await,
+            Api::SignInTotp(code) => sign::sign_in_totp(TotpCodeReq { code }).await,
             Api::SignCheck => sign::sign_check().await,
             Api::Logout => sign::logout().await,
+            Api::Sessions => sign::sessions().await,
+            Api::RevokeSession(id) => sign::revoke_session(RevokeReq { id }).await,
+            Api::Profile => account::profile().await,
+            Api::UpdateProfile(req) => account::update_profile(req).await,
+            Api::ChangePassword(req) => account::change_password(req).await,
+            Api::DeleteAccount(req) => account::delete_account(req).await,
+            Api::ForgotPassword(req) => account::forgot_password(req).await,
+            Api::ResetPassword(req) => account::reset_password(req).await,
+            Api::TotpSetup => account::totp_setup().await,
+            Api::TotpEnable(code) => account::totp_enable(TotpCodeReq { code }).await,
+            Api::TotpDisable(code) => account::totp_disable(TotpCodeReq { code }).await,
+            Api::ApiTokens => token::api_tokens().await,
+            Api::CreateToken(req) => token::create_token(req).await,
+            Api::RevokeToken(id) => token::revoke_token(RevokeTokenReq { id }).await,
             Api::ConfigReload => config::reload().await,
+            Api::Config => config::config().await,
+            Api::SaveConfig(req) => config::save_config(req).await,
+            Api::RollbackConfig(id) => config::rollback_config(RollbackConfigReq { id }).await,
             Api::Sudoku(req) => sudoku::sudoku(req).await,
+            Api::Convert(req) => chinese::convert(req).await,
+            Api::Pinyin(req) => chinese::pinyin(req).await,
         }
     }
 }
@@ -48,30 +100,69 @@ pub async fn http<'a, Req: Writable<LittleEndian>, Res: Readable<'a, LittleEndia
     let client = HTTP_CLIENT.get().unwrap();
     let url = HTTP_URL.get().and_then(|u| u.join(path).ok()).unwrap();
 
-    // 发送请求
+    // 发送请求，服务端按Accept返回speedy二进制
+    let builder = client
+        .request(method, url)
+        .header(ACCEPT, "application/octet-stream")
+        .header(PROTOCOL_HEADER, PROTOCOL_VERSION);
     let res = match request {
         Some(req) => {
             let body = req.write_to_vec().map_err(Error::Parse)?;
-            client
-                .request(method, url)
+            builder
                 .header("content-type", "application/octet-stream")
                 .body(body)
                 .send()
                 .await?
         }
-        None => client.request(method, url).send().await?,
+        None => builder.send().await?,
     };
 
-    let status = res.status();
-    let msg = Res::read_from_buffer_copying_data(&res.bytes().await?)?;
+    // 服务端升级后不再兼容当前页面时，响应体可能已无法解码
+    let server = res
+        .headers()
+        .get(PROTOCOL_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok());
+    if let Some(server) = server.filter(|v| !protocol::compatible(*v)) {
+        return Err(Protocol { server });
+    }
 
+    // 服务端记录了链路时带回trace id，出错时据此查找服务端的链路和日志
+    let trace_id = res
+        .headers()
+        .get(TRACE_ID_HEADER)
+        .and_then(|v| v.to_str().ok())
+        .map(str::to_owned);
+    let status = res.status();
+    if let Some(trace_id) = &trace_id {
+        debug!("{path} {status} trace_id: {trace_id}");
+    }
     if !status.is_success() {
+        warn!(
+            "{path} 请求失败：{status}，trace_id: {}",
+            trace_id.as_deref().unwrap_or("无")
+        );
         if StatusCode::UNAUTHORIZED.eq(&status) {
             *AUTHENTICATED.write() = false;
         }
+        if StatusCode::TOO_MANY_REQUESTS.eq(&status) {
+            let retry_after = res
+                .headers()
+                .get(RETRY_AFTER)
+                .and_then(|v| v.to_str().ok())
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(60);
+            return Err(TooManyRequests { retry_after });
+        }
 
-        return Err(Response { status });
+        // 错误响应体是ApiError，解析不了时只返回状态码
+        let body = res.bytes().await?;
+        return Err(match ApiError::read_from_buffer_copying_data(&body) {
+            Ok(error) => Api { status, error },
+            Err(_) => Response { status },
+        });
     }
 
+    let msg = Res::read_from_buffer_copying_data(&res.bytes().await?)?;
     Ok(msg)
 }
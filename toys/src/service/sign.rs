@@ -1,42 +1,103 @@
-use reqwest::Method;
+use reqwest::{Method, StatusCode};
 use tracing::{error, info};
 
-use toy_schema::sign::SignReq;
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{LoginSession, RevokeReq, Role, SignCheckRes, SignReq, SignRes, TotpCodeReq};
 
 use crate::service::http;
-use crate::ui::{AlertMsg, AlertType, ALERT_MSG, AUTHENTICATED};
+use crate::ui::{AlertMsg, AlertType, ALERT_MSG, AUTHENTICATED, ROLE, SESSIONS, TOTP_REQUIRED};
 
 pub async fn sign_up(req: SignReq) {
-    if let Err(e) = http::<SignReq, ()>(Method::POST, "/api/sign_up", Some(&req)).await {
-        error!("注册失败： {e}");
-        *ALERT_MSG.write() = AlertMsg::new(Some(AlertType::Error), "信息有误，注册失败");
-    } else {
-        *AUTHENTICATED.write() = true;
+    match http::<SignReq, SignRes>(Method::POST, "/api/sign_up", Some(&req)).await {
+        Ok(res) => sign_res(res),
+        Err(e) => {
+            error!("注册失败： {e}");
+            *ALERT_MSG.write() = AlertMsg::error(&e, "信息有误，注册失败");
+        }
     }
 }
 
 pub async fn sign_in(req: SignReq) {
-    if let Err(e) = http::<SignReq, ()>(Method::POST, "/api/sign_in", Some(&req)).await {
-        error!("登录失败： {e}");
-        *ALERT_MSG.write() = AlertMsg::new(Some(AlertType::Error), "用户名或密码错误");
-    } else {
-        *AUTHENTICATED.write() = true;
+    match http::<SignReq, SignRes>(Method::POST, "/api/sign_in", Some(&req)).await {
+        Ok(res) => sign_res(res),
+        Err(e) => {
+            error!("登录失败： {e}");
+            *ALERT_MSG.write() = AlertMsg::error(&e, "用户名或密码错误");
+        }
+    }
+}
+
+pub async fn sign_in_totp(req: TotpCodeReq) {
+    match http::<TotpCodeReq, SignRes>(Method::POST, "/api/sign_in_totp", Some(&req)).await {
+        Ok(res) => sign_res(res),
+        Err(e) => {
+            error!("两步验证失败： {e}");
+            // 等待验证已超时，回到输入密码
+            if e.status() == Some(StatusCode::UNAUTHORIZED) {
+                *TOTP_REQUIRED.write() = false;
+                *ALERT_MSG.write() =
+                    AlertMsg::new(Some(AlertType::Error), "验证已超时，请重新登录");
+                return;
+            }
+            *ALERT_MSG.write() = AlertMsg::error(&e, "验证码错误");
+        }
     }
 }
 
 pub async fn sign_check() {
-    if let Ok(true) = http::<(), bool>(Method::POST, "/api/sign_check", None).await {
-        *AUTHENTICATED.write() = true;
+    if let Ok(SignCheckRes { role: Some(role) }) =
+        http::<(), SignCheckRes>(Method::POST, "/api/sign_check", None).await
+    {
+        signed_in(role);
     } else {
         *AUTHENTICATED.write() = false;
     }
 }
 
+fn sign_res(res: SignRes) {
+    match res {
+        SignRes::SignedIn(role) => signed_in(role),
+        SignRes::TotpRequired => *TOTP_REQUIRED.write() = true,
+    }
+}
+
+fn signed_in(role: Role) {
+    *TOTP_REQUIRED.write() = false;
+    *ROLE.write() = role;
+    *AUTHENTICATED.write() = true;
+}
+
 pub async fn logout() {
     *AUTHENTICATED.write() = false;
-    if let Err(e) = http::<(), ()>(Method::POST, "/api/logout", None).await {
+    if let Err(e) = http::<(), Empty>(Method::POST, "/api/logout", None).await {
         error!("登出失败： {e}");
     } else {
         info!("登出成功！")
     }
 }
+
+pub async fn sessions() {
+    match http::<(), Vec<LoginSession>>(Method::POST, "/api/sessions", None).await {
+        Ok(res) => *SESSIONS.write() = res,
+        Err(e) => error!("查询登录设备失败： {e}"),
+    }
+}
+
+pub async fn revoke_session(req: RevokeReq) {
+    // 撤销了当前登录时，服务端已清除session，需要重新登录
+    let current = SESSIONS
+        .read()
+        .iter()
+        .any(|s| s.current && req.id.as_ref().is_none_or(|id| *id == s.id));
+    if let Err(e) = http::<RevokeReq, Empty>(Method::POST, "/api/revoke_session", Some(&req)).await
+    {
+        error!("退出登录设备失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "退出登录设备失败");
+        return;
+    }
+    if current {
+        *AUTHENTICATED.write() = false;
+    } else {
+        sessions().await;
+    }
+}
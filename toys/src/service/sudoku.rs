@@ -1,12 +1,15 @@
 use reqwest::Method;
 use tracing::error;
 
+use toy_schema::sudoku::SudokuBoard;
+
 use crate::service::http;
 use crate::ui::SUDOKU;
 
 pub async fn sudoku(req: [u16; 81]) {
-    match http::<[u16; 81], [u16; 81]>(Method::POST, "/api/sudoku", Some(&req)).await {
-        Ok(res) => *SUDOKU.write() = res,
+    let req = SudokuBoard(req);
+    match http::<SudokuBoard, SudokuBoard>(Method::POST, "/api/sudoku", Some(&req)).await {
+        Ok(res) => *SUDOKU.write() = res.0,
         Err(e) => error!("数独计算失败： {e}"),
     }
 }
@@ -0,0 +1,42 @@
+use reqwest::Method;
+use tracing::error;
+
+use toy_schema::protocol::Empty;
+use toy_schema::sign::{ApiToken, CreateTokenReq, CreateTokenRes, RevokeTokenReq};
+
+use crate::service::http;
+use crate::ui::{AlertMsg, ALERT_MSG, NEW_TOKEN, TOKENS};
+
+pub async fn api_tokens() {
+    match http::<(), Vec<ApiToken>>(Method::POST, "/api/api_tokens", None).await {
+        Ok(res) => *TOKENS.write() = res,
+        Err(e) => error!("查询访问令牌失败： {e}"),
+    }
+}
+
+pub async fn create_token(req: CreateTokenReq) {
+    match http::<CreateTokenReq, CreateTokenRes>(Method::POST, "/api/create_token", Some(&req))
+        .await
+    {
+        Ok(CreateTokenRes { token }) => {
+            *ALERT_MSG.write() = Default::default();
+            *NEW_TOKEN.write() = Some(token);
+            api_tokens().await;
+        }
+        Err(e) => {
+            error!("创建访问令牌失败： {e}");
+            *ALERT_MSG.write() = AlertMsg::error(&e, "令牌名称和权限范围不能为空");
+        }
+    }
+}
+
+pub async fn revoke_token(req: RevokeTokenReq) {
+    if let Err(e) =
+        http::<RevokeTokenReq, Empty>(Method::POST, "/api/revoke_token", Some(&req)).await
+    {
+        error!("撤销访问令牌失败： {e}");
+        *ALERT_MSG.write() = AlertMsg::error(&e, "撤销访问令牌失败");
+        return;
+    }
+    api_tokens().await;
+}
@@ -0,0 +1,186 @@
+use dioxus::prelude::*;
+
+use toy_schema::sign::{ChangePasswordReq, DeleteAccountReq, Profile, TotpSetup};
+
+use crate::service::Api;
+use crate::ui::{AlertType, FieldHint, ALERT_MSG};
+
+pub static PROFILE: GlobalSignal<Profile> = Signal::global(Default::default);
+/// 待确认的两步验证密钥
+pub static TOTP_SETUP: GlobalSignal<Option<TotpSetup>> = Signal::global(|| None);
+/// 启用两步验证后返回的恢复码，只展示这一次
+pub static RECOVERY_CODES: GlobalSignal<Vec<String>> = Signal::global(Vec::new);
+
+#[component]
+pub fn Account() -> Element {
+    let api = use_coroutine_handle::<Api>();
+    use_hook(|| api.send(Api::Profile));
+
+    let mut name = use_signal(String::new);
+    let mut email = use_signal(String::new);
+    let mut old_password = use_signal(String::new);
+    let mut new_password = use_signal(String::new);
+    let mut confirm_password = use_signal(String::new);
+    let mut delete_password = use_signal(String::new);
+    let mut totp_code = use_signal(String::new);
+
+    // 资料加载完成后填入表单
+    use_effect(move || {
+        let profile = PROFILE.read();
+        name.set(profile.name.clone());
+        email.set(profile.email.clone());
+    });
+
+    let alert_class = match ALERT_MSG.read().typ {
+        Some(AlertType::Error) => "alert alert-error",
+        _ => "alert alert-info",
+    };
+
+    rsx!(article { class:"flex flex-col p-3 space-y-6 w-full max-w-xl",
+        div { class: "{alert_class}",
+            role: "alert",
+            hidden: ALERT_MSG.read().typ.is_none(),
+            span { {ALERT_MSG.read().msg.as_str()} }
+            button { class: "btn btn-ghost btn-xs",
+                onclick: |_| *ALERT_MSG.write() = Default::default(),
+                "关闭"
+            }
+        }
+        section { class: "flex flex-col space-y-3",
+            h2 { class: "text-lg font-medium", "用户资料" }
+            input { class: "input input-bordered w-full",
+                r#type: "text",
+                placeholder: "姓名",
+                value: "{name}",
+                onchange: move |evt| name.set(evt.value()),
+            }
+            input { class: "input input-bordered w-full",
+                r#type: "email",
+                placeholder: "邮箱",
+                value: "{email}",
+                onchange: move |evt| email.set(evt.value()),
+            }
+            FieldHint { field: "email" }
+            button { class: "btn btn-outline",
+                onclick: move |_| api.send(Api::UpdateProfile(Profile {
+                    name: name(),
+                    email: email(),
+                    totp_enabled: PROFILE.read().totp_enabled,
+                })),
+                "保存资料"
+            }
+        }
+        section { class: "flex flex-col space-y-3",
+            h2 { class: "text-lg font-medium", "修改密码" }
+            input { class: "input input-bordered w-full",
+                r#type: "password",
+                placeholder: "原密码",
+                autocomplete: "off",
+                value: "{old_password}",
+                onchange: move |evt| old_password.set(evt.value()),
+            }
+            input { class: "input input-bordered w-full",
+                r#type: "password",
+                placeholder: "新密码",
+                autocomplete: "off",
+                value: "{new_password}",
+                onchange: move |evt| new_password.set(evt.value()),
+            }
+            input { class: "input input-bordered w-full",
+                r#type: "password",
+                placeholder: "确认新密码",
+                autocomplete: "off",
+                value: "{confirm_password}",
+                onchange: move |evt| confirm_password.set(evt.value()),
+            }
+            button { class: "btn btn-outline",
+                disabled: new_password().is_empty() || new_password() != confirm_password(),
+                onclick: move |_| {
+                    api.send(Api::ChangePassword(ChangePasswordReq {
+                        old_password: old_password(),
+                        new_password: new_password(),
+                    }));
+                    old_password.set(String::new());
+                    new_password.set(String::new());
+                    confirm_password.set(String::new());
+                },
+                "修改密码"
+            }
+        }
+        section { class: "flex flex-col space-y-3",
+            h2 { class: "text-lg font-medium", "两步验证" }
+            if PROFILE.read().totp_enabled {
+                p { class: "text-sm text-gray-500", "已启用。登录时需要输入验证器中的验证码，关闭时需要验证码或恢复码。" }
+                if !RECOVERY_CODES.read().is_empty() {
+                    p { class: "text-sm", "恢复码只展示这一次，每个只能使用一次，请妥善保存：" }
+                    ul { class: "font-mono text-sm grid grid-cols-2 gap-1",
+                        for code in RECOVERY_CODES.read().iter() {
+                            li { key: "{code}", "{code}" }
+                        }
+                    }
+                }
+                input { class: "input input-bordered w-full",
+                    r#type: "text",
+                    placeholder: "验证码或恢复码",
+                    autocomplete: "one-time-code",
+                    value: "{totp_code}",
+                    onchange: move |evt| totp_code.set(evt.value()),
+                }
+                button { class: "btn btn-outline",
+                    disabled: totp_code().trim().is_empty(),
+                    onclick: move |_| {
+                        api.send(Api::TotpDisable(totp_code()));
+                        totp_code.set(String::new());
+                    },
+                    "关闭两步验证"
+                }
+            } else if let Some(setup) = TOTP_SETUP() {
+                p { class: "text-sm text-gray-500", "用验证器应用扫描二维码，或手动输入密钥，然后填写生成的验证码。" }
+                img { class: "w-48 h-48",
+                    src: "data:image/png;base64,{setup.qr_png}",
+                    alt: "{setup.otpauth_uri}",
+                }
+                p { class: "font-mono text-sm break-all", "{setup.secret}" }
+                input { class: "input input-bordered w-full",
+                    r#type: "text",
+                    placeholder: "6位验证码",
+                    autocomplete: "one-time-code",
+                    value: "{totp_code}",
+                    onchange: move |evt| totp_code.set(evt.value()),
+                }
+                button { class: "btn btn-outline",
+                    disabled: totp_code().trim().is_empty(),
+                    onclick: move |_| {
+                        api.send(Api::TotpEnable(totp_code()));
+                        totp_code.set(String::new());
+                    },
+                    "确认启用"
+                }
+            } else {
+                p { class: "text-sm text-gray-500", "启用后，登录时除密码外还需要输入验证器应用生成的验证码。" }
+                button { class: "btn btn-outline",
+                    onclick: move |_| api.send(Api::TotpSetup),
+                    "启用两步验证"
+                }
+            }
+        }
+        section { class: "flex flex-col space-y-3",
+            h2 { class: "text-lg font-medium text-red-600", "注销账户" }
+            p { class: "text-sm text-gray-500", "账户及其所有数据将被永久删除，无法恢复。" }
+            input { class: "input input-bordered w-full",
+                r#type: "password",
+                placeholder: "输入密码确认",
+                autocomplete: "off",
+                value: "{delete_password}",
+                onchange: move |evt| delete_password.set(evt.value()),
+            }
+            button { class: "btn btn-error btn-outline",
+                disabled: delete_password().is_empty(),
+                onclick: move |_| api.send(Api::DeleteAccount(DeleteAccountReq {
+                    password: delete_password(),
+                })),
+                "注销账户"
+            }
+        }
+    })
+}
@@ -1,32 +1,251 @@
+use std::collections::BTreeMap;
+
 use dioxus::prelude::*;
 use tracing::info;
 
+use toy_schema::config::{ConfigChange, ConfigField, ConfigKind, ConfigView, SaveConfigReq};
+use toy_schema::error::FieldError;
+
 use crate::service::Api;
 
 pub static SETTINGS_BTN_DISABLE: GlobalSignal<bool> = Signal::global(|| false);
+/// 上次重新加载配置时有变化的配置项，未加载过时为空
+pub static RELOAD_CHANGES: GlobalSignal<Option<Vec<ConfigChange>>> = Signal::global(|| None);
+/// 上次重新加载时配置文件中的错误，此时服务端仍使用原配置
+pub static RELOAD_ERRORS: GlobalSignal<Vec<FieldError>> = Signal::global(Vec::new);
+/// 当前生效的可编辑配置和历史版本
+pub static CONFIG_VIEW: GlobalSignal<Option<ConfigView>> = Signal::global(|| None);
+
+/// 表单中的校验，其余的由服务端校验
+fn check(kind: ConfigKind, value: &str) -> Option<&'static str> {
+    let value = value.trim();
+    match kind {
+        ConfigKind::Integer if value.parse::<i64>().is_err() => Some("应为整数"),
+        ConfigKind::Float if value.parse::<f64>().is_err() => Some("应为数字"),
+        _ => None,
+    }
+}
 
 #[component]
 pub fn Settings() -> Element {
     let api = use_coroutine_handle::<Api>();
+    use_hook(|| api.send(Api::Config));
+
+    // 修改过的配置项，key为配置项的路径
+    let mut edits = use_signal(BTreeMap::<String, String>::new);
+    // 保存或回滚成功后服务端的配置已更新，清空表单中的修改
+    use_effect(move || {
+        let _ = CONFIG_VIEW.read();
+        edits.write().clear();
+    });
+
+    let view = CONFIG_VIEW().unwrap_or_default();
+    let invalid = view.fields.iter().any(|f| {
+        edits
+            .read()
+            .get(&f.key)
+            .is_some_and(|v| check(f.kind, v).is_some())
+    });
+    let nothing_changed = edits.read().is_empty();
+    let changed: Vec<ConfigField> = view
+        .fields
+        .iter()
+        .filter_map(|f| {
+            edits.read().get(&f.key).map(|value| ConfigField {
+                value: value.clone(),
+                ..f.clone()
+            })
+        })
+        .collect();
 
     rsx!(article { class:"flex flex-col p-3 space-y-3",
         h1{
             "Settings"
         }
-        button { class: "group inline-block rounded w-full enabled:outline outline-red-500 disabled:bg-gray-300
-        enabled:hover:bg-gradient-to-r enabled:hover:from-pink-500 enabled:hover:via-red-500 enabled:hover:to-yellow-500
-        hover:outline-none hover:shadow-xl hover:text-white active:text-opacity-75",
-            r#type: "button",
-            disabled: SETTINGS_BTN_DISABLE(),
-            onclick: move |_| {
-                info!("begin config reload");
-                *SETTINGS_BTN_DISABLE.write() = true;
-                api.send(Api::ConfigReload);
-            },
-            span { class: "flex justify-center rounded-sm bg-white px-8 py-3 text-sm font-medium group-hover:bg-transparent
-            group-disabled:bg-transparent",
-                "提交"
+        if !RELOAD_ERRORS.read().is_empty() {
+            div { class: "alert alert-error flex flex-col items-start",
+                p { "配置有误，仍在使用原配置：" }
+                ul { class: "list-disc pl-5 text-sm",
+                    for e in RELOAD_ERRORS.read().iter() {
+                        li { key: "{e.field}{e.message}",
+                            span { class: "font-mono", "{e.field}" }
+                            "：{e.message}"
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(changes) = RELOAD_CHANGES.read().as_ref() {
+            if changes.is_empty() {
+                p { class: "text-sm", "配置没有变化" }
+            } else {
+                table { class: "table",
+                    thead {
+                        tr {
+                            th { "配置项" }
+                            th { "原值" }
+                            th { "新值" }
+                            th {}
+                        }
+                    }
+                    tbody {
+                        for change in changes.iter() {
+                            tr { key: "{change.key}",
+                                td { class: "font-mono", "{change.key}" }
+                                td { class: "max-w-xs truncate font-mono", title: "{change.old}", "{change.old}" }
+                                td { class: "max-w-xs truncate font-mono", title: "{change.new}", "{change.new}" }
+                                td {
+                                    if change.applied {
+                                        span { class: "badge badge-success", "已生效" }
+                                    } else {
+                                        span { class: "badge badge-warning", "重启后生效" }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        section { class: "flex flex-col space-y-3 max-w-2xl",
+            h2 { class: "text-lg font-medium", "配置" }
+            p { class: "text-sm text-gray-500", "保存后写入服务端的配置文件并重新加载，原文件自动备份为历史版本。密码等敏感项不显示，留空表示不修改。" }
+            for field in view.fields.iter() {
+                ConfigInput { key: "{field.key}", field: field.clone(), edits }
+            }
+            div { class: "flex space-x-3",
+                button { class: "btn btn-outline",
+                    disabled: nothing_changed || invalid || SETTINGS_BTN_DISABLE(),
+                    onclick: move |_| {
+                        info!("save config, {} fields changed", changed.len());
+                        *SETTINGS_BTN_DISABLE.write() = true;
+                        api.send(Api::SaveConfig(SaveConfigReq { fields: changed.clone() }));
+                    },
+                    "保存"
+                }
+                button { class: "btn btn-ghost",
+                    disabled: nothing_changed,
+                    onclick: move |_| edits.write().clear(),
+                    "撤销修改"
+                }
+            }
+        }
+        section { class: "flex flex-col space-y-3 max-w-2xl",
+            h2 { class: "text-lg font-medium", "历史版本" }
+            if view.versions.is_empty() {
+                p { class: "text-sm text-gray-500", "还没有保存过配置" }
+            } else {
+                table { class: "table",
+                    thead {
+                        tr {
+                            th { "保存时间（UTC）" }
+                            th {}
+                        }
+                    }
+                    tbody {
+                        for version in view.versions.iter() {
+                            tr { key: "{version.id}",
+                                td { class: "font-mono", "{version.saved_at}" }
+                                td {
+                                    button { class: "btn btn-outline btn-xs",
+                                        disabled: SETTINGS_BTN_DISABLE(),
+                                        onclick: {
+                                            let id = version.id.clone();
+                                            move |_| {
+                                                info!("rollback config to {id}");
+                                                *SETTINGS_BTN_DISABLE.write() = true;
+                                                api.send(Api::RollbackConfig(id.clone()));
+                                            }
+                                        },
+                                        "回滚"
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        section { class: "flex flex-col space-y-3 max-w-2xl",
+            h2 { class: "text-lg font-medium", "重新加载" }
+            p { class: "text-sm text-gray-500", "配置文件在服务端被直接修改后，重新加载使其生效。" }
+            button { class: "group inline-block rounded w-full enabled:outline outline-red-500 disabled:bg-gray-300
+            enabled:hover:bg-gradient-to-r enabled:hover:from-pink-500 enabled:hover:via-red-500 enabled:hover:to-yellow-500
+            hover:outline-none hover:shadow-xl hover:text-white active:text-opacity-75",
+                r#type: "button",
+                disabled: SETTINGS_BTN_DISABLE(),
+                onclick: move |_| {
+                    info!("begin config reload");
+                    *SETTINGS_BTN_DISABLE.write() = true;
+                    api.send(Api::ConfigReload);
+                },
+                span { class: "flex justify-center rounded-sm bg-white px-8 py-3 text-sm font-medium group-hover:bg-transparent
+                group-disabled:bg-transparent",
+                    "提交"
+                }
             }
         }
     })
 }
+
+/// 一个配置项的输入框，按类型选择输入方式
+#[component]
+fn ConfigInput(field: ConfigField, edits: Signal<BTreeMap<String, String>>) -> Element {
+    let value = edits
+        .read()
+        .get(&field.key)
+        .cloned()
+        .unwrap_or_else(|| field.value.clone());
+    let hint = check(field.kind, &value).map(str::to_owned).or_else(|| {
+        RELOAD_ERRORS
+            .read()
+            .iter()
+            .find(|e| e.field == field.key)
+            .map(|e| e.message.clone())
+    });
+    let (input_type, class) = match field.kind {
+        ConfigKind::Bool => ("checkbox", "checkbox"),
+        ConfigKind::Integer | ConfigKind::Float => ("number", "input input-bordered input-sm w-full"),
+        ConfigKind::Text if field.secret => ("password", "input input-bordered input-sm w-full"),
+        ConfigKind::Text => ("text", "input input-bordered input-sm w-full"),
+    };
+    let edited = edits.read().contains_key(&field.key);
+
+    rsx!(div { class: "flex flex-col",
+        label { class: if edited { "text-xs font-mono text-blue-600" } else { "text-xs font-mono" },
+            r#for: "{field.key}",
+            "{field.key}"
+        }
+        input { class: "{class}",
+            id: "{field.key}",
+            r#type: input_type,
+            step: if field.kind == ConfigKind::Float { "any" } else { "1" },
+            value: "{value}",
+            checked: value == "true",
+            placeholder: if field.secret { "未修改" } else { "" },
+            autocomplete: "off",
+            disabled: field.overridden,
+            onchange: {
+                let field = field.clone();
+                move |evt: FormEvent| {
+                    let value = match field.kind {
+                        ConfigKind::Bool => evt.checked().to_string(),
+                        _ => evt.value(),
+                    };
+                    // 改回原值时不再提交
+                    if value == field.value {
+                        edits.write().remove(&field.key);
+                    } else {
+                        edits.write().insert(field.key.clone(), value);
+                    }
+                }
+            },
+        }
+        if field.overridden {
+            p { class: "mt-1 text-xs text-gray-500", "由TOYS_开头的环境变量设置，修改配置文件不会生效" }
+        }
+        if let Some(msg) = hint {
+            p { class: "mt-1 text-xs text-red-600", "{msg}" }
+        }
+    })
+}
@@ -0,0 +1,92 @@
+use dioxus::prelude::*;
+
+use toy_schema::chinese::{ChineseVariant, ConvertReq};
+
+use crate::service::Api;
+
+pub static CONVERTED: GlobalSignal<String> = Signal::global(String::new);
+
+#[component]
+pub fn Conversion() -> Element {
+    let mut input_text = use_signal(String::new);
+    let mut target = use_signal(|| ChineseVariant::Traditional);
+    let mut source = use_signal(|| None);
+    let mut normalize_punctuation = use_signal(|| true);
+    let api = use_coroutine_handle::<Api>();
+
+    rsx!(article { class:"grid grid-cols-4 gap-3 w-full",
+        div{ class:"col-start-1 col-end-4 flex flex-col p-3 border-e",
+            label { class: "block text-sm font-medium text-gray-700",
+                r#for: "InputText",
+                "原文"
+            }
+            textarea { class: "flex-1 resize-none mt-2 mr-3 p-3 rounded-lg align-top shadow-sm border focus:outline-none sm:text-sm",
+                id: "InputText",
+                placeholder: "输入需要转换的文本",
+                value: "{input_text}",
+                onchange: move |evt| input_text.set(evt.value()),
+            }
+            label { class: "block mt-3 text-sm font-medium text-gray-700",
+                r#for: "OutputText",
+                "译文"
+            }
+            textarea { class: "flex-1 resize-none mt-2 mr-3 p-3 rounded-lg align-top shadow-sm border focus:outline-none sm:text-sm",
+                id: "OutputText",
+                readonly: true,
+                value: "{CONVERTED}",
+            }
+        }
+        div{ class:"col-start-4 col-end-5 flex flex-col p-3 space-y-3",
+            select { class: "select select-bordered w-full",
+                onchange: move |evt| {
+                    let variant = match evt.value().as_str() {
+                        "simplified" => ChineseVariant::Simplified,
+                        "taiwan" => ChineseVariant::Taiwan,
+                        "hong_kong" => ChineseVariant::HongKong,
+                        _ => ChineseVariant::Traditional,
+                    };
+                    target.set(variant);
+                },
+                option { value: "traditional", selected: true, "繁体" }
+                option { value: "simplified", "简体" }
+                option { value: "taiwan", "台湾正体" }
+                option { value: "hong_kong", "香港繁体" }
+            }
+            // 原文来自台湾或香港时才还原地区用词
+            select { class: "select select-bordered w-full",
+                onchange: move |evt| {
+                    let variant = match evt.value().as_str() {
+                        "taiwan" => Some(ChineseVariant::Taiwan),
+                        "hong_kong" => Some(ChineseVariant::HongKong),
+                        _ => None,
+                    };
+                    source.set(variant);
+                },
+                option { value: "", selected: true, "原文地区不限" }
+                option { value: "taiwan", "原文为台湾用词" }
+                option { value: "hong_kong", "原文为香港用词" }
+            }
+            label { class: "label cursor-pointer",
+                span { class: "label-text", "规范标点与间距" }
+                input { class: "checkbox",
+                    r#type: "checkbox",
+                    checked: normalize_punctuation(),
+                    onchange: move |evt| normalize_punctuation.set(evt.checked()),
+                }
+            }
+            a { class: "group inline-block rounded w-full bg-gradient-to-r from-pink-500 via-red-500 to-yellow-500 p-[2px] hover:shadow-xl hover:text-white focus:outline-none focus:ring active:text-opacity-75",
+                onclick: move |_| {
+                    api.send(Api::Convert(ConvertReq {
+                        text: input_text(),
+                        target: target(),
+                        normalize_punctuation: normalize_punctuation(),
+                        source: source(),
+                    }));
+                },
+                span { class: "flex justify-center rounded-sm bg-white px-8 py-3 text-sm font-medium group-hover:bg-transparent",
+                    "转换"
+                },
+            }
+        }
+    })
+}
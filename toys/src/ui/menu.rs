@@ -3,8 +3,11 @@ use dioxus::dioxus_core::Element;
 use dioxus::prelude::*;
 use dioxus_router::prelude::*;
 
+use toy_schema::sign::Role;
+
 use crate::ui::header::Breadcrumbs;
 use crate::ui::route::Route;
+use crate::ui::ROLE;
 
 pub struct MenuHidden(pub bool);
 
@@ -107,35 +110,151 @@ pub fn Menu() -> Element {
                                     "文本纠错"
                                 }
                             }
+                            li {
+                                Link { class: "flex flex-row rounded-lg px-4 py-2 text-sm text-nowrap font-medium text-gray-500 hover:bg-gray-100 hover:text-gray-700",
+                                    onclick: move|_| breadcrumbs.set(vec!["中文校对".into(), "拼音标注".into()].into()),
+                                    to: Route::Pinyin {},
+                                    svg {
+                                        width: "24",
+                                        "fill": "none",
+                                        "stroke-linecap": "round",
+                                        "viewBox": "0 0 24 24",
+                                        "stroke-width": "2",
+                                        "xmlns": "http://www.w3.org/2000/svg",
+                                        "stroke-linejoin": "round",
+                                        "stroke": "currentColor",
+                                        height: "24",
+                                        path { "d": "M4 7V4h16v3" }
+                                        path { "d": "M9 20h6" }
+                                        path { "d": "M12 4v16" }
+                                    }
+                                    "拼音标注"
+                                }
+                            }
+                            li {
+                                Link { class: "flex flex-row rounded-lg px-4 py-2 text-sm text-nowrap font-medium text-gray-500 hover:bg-gray-100 hover:text-gray-700",
+                                    onclick: move|_| breadcrumbs.set(vec!["中文校对".into(), "繁简转换".into()].into()),
+                                    to: Route::Conversion {},
+                                    svg {
+                                        width: "24",
+                                        "fill": "none",
+                                        "stroke-linecap": "round",
+                                        "viewBox": "0 0 24 24",
+                                        "stroke-width": "2",
+                                        "xmlns": "http://www.w3.org/2000/svg",
+                                        "stroke-linejoin": "round",
+                                        "stroke": "currentColor",
+                                        height: "24",
+                                        path { "d": "m5 8 6 6" }
+                                        path { "d": "m4 14 6-6 2-3" }
+                                        path { "d": "M2 5h12" }
+                                        path { "d": "M7 2h1" }
+                                        path { "d": "m22 22-5-10-5 10" }
+                                        path { "d": "M14 18h6" }
+                                    }
+                                    "繁简转换"
+                                }
+                            }
                         }
                     }
                 }
                 li {
                     details { open: true,
-                        summary { "管理" }
+                        summary { "账户" }
                         ul {
                             li {
                                 Link { class: "flex flex-row rounded-lg px-4 py-2 text-sm text-nowrap font-medium text-gray-500 hover:bg-gray-100 hover:text-gray-700",
-                                    onclick: move|_| breadcrumbs.set(vec!["管理".into(), "设置".into()].into()),
-                                    to: Route::Settings {},
-                                    svg { class: "h-5 w-5 mr-3 opacity-75",
-                                        xmlns: "http://www.w3.org/2000/svg",
-                                        fill: "none",
-                                        stroke: "currentColor",
-                                        stroke_width: "2",
-                                        view_box: "0 0 24 24",
-                                        path {
-                                            stroke_linecap: "round",
-                                            stroke_linejoin: "round",
-                                            d: "M10.325 4.317c.426-1.756 2.924-1.756 3.35 0a1.724 1.724 0 002.573 1.066c1.543-.94 3.31.826 2.37 2.37a1.724 1.724 0 001.065 2.572c1.756.426 1.756 2.924 0 3.35a1.724 1.724 0 00-1.066 2.573c.94 1.543-.826 3.31-2.37 2.37a1.724 1.724 0 00-2.572 1.065c-.426 1.756-2.924 1.756-3.35 0a1.724 1.724 0 00-2.573-1.066c-1.543.94-3.31-.826-2.37-2.37a1.724 1.724 0 00-1.065-2.572c-1.756-.426-1.756-2.924 0-3.35a1.724 1.724 0 001.066-2.573c-.94-1.543.826-3.31 2.37-2.37.996.608 2.296.07 2.572-1.065z"
-                                        }
-                                        path {
-                                            stroke_linejoin: "round",
-                                            d: "M15 12a3 3 0 11-6 0 3 3 0 016 0z",
-                                            stroke_linecap: "round"
-                                        }
-                                    },
-                                    "设置"
+                                    onclick: move|_| breadcrumbs.set(vec!["账户".into(), "账户设置".into()].into()),
+                                    to: Route::Account {},
+                                    svg {
+                                        width: "24",
+                                        "fill": "none",
+                                        "stroke-linecap": "round",
+                                        "viewBox": "0 0 24 24",
+                                        "stroke-width": "2",
+                                        "xmlns": "http://www.w3.org/2000/svg",
+                                        "stroke-linejoin": "round",
+                                        "stroke": "currentColor",
+                                        height: "24",
+                                        circle { "cx": "12", "cy": "8", "r": "5" }
+                                        path { "d": "M20 21a8 8 0 0 0-16 0" }
+                                    }
+                                    "账户设置"
+                                }
+                            }
+                            li {
+                                Link { class: "flex flex-row rounded-lg px-4 py-2 text-sm text-nowrap font-medium text-gray-500 hover:bg-gray-100 hover:text-gray-700",
+                                    onclick: move|_| breadcrumbs.set(vec!["账户".into(), "登录设备".into()].into()),
+                                    to: Route::Sessions {},
+                                    svg {
+                                        width: "24",
+                                        "fill": "none",
+                                        "stroke-linecap": "round",
+                                        "viewBox": "0 0 24 24",
+                                        "stroke-width": "2",
+                                        "xmlns": "http://www.w3.org/2000/svg",
+                                        "stroke-linejoin": "round",
+                                        "stroke": "currentColor",
+                                        height: "24",
+                                        rect { width: "20", height: "14", "x": "2", "y": "3", "rx": "2" }
+                                        path { "d": "M8 21h8" }
+                                        path { "d": "M12 17v4" }
+                                    }
+                                    "登录设备"
+                                }
+                            }
+                            li {
+                                Link { class: "flex flex-row rounded-lg px-4 py-2 text-sm text-nowrap font-medium text-gray-500 hover:bg-gray-100 hover:text-gray-700",
+                                    onclick: move|_| breadcrumbs.set(vec!["账户".into(), "访问令牌".into()].into()),
+                                    to: Route::Tokens {},
+                                    svg {
+                                        width: "24",
+                                        "fill": "none",
+                                        "stroke-linecap": "round",
+                                        "viewBox": "0 0 24 24",
+                                        "stroke-width": "2",
+                                        "xmlns": "http://www.w3.org/2000/svg",
+                                        "stroke-linejoin": "round",
+                                        "stroke": "currentColor",
+                                        height: "24",
+                                        circle { "cx": "7.5", "cy": "15.5", "r": "5.5" }
+                                        path { "d": "m21 2-9.6 9.6" }
+                                        path { "d": "m15.5 7.5 3 3L22 7l-3-3" }
+                                    }
+                                    "访问令牌"
+                                }
+                            }
+                        }
+                    }
+                }
+                if ROLE() == Role::Admin {
+                    li {
+                        details { open: true,
+                            summary { "管理" }
+                            ul {
+                                li {
+                                    Link { class: "flex flex-row rounded-lg px-4 py-2 text-sm text-nowrap font-medium text-gray-500 hover:bg-gray-100 hover:text-gray-700",
+                                        onclick: move|_| breadcrumbs.set(vec!["管理".into(), "设置".into()].into()),
+                                        to: Route::Settings {},
+                                        svg { class: "h-5 w-5 mr-3 opacity-75",
+                                            xmlns: "http://www.w3.org/2000/svg",
+                                            fill: "none",
+                                            stroke: "currentColor",
+                                            stroke_width: "2",
+                                            view_box: "0 0 24 24",
+                                            path {
+                                                stroke_linecap: "round",
+                                                stroke_linejoin: "round",
+                                                d: "M10.325 4.317c.426-1.756 2.924-1.756 3.35 0a1.724 1.724 0 002.573 1.066c1.543-.94 3.31.826 2.37 2.37a1.724 1.724 0 001.065 2.572c1.756.426 1.756 2.924 0 3.35a1.724 1.724 0 00-1.066 2.573c.94 1.543-.826 3.31-2.37 2.37a1.724 1.724 0 00-2.572 1.065c-.426 1.756-2.924 1.756-3.35 0a1.724 1.724 0 00-2.573-1.066c-1.543.94-3.31-.826-2.37-2.37a1.724 1.724 0 00-1.065-2.572c-1.756-.426-1.756-2.924 0-3.35a1.724 1.724 0 001.066-2.573c-.94-1.543.826-3.31 2.37-2.37.996.608 2.296.07 2.572-1.065z"
+                                            }
+                                            path {
+                                                stroke_linejoin: "round",
+                                                d: "M15 12a3 3 0 11-6 0 3 3 0 016 0z",
+                                                stroke_linecap: "round"
+                                            }
+                                        },
+                                        "设置"
+                                    }
                                 }
                             }
                         }
@@ -1,14 +1,24 @@
-pub use config::SETTINGS_BTN_DISABLE;
+pub use account::{PROFILE, RECOVERY_CODES, TOTP_SETUP};
+pub use config::{CONFIG_VIEW, RELOAD_CHANGES, RELOAD_ERRORS, SETTINGS_BTN_DISABLE};
+pub use conversion::CONVERTED;
+pub use pinyin::PINYIN;
 pub use route::Route;
-pub use sign::{AlertMsg, AlertType, ALERT_MSG, AUTHENTICATED};
+pub use sessions::SESSIONS;
+pub use sign::{AlertMsg, AlertType, FieldHint, ALERT_MSG, AUTHENTICATED, ROLE, TOTP_REQUIRED};
 pub use sudoku::SUDOKU;
+pub use tokens::{NEW_TOKEN, TOKENS};
 
+mod account;
 mod config;
+mod conversion;
 mod fight_the_landlord;
 mod header;
 mod home;
 mod menu;
+mod pinyin;
 mod proofreading;
 mod route;
+mod sessions;
 mod sign;
 mod sudoku;
+mod tokens;
@@ -0,0 +1,61 @@
+use dioxus::prelude::*;
+
+use toy_schema::chinese::{PinyinReq, Ruby, ToneStyle};
+
+use crate::service::Api;
+
+pub static PINYIN: GlobalSignal<Vec<Ruby>> = Signal::global(Vec::new);
+
+#[component]
+pub fn Pinyin() -> Element {
+    let mut input_text = use_signal(String::new);
+    let mut style = use_signal(|| ToneStyle::Mark);
+    let api = use_coroutine_handle::<Api>();
+
+    rsx!(article { class:"grid grid-cols-4 gap-3 w-full",
+        div{ class:"col-start-1 col-end-4 flex flex-col p-3 border-e",
+            label { class: "block text-sm font-medium text-gray-700",
+                r#for: "InputText",
+                "原文"
+            }
+            textarea { class: "flex-1 resize-none mt-2 mr-3 p-3 rounded-lg align-top shadow-sm border focus:outline-none sm:text-sm",
+                id: "InputText",
+                placeholder: "输入需要注音的文本",
+                value: "{input_text}",
+                onchange: move |evt| input_text.set(evt.value()),
+            }
+            p { class: "flex-1 mt-3 mr-3 p-3 rounded-lg shadow-sm border text-xl leading-loose whitespace-pre-wrap",
+                for segment in PINYIN.read().iter() {
+                    match &segment.pinyin {
+                        Some(pinyin) => rsx!(ruby { "{segment.text}" rt { class: "text-xs text-gray-500", "{pinyin}" } }),
+                        None => rsx!(span { "{segment.text}" }),
+                    }
+                }
+            }
+        }
+        div{ class:"col-start-4 col-end-5 flex flex-col p-3 space-y-3",
+            select { class: "select select-bordered w-full",
+                onchange: move |evt| {
+                    let tone_style = match evt.value().as_str() {
+                        "number" => ToneStyle::Number,
+                        _ => ToneStyle::Mark,
+                    };
+                    style.set(tone_style);
+                },
+                option { value: "mark", selected: true, "声调符号" }
+                option { value: "number", "数字声调" }
+            }
+            a { class: "group inline-block rounded w-full bg-gradient-to-r from-pink-500 via-red-500 to-yellow-500 p-[2px] hover:shadow-xl hover:text-white focus:outline-none focus:ring active:text-opacity-75",
+                onclick: move |_| {
+                    api.send(Api::Pinyin(PinyinReq {
+                        text: input_text(),
+                        style: style(),
+                    }));
+                },
+                span { class: "flex justify-center rounded-sm bg-white px-8 py-3 text-sm font-medium group-hover:bg-transparent",
+                    "注音"
+                },
+            }
+        }
+    })
+}
@@ -1,14 +1,19 @@
 use dioxus::prelude::*;
 use dioxus_router::prelude::*;
 
+use super::account::Account;
 use super::config::Settings;
+use super::conversion::Conversion;
 use super::fight_the_landlord::{FTLInit, FTLPlay, FightTheLandlord};
 use super::header::{Breadcrumbs, Header};
 use super::home::Home;
 use super::menu::{Menu, MenuHidden};
+use super::pinyin::Pinyin;
 use super::proofreading::Proofreading;
+use super::sessions::Sessions;
 use super::sign::{Sign, AUTHENTICATED};
 use super::sudoku::Sudoku;
+use super::tokens::Tokens;
 
 #[rustfmt::skip]
 #[derive(Clone, Debug, PartialEq, Routable)]
@@ -30,6 +35,16 @@ pub enum Route {
     #[end_nest]
     #[route("/proofreading")]
     Proofreading {},
+    #[route("/conversion")]
+    Conversion {},
+    #[route("/pinyin")]
+    Pinyin {},
+    #[route("/account")]
+    Account {},
+    #[route("/sessions")]
+    Sessions {},
+    #[route("/tokens")]
+    Tokens {},
     #[route("/settings")]
     Settings {},
 }
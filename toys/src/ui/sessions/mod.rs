@@ -0,0 +1,57 @@
+use dioxus::prelude::*;
+
+use toy_schema::sign::LoginSession;
+
+use crate::service::Api;
+
+pub static SESSIONS: GlobalSignal<Vec<LoginSession>> = Signal::global(Vec::new);
+
+#[component]
+pub fn Sessions() -> Element {
+    let api = use_coroutine_handle::<Api>();
+    use_hook(|| api.send(Api::Sessions));
+
+    rsx!(article { class:"flex flex-col p-3 space-y-3 w-full",
+        div { class: "flex flex-row justify-between items-center",
+            h1 { class: "text-lg font-medium", "登录设备" }
+            button { class: "btn btn-outline btn-error btn-sm",
+                onclick: move |_| api.send(Api::RevokeSession(None)),
+                "退出全部登录"
+            }
+        }
+        table { class: "table",
+            thead {
+                tr {
+                    th { "设备" }
+                    th { "IP" }
+                    th { "登录时间" }
+                    th { "最近活动" }
+                    th {}
+                }
+            }
+            tbody {
+                for login in SESSIONS.read().iter() {
+                    tr { key: "{login.id}",
+                        td { class: "max-w-md truncate", title: "{login.user_agent}", "{login.user_agent}" }
+                        td { "{login.ip}" }
+                        td { "{login.created_at}" }
+                        td { "{login.last_seen}" }
+                        td {
+                            if login.current {
+                                span { class: "badge badge-success", "当前" }
+                            } else {
+                                button { class: "btn btn-ghost btn-xs",
+                                    onclick: {
+                                        let id = login.id.clone();
+                                        move |_| api.send(Api::RevokeSession(Some(id.clone())))
+                                    },
+                                    "退出"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}
@@ -1,17 +1,29 @@
 use dioxus::prelude::*;
 use tracing::info;
 
-use toy_schema::sign::SignReq;
+use toy_schema::error::FieldError;
+use toy_schema::sign::{Role, SignReq};
 
+use crate::error::Error;
 use crate::service::Api;
+use crate::ui::sign::reset::ResetPassword;
+use crate::ui::sign::totp::TotpSignIn;
+
+mod reset;
+mod totp;
 
 pub static AUTHENTICATED: GlobalSignal<bool> = Signal::global(|| false);
+pub static ROLE: GlobalSignal<Role> = Signal::global(Role::default);
+/// 密码已验证，等待输入两步验证码
+pub static TOTP_REQUIRED: GlobalSignal<bool> = Signal::global(|| false);
 pub static ALERT_MSG: GlobalSignal<AlertMsg> = Signal::global(Default::default);
 
 #[derive(Default)]
 pub struct AlertMsg {
     pub typ: Option<AlertType>,
     pub msg: String,
+    /// 字段级的错误，显示在对应的输入框下
+    pub details: Vec<FieldError>,
 }
 
 impl AlertMsg {
@@ -19,12 +31,39 @@ impl AlertMsg {
         AlertMsg {
             typ,
             msg: msg.to_string(),
+            details: Vec::new(),
+        }
+    }
+
+    /// 接口出错时的提示，带上服务端返回的字段级错误
+    pub fn error(e: &Error, fallback: &str) -> Self {
+        AlertMsg {
+            typ: Some(AlertType::Error),
+            msg: e.alert(fallback),
+            details: e.details(),
         }
     }
+
+    pub fn field(&self, field: &str) -> Option<String> {
+        self.details
+            .iter()
+            .find(|d| d.field == field)
+            .map(|d| d.message.clone())
+    }
+}
+
+/// 输入框下的字段级错误提示
+#[component]
+pub fn FieldHint(field: &'static str) -> Element {
+    rsx!(
+        if let Some(msg) = ALERT_MSG.read().field(field) {
+            p { class: "mt-1 text-xs text-red-600", "{msg}" }
+        }
+    )
 }
 
 pub enum AlertType {
-    // Info,
+    Info,
     // Warn,
     Error,
 }
@@ -35,9 +74,15 @@ pub fn Sign() -> Element {
     let mut user_name = use_signal(|| "".to_string());
     let mut user_email = use_signal(|| "".to_string());
     let mut user_password = use_signal(|| "".to_string());
+    let mut resetting = use_signal(|| false);
     let api = use_coroutine_handle::<Api>();
     api.send(Api::SignCheck);
 
+    let (alert_color, alert_title) = match ALERT_MSG.read().typ {
+        Some(AlertType::Info) => ("text-gray-700", "提示"),
+        _ => ("text-red-600", "失败"),
+    };
+
     rsx!(
         div { class: "text-gray-800 antialiased",
             nav{class:"top-0 absolute z-50 w-full flex flex-wrap items-center justify-between px-2 py-3 ",
@@ -59,10 +104,10 @@ pub fn Sign() -> Element {
                     div{class: "mx-auto sm:w-3/4 md:w-2/4 fixed z-50 inset-x-0 top-10 rounded-xl border border-gray-100 bg-white p-4",
                         role: "alert",
                         hidden: ALERT_MSG.read().typ.is_none(),
-                        div{class: "flex items-start gap-4 text-red-600",
+                        div{class: "flex items-start gap-4 {alert_color}",
                             img{width: 24,src:"error.svg",alt:""}
                             div{class:"flex-1",
-                                strong{class:"block font-medium", "失败"}
+                                strong{class:"block font-medium", "{alert_title}"}
                                 p{class:"mt-1 text-sm", {ALERT_MSG.read().msg.as_str()}}
                             }
                             button{class:"text-gray-500 transition hover:text-gray-600",
@@ -86,6 +131,7 @@ pub fn Sign() -> Element {
                             div{ class:"w-full lg:w-6/12 px-4",
                                 div{ class:"relative flex flex-col min-w-0 break-words w-full mb-6 shadow-lg rounded-lg bg-gray-300 border-0",
                                     div{class:"flex-auto px-4 py-6 lg:px-10",
+                                        hidden: resetting() || TOTP_REQUIRED(),
                                         // form{
                                             div{class:"relative w-full mb-3",
                                                 hidden: *sign_in.read(),
@@ -100,6 +146,7 @@ pub fn Sign() -> Element {
                                                     style:"transition: all 0.15s ease 0s;",
                                                     onchange: move |evt| user_name.set(evt.value()),
                                                 }
+                                                FieldHint { field: "name" }
                                             }
                                             div{class:"relative w-full mb-3",
                                                 label{class:"block text-gray-700 text-xs font-bold mb-2",
@@ -113,6 +160,7 @@ pub fn Sign() -> Element {
                                                     style:"transition: all 0.15s ease 0s;",
                                                     onchange: move |evt| user_email.set(evt.value()),
                                                 }
+                                                FieldHint { field: "email" }
                                             }
                                             div{class:"relative w-full mb-3",
                                                 label{class:"block text-gray-700 text-xs font-bold mb-2",
@@ -127,6 +175,7 @@ pub fn Sign() -> Element {
                                                     style:"transition: all 0.15s ease 0s;",
                                                     onchange: move |evt| user_password.set(evt.value()),
                                                 }
+                                                FieldHint { field: "password" }
                                             }
                                             div{class:"text-center mt-6 mb-3",
                                                 button{ class:"btn btn-wide btn-outline",
@@ -157,7 +206,7 @@ pub fn Sign() -> Element {
                                                     },
                                                 }
                                             }
-                                            div{class:"flex flex-wrap",
+                                            div{class:"flex flex-wrap justify-between",
                                                 a{ class: "text-xs cursor-pointer",
                                                     style: "color: lightslategray;",
                                                     onclick: move |_| *sign_in.write() = !sign_in(),
@@ -167,9 +216,20 @@ pub fn Sign() -> Element {
                                                         "尚无账号，立即注册"
                                                     },
                                                 }
+                                                a{ class: "text-xs cursor-pointer",
+                                                    style: "color: lightslategray;",
+                                                    hidden: !sign_in(),
+                                                    onclick: move |_| resetting.set(true),
+                                                    "忘记密码"
+                                                }
                                             }
                                         // }
                                     }
+                                    if resetting() {
+                                        ResetPassword { resetting }
+                                    } else if TOTP_REQUIRED() {
+                                        TotpSignIn {}
+                                    }
                                 }
                             }
                         }
@@ -0,0 +1,69 @@
+use dioxus::prelude::*;
+
+use toy_schema::sign::{ForgotPasswordReq, ResetPasswordReq};
+
+use crate::service::Api;
+
+/// 忘记密码：先申请重置令牌发到邮箱，再用令牌设置新密码
+#[component]
+pub fn ResetPassword(resetting: Signal<bool>) -> Element {
+    let mut email = use_signal(String::new);
+    let mut token = use_signal(String::new);
+    let mut password = use_signal(String::new);
+    let api = use_coroutine_handle::<Api>();
+
+    rsx!(
+        div{class:"flex-auto px-4 py-6 lg:px-10",
+            div{class:"relative w-full mb-3",
+                label{class:"block text-gray-700 text-xs font-bold mb-2", "邮箱"}
+                div{class:"flex flex-row space-x-3",
+                    input{ class: "flex-1 border-0 px-3 py-3 placeholder-gray-400 text-gray-700 bg-white rounded text-sm shadow focus:outline-none focus:ring",
+                        r#type: "email",
+                        placeholder: "注册时使用的邮箱",
+                        onchange: move |evt| email.set(evt.value()),
+                    }
+                    button{ class:"btn btn-outline",
+                        disabled: email().is_empty(),
+                        onclick: move |_| api.send(Api::ForgotPassword(ForgotPasswordReq{ email: email() })),
+                        "发送重置令牌"
+                    }
+                }
+            }
+            div{class:"relative w-full mb-3",
+                label{class:"block text-gray-700 text-xs font-bold mb-2", "重置令牌"}
+                input{ class: "border-0 px-3 py-3 placeholder-gray-400 text-gray-700 bg-white rounded text-sm shadow focus:outline-none focus:ring w-full",
+                    r#type: "text",
+                    placeholder: "邮件中的重置令牌",
+                    autocomplete: "off",
+                    onchange: move |evt| token.set(evt.value()),
+                }
+            }
+            div{class:"relative w-full mb-3",
+                label{class:"block text-gray-700 text-xs font-bold mb-2", "新密码"}
+                input{ class: "border-0 px-3 py-3 placeholder-gray-400 text-gray-700 bg-white rounded text-sm shadow focus:outline-none focus:ring w-full",
+                    r#type: "password",
+                    placeholder: "新密码",
+                    autocomplete: "off",
+                    onchange: move |evt| password.set(evt.value()),
+                }
+            }
+            div{class:"text-center mt-6 mb-3",
+                button{ class:"btn btn-wide btn-outline",
+                    disabled: token().is_empty() || password().is_empty(),
+                    onclick: move |_| api.send(Api::ResetPassword(ResetPasswordReq{
+                        token: token(),
+                        password: password(),
+                    })),
+                    "重置密码"
+                }
+            }
+            div{class:"flex flex-wrap",
+                a{ class: "text-xs cursor-pointer",
+                    style: "color: lightslategray;",
+                    onclick: move |_| resetting.set(false),
+                    "返回登录"
+                }
+            }
+        }
+    )
+}
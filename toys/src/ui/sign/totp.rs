@@ -0,0 +1,39 @@
+use dioxus::prelude::*;
+
+use crate::service::Api;
+use crate::ui::TOTP_REQUIRED;
+
+/// 登录第二步：输入验证器中的6位验证码，或一个恢复码
+#[component]
+pub fn TotpSignIn() -> Element {
+    let mut code = use_signal(String::new);
+    let api = use_coroutine_handle::<Api>();
+
+    rsx!(
+        div{class:"flex-auto px-4 py-6 lg:px-10",
+            div{class:"relative w-full mb-3",
+                label{class:"block text-gray-700 text-xs font-bold mb-2", "两步验证"}
+                input{ class: "border-0 px-3 py-3 placeholder-gray-400 text-gray-700 bg-white rounded text-sm shadow focus:outline-none focus:ring w-full",
+                    r#type: "text",
+                    placeholder: "验证器中的6位验证码或恢复码",
+                    autocomplete: "one-time-code",
+                    onchange: move |evt| code.set(evt.value()),
+                }
+            }
+            div{class:"text-center mt-6 mb-3",
+                button{ class:"btn btn-wide btn-outline",
+                    disabled: code().trim().is_empty(),
+                    onclick: move |_| api.send(Api::SignInTotp(code())),
+                    "验证"
+                }
+            }
+            div{class:"flex flex-wrap",
+                a{ class: "text-xs cursor-pointer",
+                    style: "color: lightslategray;",
+                    onclick: move |_| *TOTP_REQUIRED.write() = false,
+                    "返回登录"
+                }
+            }
+        }
+    )
+}
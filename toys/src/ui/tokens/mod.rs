@@ -0,0 +1,127 @@
+use dioxus::prelude::*;
+
+use toy_schema::sign::{ApiToken, CreateTokenReq, Scope};
+
+use crate::service::Api;
+use crate::ui::{AlertType, FieldHint, ALERT_MSG};
+
+pub static TOKENS: GlobalSignal<Vec<ApiToken>> = Signal::global(Vec::new);
+/// 刚创建的令牌，只展示这一次
+pub static NEW_TOKEN: GlobalSignal<Option<String>> = Signal::global(|| None);
+
+fn scope_label(scope: Scope) -> &'static str {
+    match scope {
+        Scope::Sudoku => "数独",
+        Scope::Chinese => "繁简转换与拼音",
+    }
+}
+
+#[component]
+pub fn Tokens() -> Element {
+    let api = use_coroutine_handle::<Api>();
+    use_hook(|| {
+        *NEW_TOKEN.write() = None;
+        api.send(Api::ApiTokens)
+    });
+
+    let mut name = use_signal(String::new);
+    let mut scopes = use_signal(Vec::<Scope>::new);
+
+    rsx!(article { class:"flex flex-col p-3 space-y-6 w-full",
+        div { class: "alert alert-error",
+            role: "alert",
+            hidden: !matches!(ALERT_MSG.read().typ, Some(AlertType::Error)),
+            span { {ALERT_MSG.read().msg.as_str()} }
+            button { class: "btn btn-ghost btn-xs",
+                onclick: |_| *ALERT_MSG.write() = Default::default(),
+                "关闭"
+            }
+        }
+        section { class: "flex flex-col space-y-3 max-w-xl",
+            h1 { class: "text-lg font-medium", "创建访问令牌" }
+            p { class: "text-sm text-gray-500",
+                "脚本调用接口时在请求头中携带 Authorization: Bearer <令牌>，令牌只能调用所选范围内的接口。"
+            }
+            input { class: "input input-bordered w-full",
+                r#type: "text",
+                placeholder: "令牌名称，如：批量解数独",
+                value: "{name}",
+                onchange: move |evt| name.set(evt.value()),
+            }
+            FieldHint { field: "name" }
+            div { class: "flex flex-row space-x-6",
+                for scope in Scope::ALL {
+                    label { class: "label cursor-pointer space-x-2",
+                        input { class: "checkbox",
+                            r#type: "checkbox",
+                            checked: scopes.read().contains(&scope),
+                            onchange: move |evt| {
+                                if evt.checked() {
+                                    scopes.write().push(scope);
+                                } else {
+                                    scopes.write().retain(|s| *s != scope);
+                                }
+                            },
+                        }
+                        span { class: "label-text", {scope_label(scope)} }
+                    }
+                }
+            }
+            button { class: "btn btn-outline",
+                disabled: name().trim().is_empty() || scopes.read().is_empty(),
+                onclick: move |_| {
+                    api.send(Api::CreateToken(CreateTokenReq {
+                        name: name(),
+                        scopes: scopes(),
+                    }));
+                    name.set(String::new());
+                    scopes.set(Vec::new());
+                },
+                "创建令牌"
+            }
+            if let Some(token) = NEW_TOKEN() {
+                div { class: "alert alert-success flex flex-col items-start",
+                    span { "令牌只展示这一次，请立即复制保存：" }
+                    code { class: "font-mono text-sm break-all select-all", "{token}" }
+                }
+            }
+        }
+        section { class: "flex flex-col space-y-3",
+            h1 { class: "text-lg font-medium", "我的访问令牌" }
+            table { class: "table",
+                thead {
+                    tr {
+                        th { "名称" }
+                        th { "权限范围" }
+                        th { "创建时间" }
+                        th { "最近使用" }
+                        th {}
+                    }
+                }
+                tbody {
+                    for token in TOKENS.read().iter() {
+                        tr { key: "{token.id}",
+                            td { "{token.name}" }
+                            td {
+                                for scope in token.scopes.iter() {
+                                    span { class: "badge badge-outline mr-1", {scope_label(*scope)} }
+                                }
+                            }
+                            td { "{token.created_at}" }
+                            td { {token.last_used.as_deref().unwrap_or("从未使用")} }
+                            td {
+                                button { class: "btn btn-ghost btn-xs",
+                                    onclick: {
+                                        let id = token.id.clone();
+                                        move |_| api.send(Api::RevokeToken(id.clone()))
+                                    },
+                                    "撤销"
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    })
+}